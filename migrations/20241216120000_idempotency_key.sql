@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE
+    idempotency_key (
+        key VARCHAR(255) PRIMARY KEY,
+        fingerprint VARCHAR(64) NOT NULL,
+        -- Only null while the request that claimed the key is still running
+        response JSONB,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            expires_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL
+    );
@@ -0,0 +1,27 @@
+-- Add migration script here
+CREATE TABLE
+    transfer (
+        id UUID PRIMARY KEY,
+        origin_account_id UUID NOT NULL REFERENCES account (id),
+        destination_account_id UUID NOT NULL REFERENCES account (id),
+        amount BIGINT NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW ()
+    );
+
+ALTER TABLE transaction
+ADD COLUMN transfer_id UUID REFERENCES transfer (id),
+ADD COLUMN counterparty_account_id UUID REFERENCES account (id);
+
+-- Transfers booked before this migration are plain withdraw/deposit pairs and are left as they
+-- are, NOT VALID only enforces the link on new rows
+ALTER TABLE transaction
+ADD CONSTRAINT transaction_transfer_link CHECK (
+    (type IN ('transfer_in', 'transfer_out')) = (
+        transfer_id IS NOT NULL
+        AND counterparty_account_id IS NOT NULL
+    )
+) NOT VALID;
+
+CREATE UNIQUE INDEX transaction_transfer_leg ON transaction (transfer_id, type);
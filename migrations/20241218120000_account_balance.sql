@@ -0,0 +1,17 @@
+-- Add migration script here
+ALTER TABLE account
+ADD COLUMN balance BIGINT NOT NULL DEFAULT 0;
+
+UPDATE account
+SET
+    balance = COALESCE(
+        (
+            SELECT
+                SUM(amount)
+            FROM
+                transaction
+            WHERE
+                transaction.account_id = account.id
+        ),
+        0
+    );
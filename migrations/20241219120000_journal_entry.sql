@@ -0,0 +1,130 @@
+-- Add migration script here
+ALTER TABLE account
+ADD COLUMN system_code VARCHAR(64) UNIQUE;
+
+-- Internal accounts balancing customer movements, negative numbers keep them out of the range
+-- used by customer accounts
+INSERT INTO
+    account (id, number, system_code)
+VALUES
+    (gen_random_uuid (), -1, 'cash_vault'),
+    (gen_random_uuid (), -2, 'fee_income'),
+    (gen_random_uuid (), -3, 'suspense');
+
+CREATE TABLE
+    journal_entry (
+        id UUID PRIMARY KEY,
+        description VARCHAR(255) NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW ()
+    );
+
+ALTER TABLE transaction
+ADD COLUMN journal_entry_id UUID REFERENCES journal_entry (id);
+
+-- Transfer legs already balance each other, so each transfer becomes one entry
+INSERT INTO
+    journal_entry (id, description, created_at)
+SELECT
+    id,
+    'transfer',
+    created_at
+FROM
+    transfer;
+
+UPDATE transaction
+SET
+    journal_entry_id = transfer_id
+WHERE
+    transfer_id IS NOT NULL;
+
+-- Every other movement was booked on a single side, so it is balanced against the suspense
+-- account until someone reconciles it
+CREATE TEMPORARY TABLE legacy_entry AS
+SELECT
+    id AS transaction_id,
+    gen_random_uuid () AS journal_entry_id
+FROM
+    transaction
+WHERE
+    journal_entry_id IS NULL;
+
+INSERT INTO
+    journal_entry (id, description, created_at)
+SELECT
+    legacy_entry.journal_entry_id,
+    'legacy ' || COALESCE(transaction.type, 'movement'),
+    transaction.created_at
+FROM
+    legacy_entry
+    JOIN transaction ON transaction.id = legacy_entry.transaction_id;
+
+UPDATE transaction
+SET
+    journal_entry_id = legacy_entry.journal_entry_id
+FROM
+    legacy_entry
+WHERE
+    transaction.id = legacy_entry.transaction_id;
+
+INSERT INTO
+    transaction (
+        account_id,
+        amount,
+        type,
+        created_at,
+        journal_entry_id
+    )
+SELECT
+    suspense.id,
+    - transaction.amount,
+    transaction.type,
+    transaction.created_at,
+    transaction.journal_entry_id
+FROM
+    legacy_entry
+    JOIN transaction ON transaction.id = legacy_entry.transaction_id
+    CROSS JOIN account AS suspense
+WHERE
+    suspense.system_code = 'suspense';
+
+DROP TABLE legacy_entry;
+
+UPDATE account
+SET
+    balance = COALESCE(
+        (
+            SELECT
+                SUM(amount)
+            FROM
+                transaction
+            WHERE
+                transaction.account_id = account.id
+        ),
+        0
+    )
+WHERE
+    system_code = 'suspense';
+
+ALTER TABLE transaction
+ALTER COLUMN journal_entry_id
+SET NOT NULL;
+
+-- Checked at commit, so all the postings of an entry can be inserted before it is verified
+CREATE FUNCTION check_journal_entry_balanced () RETURNS TRIGGER AS $$
+BEGIN
+    IF (SELECT SUM(amount) FROM transaction WHERE journal_entry_id = NEW.journal_entry_id) <> 0 THEN
+        RAISE EXCEPTION 'Journal entry % does not sum to zero', NEW.journal_entry_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE CONSTRAINT TRIGGER journal_entry_balanced
+AFTER INSERT
+OR
+UPDATE ON transaction DEFERRABLE INITIALLY DEFERRED FOR EACH ROW
+EXECUTE FUNCTION check_journal_entry_balanced ();
+
+CREATE INDEX transaction_journal_entry ON transaction (journal_entry_id);
@@ -0,0 +1,31 @@
+-- Add migration script here
+CREATE TABLE
+    reversal (
+        id UUID PRIMARY KEY,
+        original_journal_entry_id UUID NOT NULL REFERENCES journal_entry (id),
+        journal_entry_id UUID NOT NULL UNIQUE REFERENCES journal_entry (id),
+        amount BIGINT NOT NULL CHECK (amount > 0),
+        reason VARCHAR(255) NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW ()
+    );
+
+CREATE INDEX reversal_original_journal_entry ON reversal (original_journal_entry_id);
+
+-- Booked movements are never edited, mistakes are fixed with compensating entries
+CREATE FUNCTION reject_ledger_change () RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION '% rows are immutable, book a reversal instead', TG_TABLE_NAME;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transaction_immutable BEFORE
+UPDATE
+OR DELETE ON transaction FOR EACH ROW
+EXECUTE FUNCTION reject_ledger_change ();
+
+CREATE TRIGGER journal_entry_immutable BEFORE
+UPDATE
+OR DELETE ON journal_entry FOR EACH ROW
+EXECUTE FUNCTION reject_ledger_change ();
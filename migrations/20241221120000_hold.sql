@@ -0,0 +1,29 @@
+-- Add migration script here
+INSERT INTO
+    account (id, number, system_code)
+VALUES
+    (gen_random_uuid (), -4, 'settlement');
+
+CREATE TABLE
+    hold (
+        id UUID PRIMARY KEY,
+        account_id UUID NOT NULL REFERENCES account (id),
+        amount BIGINT NOT NULL CHECK (amount > 0),
+        -- active, captured, released or expired
+        status VARCHAR(16) NOT NULL DEFAULT 'active',
+        captured_amount BIGINT,
+        journal_entry_id UUID REFERENCES journal_entry (id),
+        expires_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            closed_at TIMESTAMP
+        WITH
+            TIME ZONE
+    );
+
+CREATE INDEX hold_active ON hold (account_id)
+WHERE
+    status = 'active';
@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE account ADD COLUMN overdraft_limit BIGINT NOT NULL DEFAULT 0 CONSTRAINT account_overdraft_limit_positive CHECK (overdraft_limit >= 0);
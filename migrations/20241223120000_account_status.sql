@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE account
+ADD COLUMN status VARCHAR(16) NOT NULL DEFAULT 'active' CONSTRAINT account_status_valid CHECK (status IN ('active', 'frozen', 'closed'));
+
+CREATE TABLE
+    account_status_change (
+        id UUID PRIMARY KEY,
+        account_id UUID NOT NULL REFERENCES account (id),
+        from_status VARCHAR(16) NOT NULL,
+        to_status VARCHAR(16) NOT NULL,
+        reason VARCHAR(255) NOT NULL,
+        -- Set when closing moved the remaining balance to another account
+        sweep_journal_entry_id UUID REFERENCES journal_entry (id),
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW ()
+    );
+
+CREATE INDEX account_status_change_account ON account_status_change (account_id);
@@ -0,0 +1,26 @@
+-- Add migration script here
+CREATE TABLE
+    customer (
+        id UUID PRIMARY KEY,
+        name VARCHAR(255) NOT NULL,
+        -- CPF or CNPJ, digits only
+        document_number VARCHAR(14) UNIQUE NOT NULL,
+        email VARCHAR(255) NOT NULL,
+        date_of_birth DATE NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW ()
+    );
+
+-- Accounts may have several holders, and customers several accounts
+CREATE TABLE
+    account_holder (
+        account_id UUID NOT NULL REFERENCES account (id),
+        customer_id UUID NOT NULL REFERENCES customer (id),
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            PRIMARY KEY (account_id, customer_id)
+    );
+
+CREATE INDEX account_holder_customer ON account_holder (customer_id);
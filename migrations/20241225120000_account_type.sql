@@ -0,0 +1,13 @@
+-- Add migration script here
+ALTER TABLE account
+ADD COLUMN type VARCHAR(16) NOT NULL DEFAULT 'checking' CONSTRAINT account_type_valid CHECK (type IN ('checking', 'savings', 'system'));
+
+UPDATE account
+SET
+    type = 'system'
+WHERE
+    system_code IS NOT NULL;
+
+-- Only the seeded system accounts are internal
+ALTER TABLE account
+ADD CONSTRAINT account_system_type CHECK ((type = 'system') = (system_code IS NOT NULL));
@@ -0,0 +1,37 @@
+-- Add migration script here
+INSERT INTO
+    account (id, number, system_code, type)
+VALUES
+    (gen_random_uuid (), -5, 'interest_expense', 'system');
+
+CREATE TABLE
+    interest_accrual (
+        account_id UUID NOT NULL REFERENCES account (id),
+        accrual_date DATE NOT NULL,
+        -- End of day balance the interest was computed on
+        balance BIGINT NOT NULL,
+        annual_rate NUMERIC NOT NULL,
+        day_count VARCHAR(16) NOT NULL,
+        amount NUMERIC NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            PRIMARY KEY (account_id, accrual_date)
+    );
+
+CREATE TABLE
+    interest_capitalization (
+        id UUID PRIMARY KEY,
+        account_id UUID NOT NULL REFERENCES account (id),
+        -- First day of the capitalized month
+        month DATE NOT NULL,
+        accrued NUMERIC NOT NULL,
+        -- Whole amount credited, the fraction left is carried to the next month
+        amount BIGINT NOT NULL,
+        carry_over NUMERIC NOT NULL,
+        journal_entry_id UUID REFERENCES journal_entry (id),
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            UNIQUE (account_id, month)
+    );
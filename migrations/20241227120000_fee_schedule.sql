@@ -0,0 +1,32 @@
+-- Add migration script here
+-- Monthly fees are only charged for the months an account existed
+ALTER TABLE account
+ADD COLUMN created_at TIMESTAMP
+WITH
+    TIME ZONE DEFAULT NOW ();
+
+CREATE TABLE
+    fee_schedule (
+        account_type VARCHAR(16) NOT NULL,
+        -- withdraw, transfer, monthly_maintenance or overdraft_usage
+        event VARCHAR(32) NOT NULL,
+        rule JSONB NOT NULL,
+        min_fee BIGINT,
+        max_fee BIGINT,
+        updated_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            PRIMARY KEY (account_type, event)
+    );
+
+CREATE TABLE
+    maintenance_fee (
+        account_id UUID NOT NULL REFERENCES account (id),
+        -- First day of the charged month
+        month DATE NOT NULL,
+        journal_entry_id UUID NOT NULL REFERENCES journal_entry (id),
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            PRIMARY KEY (account_id, month)
+    );
@@ -0,0 +1,65 @@
+-- Add migration script here
+CREATE TABLE
+    scheduled_transaction (
+        id UUID PRIMARY KEY,
+        -- deposit, withdraw or transfer
+        transaction_type VARCHAR(16) NOT NULL,
+        amount BIGINT NOT NULL CHECK (amount > 0),
+        origin_account_id UUID REFERENCES account (id),
+        destination_account_id UUID REFERENCES account (id),
+        recurrence JSONB NOT NULL,
+        -- Occurrence being executed next, not set once the schedule is over
+        next_run_at TIMESTAMP
+        WITH
+            TIME ZONE,
+            -- Same as next_run_at, unless a failed attempt is waiting to be retried
+            next_attempt_at TIMESTAMP
+        WITH
+            TIME ZONE,
+            -- Failed attempts of the next occurrence
+            attempts INT NOT NULL DEFAULT 0,
+            ends_at TIMESTAMP
+        WITH
+            TIME ZONE,
+            -- active, completed, cancelled or failed
+            status VARCHAR(16) NOT NULL DEFAULT 'active' CHECK (
+                status IN ('active', 'completed', 'cancelled', 'failed')
+            ),
+            created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            updated_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            CHECK (
+                (status = 'active') = (next_attempt_at IS NOT NULL)
+            )
+    );
+
+CREATE INDEX scheduled_transaction_due ON scheduled_transaction (next_attempt_at)
+WHERE
+    status = 'active';
+
+CREATE TABLE
+    scheduled_transaction_execution (
+        id UUID PRIMARY KEY,
+        scheduled_transaction_id UUID NOT NULL REFERENCES scheduled_transaction (id),
+        -- Occurrence the attempt was made for
+        run_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            attempt INT NOT NULL,
+            -- succeeded or failed
+            status VARCHAR(16) NOT NULL,
+            journal_entry_id UUID REFERENCES journal_entry (id),
+            error TEXT,
+            executed_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            UNIQUE (scheduled_transaction_id, run_at, attempt)
+    );
+
+-- An occurrence is never booked twice
+CREATE UNIQUE INDEX scheduled_transaction_execution_once ON scheduled_transaction_execution (scheduled_transaction_id, run_at)
+WHERE
+    status = 'succeeded';
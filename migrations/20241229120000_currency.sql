@@ -0,0 +1,35 @@
+-- Add migration script here
+ALTER TABLE account
+ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'BRL' CONSTRAINT account_currency_valid CHECK (
+    currency IN ('BRL', 'USD', 'EUR', 'GBP', 'CHF', 'JPY', 'KWD')
+);
+
+-- Every currency has its own set of system accounts, so a journal entry never mixes currencies
+-- on the same account
+ALTER TABLE account
+DROP CONSTRAINT account_system_code_key;
+
+ALTER TABLE account
+ADD CONSTRAINT account_system_code_currency_key UNIQUE (system_code, currency);
+
+INSERT INTO
+    account (id, number, system_code, type, currency)
+SELECT
+    gen_random_uuid (),
+    system.number - 100 * currency.position,
+    system.system_code,
+    'system',
+    currency.code
+FROM
+    account AS system
+    CROSS JOIN (
+        VALUES
+            ('USD', 1),
+            ('EUR', 2),
+            ('GBP', 3),
+            ('CHF', 4),
+            ('JPY', 5),
+            ('KWD', 6)
+    ) AS currency (code, position)
+WHERE
+    system.type = 'system';
@@ -0,0 +1,81 @@
+-- Add migration script here
+-- Conversions book each leg in its own currency against the FX position of that currency, the
+-- difference with the market rate goes to the FX gain/loss account
+INSERT INTO
+    account (id, number, system_code, type, currency)
+SELECT
+    gen_random_uuid (),
+    system.number - 100 * currency.position,
+    system.system_code,
+    'system',
+    currency.code
+FROM
+    (
+        VALUES
+            (-6, 'fx_position'),
+            (-7, 'fx_gain_loss')
+    ) AS system (number, system_code)
+    CROSS JOIN (
+        VALUES
+            ('BRL', 0),
+            ('USD', 1),
+            ('EUR', 2),
+            ('GBP', 3),
+            ('CHF', 4),
+            ('JPY', 5),
+            ('KWD', 6)
+    ) AS currency (code, position);
+
+-- A conversion entry mixes currencies, so it must balance per currency instead of in total
+CREATE OR REPLACE FUNCTION check_journal_entry_balanced () RETURNS TRIGGER AS $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM transaction
+        JOIN account ON account.id = transaction.account_id
+        WHERE transaction.journal_entry_id = NEW.journal_entry_id
+        GROUP BY account.currency
+        HAVING SUM(transaction.amount) <> 0
+    ) THEN
+        RAISE EXCEPTION 'Journal entry % does not sum to zero', NEW.journal_entry_id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TABLE
+    fx_rate (
+        base_currency VARCHAR(3) NOT NULL,
+        quote_currency VARCHAR(3) NOT NULL,
+        -- Units of the quote currency bought by one unit of the base currency
+        rate NUMERIC NOT NULL CHECK (rate > 0),
+        effective_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW (),
+            PRIMARY KEY (base_currency, quote_currency, effective_at),
+            CHECK (base_currency <> quote_currency)
+    );
+
+CREATE TABLE
+    fx_quote (
+        id UUID PRIMARY KEY,
+        from_currency VARCHAR(3) NOT NULL,
+        to_currency VARCHAR(3) NOT NULL,
+        -- Minor units of from_currency
+        amount BIGINT NOT NULL CHECK (amount > 0),
+        -- Minor units of to_currency the customer receives
+        converted_amount BIGINT NOT NULL,
+        -- Market rate minus the spread
+        rate NUMERIC NOT NULL,
+        market_rate NUMERIC NOT NULL,
+        expires_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            -- Set by the transfer executing the quote, a quote is used once
+            journal_entry_id UUID REFERENCES journal_entry (id),
+            created_at TIMESTAMP
+        WITH
+            TIME ZONE DEFAULT NOW ()
+    );
@@ -0,0 +1,48 @@
+-- Add migration script here
+-- Customer account numbers come from a sequence instead of the highest number plus one, which
+-- raced between concurrent openings
+CREATE SEQUENCE account_number_seq MINVALUE 1 MAXVALUE 99999999 NO CYCLE;
+
+-- Existing accounts get numbers in the new layout, branch 0001, the sequence spread over 8
+-- digits and a modulo 11 check digit, see `account_number` in the account domain
+WITH
+    numbered AS (
+        SELECT
+            id,
+            100000000 + (ROW_NUMBER() OVER (ORDER BY number) * 48271) % 100000000 AS base
+        FROM
+            account
+        WHERE
+            type <> 'system'
+    ),
+    weighted AS (
+        SELECT
+            numbered.id,
+            numbered.base,
+            SUM(
+                (numbered.base / (10::BIGINT ^ (11 - position))::BIGINT % 10) * (ARRAY[5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2])[position + 1]
+            ) % 11 AS remainder
+        FROM
+            numbered
+            CROSS JOIN generate_series(0, 11) AS position
+        GROUP BY
+            numbered.id,
+            numbered.base
+    )
+UPDATE account
+SET
+    number = weighted.base * 10 + CASE
+        WHEN weighted.remainder < 2 THEN 0
+        ELSE 11 - weighted.remainder
+    END
+FROM
+    weighted
+WHERE
+    account.id = weighted.id;
+
+SELECT
+    setval('account_number_seq', GREATEST(COUNT(*), 1), COUNT(*) > 0)
+FROM
+    account
+WHERE
+    type <> 'system';
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Last day the interest job accrued, so the days it missed are caught up on its next run
+CREATE TABLE
+    interest_accrual_run (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        accrued_through DATE NOT NULL
+    );
@@ -0,0 +1,16 @@
+-- Add migration script here
+-- Fees are minor units, so each currency has its own schedules. The existing ones were set
+-- before accounts could be opened in other currencies, so they are in BRL.
+ALTER TABLE fee_schedule
+ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'BRL' CONSTRAINT fee_schedule_currency_valid CHECK (
+    currency IN ('BRL', 'USD', 'EUR', 'GBP', 'CHF', 'JPY', 'KWD')
+);
+
+ALTER TABLE fee_schedule
+ALTER COLUMN currency
+DROP DEFAULT;
+
+ALTER TABLE fee_schedule
+DROP CONSTRAINT fee_schedule_pkey;
+
+ALTER TABLE fee_schedule ADD PRIMARY KEY (account_type, event, currency);
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- System accounts take part in nearly every journal entry, so updating a balance on their row
+-- made every entry in a currency wait for the one before it to commit. Their balance is summed
+-- from their postings instead, and the stored one is no longer kept.
+UPDATE account
+SET
+    balance = 0
+WHERE
+    type = 'system';
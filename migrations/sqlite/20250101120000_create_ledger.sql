@@ -0,0 +1,162 @@
+-- Same tables as the Postgres migrations, for the accounts and the ledger only. Ids are stored as
+-- 16 byte blobs and timestamps as microseconds since the epoch, so they sort like in Postgres
+CREATE TABLE
+    customer (
+        id BLOB PRIMARY KEY,
+        name VARCHAR(255) NOT NULL,
+        -- CPF or CNPJ, digits only
+        document_number VARCHAR(14) UNIQUE NOT NULL,
+        email VARCHAR(255) NOT NULL,
+        date_of_birth DATE NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+CREATE TABLE
+    account (
+        id BLOB PRIMARY KEY,
+        number BIGINT UNIQUE NOT NULL,
+        balance BIGINT NOT NULL DEFAULT 0,
+        system_code VARCHAR(64),
+        overdraft_limit BIGINT NOT NULL DEFAULT 0 CONSTRAINT account_overdraft_limit_positive CHECK (overdraft_limit >= 0),
+        status VARCHAR(16) NOT NULL DEFAULT 'active' CONSTRAINT account_status_valid CHECK (status IN ('active', 'frozen', 'closed')),
+        type VARCHAR(16) NOT NULL DEFAULT 'checking' CONSTRAINT account_type_valid CHECK (type IN ('checking', 'savings', 'system')),
+        currency VARCHAR(3) NOT NULL DEFAULT 'BRL' CONSTRAINT account_currency_valid CHECK (
+            currency IN ('BRL', 'USD', 'EUR', 'GBP', 'CHF', 'JPY', 'KWD')
+        ),
+        CONSTRAINT account_system_type CHECK ((type = 'system') = (system_code IS NOT NULL)),
+        CONSTRAINT account_system_code_currency_key UNIQUE (system_code, currency)
+    );
+
+-- Accounts may have several holders, and customers several accounts
+CREATE TABLE
+    account_holder (
+        account_id BLOB NOT NULL REFERENCES account (id),
+        customer_id BLOB NOT NULL REFERENCES customer (id),
+        PRIMARY KEY (account_id, customer_id)
+    );
+
+CREATE INDEX account_holder_customer ON account_holder (customer_id);
+
+-- Stands for `account_number_seq`, handing out each value once
+CREATE TABLE
+    account_number_seq (
+        value BIGINT NOT NULL CHECK (value BETWEEN 0 AND 99999999)
+    );
+
+INSERT INTO
+    account_number_seq (value)
+VALUES
+    (0);
+
+-- Internal accounts balancing customer movements, with the numbers the Postgres migrations gave
+-- them, `-1` to `-7` in BRL and 100 less for each following currency
+WITH
+    system (number, system_code) AS (
+        VALUES
+            (-1, 'cash_vault'),
+            (-2, 'fee_income'),
+            (-3, 'suspense'),
+            (-4, 'settlement'),
+            (-5, 'interest_expense'),
+            (-6, 'fx_position'),
+            (-7, 'fx_gain_loss')
+    ),
+    currency (code, position) AS (
+        VALUES
+            ('BRL', 0),
+            ('USD', 1),
+            ('EUR', 2),
+            ('GBP', 3),
+            ('CHF', 4),
+            ('JPY', 5),
+            ('KWD', 6)
+    )
+INSERT INTO
+    account (id, number, system_code, type, currency)
+SELECT
+    randomblob (16),
+    system.number - 100 * currency.position,
+    system.system_code,
+    'system',
+    currency.code
+FROM
+    system
+    CROSS JOIN currency;
+
+CREATE TABLE
+    account_status_change (
+        id BLOB PRIMARY KEY,
+        account_id BLOB NOT NULL REFERENCES account (id),
+        from_status VARCHAR(16) NOT NULL,
+        to_status VARCHAR(16) NOT NULL,
+        reason VARCHAR(255) NOT NULL,
+        sweep_journal_entry_id BLOB REFERENCES journal_entry (id),
+        created_at INTEGER NOT NULL
+    );
+
+CREATE INDEX account_status_change_account ON account_status_change (account_id);
+
+CREATE TABLE
+    journal_entry (
+        id BLOB PRIMARY KEY,
+        description VARCHAR(255) NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+CREATE TABLE
+    transfer (
+        id BLOB PRIMARY KEY,
+        origin_account_id BLOB NOT NULL REFERENCES account (id),
+        destination_account_id BLOB NOT NULL REFERENCES account (id),
+        amount BIGINT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+
+CREATE TABLE
+    "transaction" (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        account_id BLOB NOT NULL REFERENCES account (id),
+        amount BIGINT NOT NULL,
+        type VARCHAR(255) NOT NULL,
+        transfer_id BLOB REFERENCES transfer (id),
+        counterparty_account_id BLOB REFERENCES account (id),
+        journal_entry_id BLOB NOT NULL REFERENCES journal_entry (id),
+        created_at INTEGER NOT NULL,
+        CONSTRAINT transaction_transfer_link CHECK (
+            (type IN ('transfer_in', 'transfer_out')) = (
+                transfer_id IS NOT NULL
+                AND counterparty_account_id IS NOT NULL
+            )
+        )
+    );
+
+CREATE UNIQUE INDEX transaction_transfer_leg ON "transaction" (transfer_id, type);
+
+CREATE INDEX transaction_journal_entry ON "transaction" (journal_entry_id);
+
+CREATE INDEX transaction_account ON "transaction" (account_id, created_at, id);
+
+CREATE TABLE
+    fee_schedule (
+        account_type VARCHAR(16) NOT NULL,
+        -- withdraw, transfer, monthly_maintenance or overdraft_usage
+        event VARCHAR(32) NOT NULL,
+        rule TEXT NOT NULL,
+        min_fee BIGINT,
+        max_fee BIGINT,
+        PRIMARY KEY (account_type, event)
+    );
+
+-- SQLite has no row locks, every unit of work writes this row first so it holds the write lock
+-- of the database until it ends, which serializes them the way the row locks serialize the
+-- movements of an account on Postgres
+CREATE TABLE
+    unit_of_work_lock (
+        id INTEGER PRIMARY KEY CHECK (id = 1),
+        acquired_at INTEGER NOT NULL
+    );
+
+INSERT INTO
+    unit_of_work_lock (id, acquired_at)
+VALUES
+    (1, 0);
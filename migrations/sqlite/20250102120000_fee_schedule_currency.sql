@@ -0,0 +1,31 @@
+-- Same as the Postgres migration, SQLite can't change a primary key so the table is rebuilt
+CREATE TABLE
+    fee_schedule_by_currency (
+        account_type VARCHAR(16) NOT NULL,
+        -- withdraw, transfer, monthly_maintenance or overdraft_usage
+        event VARCHAR(32) NOT NULL,
+        currency VARCHAR(3) NOT NULL CONSTRAINT fee_schedule_currency_valid CHECK (
+            currency IN ('BRL', 'USD', 'EUR', 'GBP', 'CHF', 'JPY', 'KWD')
+        ),
+        rule TEXT NOT NULL,
+        min_fee BIGINT,
+        max_fee BIGINT,
+        PRIMARY KEY (account_type, event, currency)
+    );
+
+INSERT INTO
+    fee_schedule_by_currency (account_type, event, currency, rule, min_fee, max_fee)
+SELECT
+    account_type,
+    event,
+    'BRL',
+    rule,
+    min_fee,
+    max_fee
+FROM
+    fee_schedule;
+
+DROP TABLE fee_schedule;
+
+ALTER TABLE fee_schedule_by_currency
+RENAME TO fee_schedule;
@@ -0,0 +1,6 @@
+-- Same as the Postgres migration, the balance of system accounts is summed from their postings
+UPDATE account
+SET
+    balance = 0
+WHERE
+    type = 'system';
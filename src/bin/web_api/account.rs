@@ -5,58 +5,273 @@ use axum::{
     http::StatusCode,
     Json,
 };
-use bank_case::internal::account::{account::AccountManager, domain::Account};
-use bigdecimal::BigDecimal;
+use bank_case::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, AccountLimits, AccountStatusChange, AccountType},
+    },
+    error::ErrorKind,
+    money::domain::{Currency, Money, DEFAULT_CURRENCY},
+};
+use uuid::Uuid;
 
-use crate::AppState;
+use crate::{
+    error::ApiError,
+    transaction::{find_account, AccountIdentifier},
+    AppState,
+};
 
 pub async fn create_account_controller(
     State(state): State<Arc<AppState>>,
-) -> Result<(StatusCode, Json<Account>), (StatusCode, String)> {
+    Json(account): Json<CreateAccountDto>,
+) -> Result<(StatusCode, Json<AccountResponse>), ApiError> {
     let account_manager = AccountManager::new(&state.pg_pool);
 
-    match account_manager.create_account().await {
-        Ok(account) => Ok((StatusCode::CREATED, Json(account))),
-        Err(e) => Err((e.status().clone(), e.message().to_string())),
+    let account_type = account.account_type.unwrap_or(AccountType::Checking);
+
+    let currency = account.currency.unwrap_or(DEFAULT_CURRENCY);
+
+    match account_manager
+        .create_account_with_currency(account_type, currency, &account.holders)
+        .await
+    {
+        Ok(account) => Ok((
+            StatusCode::CREATED,
+            Json(AccountResponse::new(&account_manager, account)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct AccountResponse {
+    #[serde(flatten)]
+    account: Account,
+    iban: String,
+}
+
+impl AccountResponse {
+    pub fn new(account_manager: &AccountManager<'_>, account: Account) -> Self {
+        Self::with_iban(account_manager.iban(&account), account)
     }
+
+    pub fn with_iban(iban: String, account: Account) -> Self {
+        Self { account, iban }
+    }
+
+    pub fn list(account_manager: &AccountManager<'_>, accounts: Vec<Account>) -> Vec<Self> {
+        accounts
+            .into_iter()
+            .map(|account| AccountResponse::new(account_manager, account))
+            .collect()
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct CreateAccountDto {
+    /// Checking when not given
+    #[serde(rename = "type")]
+    pub account_type: Option<AccountType>,
+    /// Customers owning the account
+    pub holders: Vec<Uuid>,
+    /// `DEFAULT_CURRENCY` when not given
+    pub currency: Option<Currency>,
 }
 
 pub async fn list_accounts_controller(
     State(state): State<Arc<AppState>>,
-) -> Result<(StatusCode, Json<Vec<Account>>), (StatusCode, String)> {
+) -> Result<(StatusCode, Json<Vec<AccountResponse>>), ApiError> {
     let account_manager = AccountManager::new(&state.pg_pool);
 
     match account_manager.list_accounts().await {
-        Ok(accounts) => Ok((StatusCode::OK, Json(accounts))),
-        Err(e) => Err((e.status().clone(), e.message().to_string())),
+        Ok(accounts) => Ok((
+            StatusCode::OK,
+            Json(AccountResponse::list(&account_manager, accounts)),
+        )),
+        Err(e) => Err(e.into()),
     }
 }
 
 #[axum::debug_handler]
 pub async fn get_balance(
     State(state): State<Arc<AppState>>,
-    Path(account_number): Path<u32>,
-) -> Result<(StatusCode, Json<GetBalanceResponse>), (StatusCode, String)> {
-    let mut pg_pool = state.pg_pool.clone().acquire().await.unwrap();
+    Path(account_number): Path<i64>,
+) -> Result<(StatusCode, Json<GetBalanceResponse>), ApiError> {
     let account_manager = AccountManager::new(&state.pg_pool);
 
     let account = match account_manager
-        .get_account_from_number(account_number.into())
+        .get_account_from_number(account_number)
         .await
     {
-        Err(e) => return Err((e.status().clone(), e.message().to_string())),
+        Err(e) => return Err(e.into()),
         Ok(account) => account,
     };
 
-    let balance = AccountManager::get_balance(&account, &mut pg_pool).await;
+    let mut conn = state
+        .pg_pool
+        .acquire()
+        .await
+        .map_err(|e| ApiError::new(ErrorKind::Internal, e.to_string()))?;
+
+    let balance = AccountManager::get_balance(&account, &mut *conn).await;
+    let available_balance = AccountManager::get_available_balance(&account, &mut *conn).await;
 
-    match balance {
-        Ok(balance) => Ok((StatusCode::OK, Json(GetBalanceResponse { balance }))),
-        Err(_) => Err((StatusCode::INTERNAL_SERVER_ERROR, "".to_string())),
+    match (balance, available_balance) {
+        (Ok(balance), Ok(available_balance)) => Ok((
+            StatusCode::OK,
+            Json(GetBalanceResponse {
+                balance,
+                available_balance,
+            }),
+        )),
+        (Err(e), _) | (_, Err(e)) => Err(e.into()),
     }
 }
 
 #[derive(serde::Serialize)]
 pub struct GetBalanceResponse {
-    balance: BigDecimal,
+    /// In the currency of the account
+    pub balance: Money,
+    /// Balance minus the active holds
+    pub available_balance: Money,
+}
+
+#[axum::debug_handler]
+pub async fn update_limits(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Json(limits): Json<UpdateLimitsDto>,
+) -> Result<(StatusCode, Json<AccountLimitsResponse>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Err(e) => return Err(e.into()),
+        Ok(account) => account,
+    };
+
+    let currency = *account.currency();
+    let overdraft_limit = match Money::parse(&limits.overdraft_limit, currency) {
+        Ok(overdraft_limit) => overdraft_limit,
+        Err(e) => return Err(ApiError::from_error(&e)),
+    };
+
+    let limits = AccountLimits {
+        overdraft_limit: overdraft_limit.amount_minor,
+    };
+
+    match account_manager.update_limits(&account, &limits).await {
+        Ok(limits) => Ok((
+            StatusCode::OK,
+            Json(AccountLimitsResponse {
+                overdraft_limit: Money::new(limits.overdraft_limit, currency),
+            }),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct UpdateLimitsDto {
+    /// Decimal string in the currency of the account
+    pub overdraft_limit: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct AccountLimitsResponse {
+    pub overdraft_limit: Money,
+}
+
+#[axum::debug_handler]
+pub async fn freeze_account(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Json(status_change): Json<StatusChangeDto>,
+) -> Result<(StatusCode, Json<AccountStatusChange>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Err(e) => return Err(e.into()),
+        Ok(account) => account,
+    };
+
+    match account_manager
+        .freeze(&account, &status_change.reason)
+        .await
+    {
+        Ok(change) => Ok((StatusCode::OK, Json(change))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn unfreeze_account(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Json(status_change): Json<StatusChangeDto>,
+) -> Result<(StatusCode, Json<AccountStatusChange>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Err(e) => return Err(e.into()),
+        Ok(account) => account,
+    };
+
+    match account_manager
+        .unfreeze(&account, &status_change.reason)
+        .await
+    {
+        Ok(change) => Ok((StatusCode::OK, Json(change))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn close_account(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Json(close): Json<CloseAccountDto>,
+) -> Result<(StatusCode, Json<AccountStatusChange>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Err(e) => return Err(e.into()),
+        Ok(account) => account,
+    };
+
+    let sweep_to = match close.sweep_to {
+        None => None,
+        Some(sweep_to) => Some(find_account(&account_manager, &sweep_to).await?),
+    };
+
+    match account_manager
+        .close(&account, sweep_to.as_ref(), &close.reason)
+        .await
+    {
+        Ok(change) => Ok((StatusCode::OK, Json(change))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct StatusChangeDto {
+    pub reason: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct CloseAccountDto {
+    reason: String,
+    /// Account receiving the remaining balance
+    sweep_to: Option<AccountIdentifier>,
 }
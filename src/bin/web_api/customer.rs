@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bank_case::internal::{
+    account::account::AccountManager,
+    customer::{
+        customer::CustomerManager,
+        domain::{Customer, NewCustomer},
+    },
+};
+use uuid::Uuid;
+
+use crate::{account::AccountResponse, error::ApiError, AppState};
+
+#[axum::debug_handler]
+pub async fn create_customer(
+    State(state): State<Arc<AppState>>,
+    Json(customer): Json<NewCustomer>,
+) -> Result<(StatusCode, Json<Customer>), ApiError> {
+    let customer_manager = CustomerManager::new(&state.pg_pool);
+
+    match customer_manager.create_customer(&customer).await {
+        Ok(customer) => Ok((StatusCode::CREATED, Json(customer))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn list_customer_accounts(
+    State(state): State<Arc<AppState>>,
+    Path(customer_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Vec<AccountResponse>>), ApiError> {
+    let customer_manager = CustomerManager::new(&state.pg_pool);
+
+    match customer_manager.list_accounts(&customer_id).await {
+        Ok(accounts) => Ok((
+            StatusCode::OK,
+            Json(AccountResponse::list(
+                &AccountManager::new(&state.pg_pool),
+                accounts,
+            )),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,248 @@
+//! Routes served with the `memory` storage, or `sqlite` when built with the `sqlite` feature,
+//! instead of Postgres.
+//!
+//! Only what every storage keeps is available, customers, accounts and deposits, withdraws and
+//! transfers. On memory everything is lost when the server stops.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{get, patch, post},
+    Json, Router,
+};
+use bank_case::internal::{
+    account::domain::{Account, AccountLimits, AccountStatusChange, AccountType},
+    customer::domain::{Customer, NewCustomer},
+    error::ErrorKind,
+    money::domain::{Money, DEFAULT_CURRENCY},
+    storage::{bank::Bank, repository::Storage},
+    transaction::domain::Transaction,
+};
+
+use crate::{
+    account::{
+        AccountLimitsResponse, AccountResponse, CreateAccountDto, GetBalanceResponse,
+        StatusChangeDto, UpdateLimitsDto,
+    },
+    error::ApiError,
+    transaction::{
+        parse_filter, parse_transaction, ListTransactionsQuery, TransactionDto,
+        TransactionPageResponse, TransactionReceiptResponse,
+    },
+};
+
+pub struct DemoState {
+    storage: Box<dyn Storage>,
+}
+
+impl DemoState {
+    fn bank(&self) -> Bank<'_> {
+        Bank::new(&*self.storage)
+    }
+}
+
+pub fn router(storage: Box<dyn Storage>) -> Router {
+    Router::new()
+        .route("/account", post(create_account))
+        .route("/account/:account_number/balance", get(get_balance))
+        .route("/account/:account_number/limits", patch(update_limits))
+        .route("/account/:account_number/freeze", post(freeze_account))
+        .route("/account/:account_number/unfreeze", post(unfreeze_account))
+        .route(
+            "/account/:account_number/transactions",
+            get(list_transactions),
+        )
+        .route("/accounts", get(list_accounts))
+        .route("/customers", post(create_customer))
+        .route("/transaction", post(create_transaction))
+        .with_state(Arc::new(DemoState { storage }))
+}
+
+async fn get_account(bank: &Bank<'_>, account_number: i64) -> Result<Account, ApiError> {
+    match bank.get_account_from_number(account_number).await {
+        Ok(account) => Ok(account),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_customer(
+    State(state): State<Arc<DemoState>>,
+    Json(customer): Json<NewCustomer>,
+) -> Result<(StatusCode, Json<Customer>), ApiError> {
+    match state.bank().create_customer(&customer).await {
+        Ok(customer) => Ok((StatusCode::CREATED, Json(customer))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn create_account(
+    State(state): State<Arc<DemoState>>,
+    Json(account): Json<CreateAccountDto>,
+) -> Result<(StatusCode, Json<AccountResponse>), ApiError> {
+    let bank = state.bank();
+
+    let account_type = account.account_type.unwrap_or(AccountType::Checking);
+
+    let currency = account.currency.unwrap_or(DEFAULT_CURRENCY);
+
+    match bank
+        .create_account(account_type, currency, &account.holders)
+        .await
+    {
+        Ok(account) => Ok((
+            StatusCode::CREATED,
+            Json(AccountResponse::with_iban(bank.iban(&account), account)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn list_accounts(
+    State(state): State<Arc<DemoState>>,
+) -> Result<(StatusCode, Json<Vec<AccountResponse>>), ApiError> {
+    let bank = state.bank();
+
+    match bank.list_accounts().await {
+        Ok(accounts) => Ok((
+            StatusCode::OK,
+            Json(
+                accounts
+                    .into_iter()
+                    .map(|account| AccountResponse::with_iban(bank.iban(&account), account))
+                    .collect(),
+            ),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_balance(
+    State(state): State<Arc<DemoState>>,
+    Path(account_number): Path<i64>,
+) -> Result<(StatusCode, Json<GetBalanceResponse>), ApiError> {
+    let bank = state.bank();
+    let account = get_account(&bank, account_number).await?;
+
+    let balance = bank.get_balance(&account).await;
+    let available_balance = bank.get_available_balance(&account).await;
+
+    match (balance, available_balance) {
+        (Ok(balance), Ok(available_balance)) => Ok((
+            StatusCode::OK,
+            Json(GetBalanceResponse {
+                balance,
+                available_balance,
+            }),
+        )),
+        (Err(e), _) | (_, Err(e)) => Err(e.into()),
+    }
+}
+
+pub async fn update_limits(
+    State(state): State<Arc<DemoState>>,
+    Path(account_number): Path<i64>,
+    Json(limits): Json<UpdateLimitsDto>,
+) -> Result<(StatusCode, Json<AccountLimitsResponse>), ApiError> {
+    let bank = state.bank();
+    let account = get_account(&bank, account_number).await?;
+
+    let currency = *account.currency();
+    let overdraft_limit = match Money::parse(&limits.overdraft_limit, currency) {
+        Ok(overdraft_limit) => overdraft_limit,
+        Err(e) => return Err(ApiError::from_error(&e)),
+    };
+
+    let limits = AccountLimits {
+        overdraft_limit: overdraft_limit.amount_minor,
+    };
+
+    match bank.update_limits(&account, &limits).await {
+        Ok(limits) => Ok((
+            StatusCode::OK,
+            Json(AccountLimitsResponse {
+                overdraft_limit: Money::new(limits.overdraft_limit, currency),
+            }),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn freeze_account(
+    State(state): State<Arc<DemoState>>,
+    Path(account_number): Path<i64>,
+    Json(status_change): Json<StatusChangeDto>,
+) -> Result<(StatusCode, Json<AccountStatusChange>), ApiError> {
+    let bank = state.bank();
+    let account = get_account(&bank, account_number).await?;
+
+    match bank.freeze(&account, &status_change.reason).await {
+        Ok(change) => Ok((StatusCode::OK, Json(change))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn unfreeze_account(
+    State(state): State<Arc<DemoState>>,
+    Path(account_number): Path<i64>,
+    Json(status_change): Json<StatusChangeDto>,
+) -> Result<(StatusCode, Json<AccountStatusChange>), ApiError> {
+    let bank = state.bank();
+    let account = get_account(&bank, account_number).await?;
+
+    match bank.unfreeze(&account, &status_change.reason).await {
+        Ok(change) => Ok((StatusCode::OK, Json(change))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Idempotency keys are not kept in memory, so the `Idempotency-Key` header is ignored
+pub async fn create_transaction(
+    State(state): State<Arc<DemoState>>,
+    Json(transaction): Json<TransactionDto>,
+) -> Result<(StatusCode, Json<TransactionReceiptResponse>), ApiError> {
+    let bank = state.bank();
+
+    let transaction = parse_transaction(&bank, transaction.transaction).await?;
+    let currency = match &transaction {
+        Transaction::Deposit { destination, .. } => *destination.currency(),
+        Transaction::Withdraw { origin, .. }
+        | Transaction::Transfer { origin, .. }
+        | Transaction::FxTransfer { origin, .. } => *origin.currency(),
+    };
+
+    match bank.create_transaction(transaction).await {
+        Ok(receipt) => Ok((
+            StatusCode::CREATED,
+            Json(TransactionReceiptResponse::new(receipt, currency)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn list_transactions(
+    State(state): State<Arc<DemoState>>,
+    Path(account_number): Path<i64>,
+    Query(query): Query<ListTransactionsQuery>,
+) -> Result<(StatusCode, Json<TransactionPageResponse>), ApiError> {
+    let bank = state.bank();
+    let account = get_account(&bank, account_number).await?;
+
+    let currency = *account.currency();
+    let (filter, cursor) = parse_filter(query, currency)?;
+
+    match bank
+        .list_transactions(&account, &filter, cursor.as_ref())
+        .await
+    {
+        Ok(page) => match TransactionPageResponse::new(page, currency) {
+            Some(page) => Ok((StatusCode::OK, Json(page))),
+            None => Err(ApiError::new(
+                ErrorKind::Internal,
+                "Running balance doesn't fit in minor units".to_string(),
+            )),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,289 @@
+//! Every error leaves the API as an RFC 7807 problem, `application/problem+json`, with a
+//! stable `code` clients can match on and the `request_id` also sent in `X-Request-Id`.
+//!
+//! Handlers return `ApiError`, built from the `BankError` of the domain. Errors produced by
+//! axum itself, like a body that isn't JSON or an unknown route, are turned into problems by
+//! the `problem_details` middleware too.
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bank_case::internal::error::{BankError, ErrorKind};
+use serde::Serialize;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Shown instead of the message of internal errors, which may come straight from the database
+const INTERNAL_ERROR_MESSAGE: &str = "An unexpected error happened, please try again";
+
+/// Bodies of errors axum answers with are short texts, a longer one is replaced by the reason
+/// of the status
+const MAX_REJECTION_BODY: usize = 4096;
+
+/// Error returned by the handlers, rendered as a problem by `problem_details`
+#[derive(Debug)]
+pub struct ApiError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl ApiError {
+    pub fn new(kind: ErrorKind, message: String) -> Self {
+        Self { kind, message }
+    }
+
+    pub fn from_error(error: &dyn BankError) -> Self {
+        Self::new(*error.kind(), error.message().to_string())
+    }
+}
+
+impl From<Box<dyn BankError>> for ApiError {
+    fn from(error: Box<dyn BankError>) -> Self {
+        Self::from_error(error.as_ref())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let problem = Problem::from(self);
+
+        // The request id is only known to the middleware, which writes the body
+        let mut response = StatusCode::from_u16(problem.status)
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            .into_response();
+        response.extensions_mut().insert(problem);
+        response
+    }
+}
+
+pub fn status(kind: &ErrorKind) -> StatusCode {
+    match kind {
+        ErrorKind::InvalidInput => StatusCode::BAD_REQUEST,
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        // Kept as 400 since clients already rely on it for a declined debit
+        ErrorKind::InsufficientFunds { .. } => StatusCode::BAD_REQUEST,
+        ErrorKind::AccountFrozen | ErrorKind::Conflict => StatusCode::CONFLICT,
+        ErrorKind::Unprocessable => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorKind::Unsupported => StatusCode::NOT_IMPLEMENTED,
+        ErrorKind::Internal => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Stable for clients, changing one is a breaking change of the API
+pub fn code(kind: &ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::InvalidInput => "INVALID_INPUT",
+        ErrorKind::NotFound => "NOT_FOUND",
+        ErrorKind::InsufficientFunds { .. } => "INSUFFICIENT_FUNDS",
+        ErrorKind::AccountFrozen => "ACCOUNT_FROZEN",
+        ErrorKind::Conflict => "CONFLICT",
+        ErrorKind::Unprocessable => "UNPROCESSABLE",
+        ErrorKind::Unsupported => "UNSUPPORTED",
+        ErrorKind::Internal => "INTERNAL_ERROR",
+    }
+}
+
+/// Code of the errors axum answers with before reaching a handler
+fn rejection_code(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "INVALID_INPUT",
+        StatusCode::NOT_FOUND => "NOT_FOUND",
+        StatusCode::METHOD_NOT_ALLOWED => "METHOD_NOT_ALLOWED",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "UNSUPPORTED_MEDIA_TYPE",
+        StatusCode::PAYLOAD_TOO_LARGE => "PAYLOAD_TOO_LARGE",
+        status if status.is_server_error() => "INTERNAL_ERROR",
+        _ => "INVALID_REQUEST",
+    }
+}
+
+/// Body of every error response. `type`, `title` and `status` are the RFC 7807 members, the
+/// others are extensions.
+#[derive(Debug, Clone, Serialize)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    problem_type: &'static str,
+    title: &'static str,
+    status: u16,
+    code: &'static str,
+    message: String,
+    details: serde_json::Value,
+    request_id: Option<String>,
+    /// Message of an internal error, only logged
+    #[serde(skip)]
+    cause: Option<String>,
+}
+
+impl Problem {
+    fn new(status: StatusCode, code: &'static str, message: String) -> Self {
+        Self {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            code,
+            message,
+            details: serde_json::json!({}),
+            request_id: None,
+            cause: None,
+        }
+    }
+
+    fn from_rejection(status: StatusCode, body: String) -> Self {
+        if status.is_server_error() {
+            let mut problem = Self::new(
+                status,
+                rejection_code(status),
+                INTERNAL_ERROR_MESSAGE.to_string(),
+            );
+            problem.cause = Some(body);
+            return problem;
+        }
+
+        let message = match body.trim() {
+            "" => status.canonical_reason().unwrap_or("Error").to_string(),
+            body => body.to_string(),
+        };
+
+        Self::new(status, rejection_code(status), message)
+    }
+}
+
+impl From<ApiError> for Problem {
+    fn from(error: ApiError) -> Self {
+        let status = status(&error.kind);
+        let code = code(&error.kind);
+
+        match error.kind {
+            ErrorKind::Internal => {
+                let mut problem = Self::new(status, code, INTERNAL_ERROR_MESSAGE.to_string());
+                problem.cause = Some(error.message);
+                problem
+            }
+            ErrorKind::InsufficientFunds {
+                available,
+                requested,
+            } => {
+                let mut problem = Self::new(status, code, error.message);
+                problem.details = serde_json::json!({
+                    "available": available,
+                    "requested": requested,
+                });
+                problem
+            }
+            _ => Self::new(status, code, error.message),
+        }
+    }
+}
+
+/// Tags each request with an id, taken from `X-Request-Id` when the client sends one, and
+/// writes error responses as problems carrying it
+pub async fn problem_details(request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128)
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| Uuid::now_v7().to_string());
+
+    let response = next.run(request).await;
+    let (mut parts, body) = response.into_parts();
+
+    let mut problem = match parts.extensions.remove::<Problem>() {
+        Some(problem) => problem,
+        None if parts.status.is_client_error() || parts.status.is_server_error() => {
+            let body = to_bytes(body, MAX_REJECTION_BODY)
+                .await
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .unwrap_or_default();
+            Problem::from_rejection(parts.status, body)
+        }
+        None => {
+            if let Ok(id) = HeaderValue::from_str(&request_id) {
+                parts.headers.insert(REQUEST_ID_HEADER, id);
+            }
+            return Response::from_parts(parts, body);
+        }
+    };
+
+    if let Some(cause) = &problem.cause {
+        tracing::error!("Request [{}] failed: {}", request_id, cause);
+    }
+    problem.request_id = Some(request_id.clone());
+
+    let body = serde_json::to_vec(&problem).unwrap_or_default();
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/problem+json"),
+    );
+    if let Ok(id) = HeaderValue::from_str(&request_id) {
+        parts.headers.insert(REQUEST_ID_HEADER, id);
+    }
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use bank_case::internal::money::domain::{Currency, Money};
+
+    use super::*;
+
+    #[test]
+    fn test_internal_message_is_hidden() {
+        let problem = Problem::from(ApiError::new(
+            ErrorKind::Internal,
+            "error returned from database: relation \"account\" does not exist".to_string(),
+        ));
+
+        assert_eq!(problem.status, 500);
+        assert_eq!(problem.code, "INTERNAL_ERROR");
+        assert_eq!(problem.message, INTERNAL_ERROR_MESSAGE);
+
+        let body = serde_json::to_string(&problem).unwrap();
+        assert!(!body.contains("database"));
+    }
+
+    #[test]
+    fn test_insufficient_funds_details() {
+        let problem = Problem::from(ApiError::new(
+            ErrorKind::InsufficientFunds {
+                available: Money::new(100, Currency::Brl),
+                requested: Money::new(150, Currency::Brl),
+            },
+            "Insufficient funds, available amount is 1.00 BRL".to_string(),
+        ));
+
+        assert_eq!(problem.status, 400);
+        assert_eq!(problem.code, "INSUFFICIENT_FUNDS");
+        assert_eq!(
+            problem.details,
+            serde_json::json!({
+                "available": { "amount": "1.00", "currency": "BRL" },
+                "requested": { "amount": "1.50", "currency": "BRL" },
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejection_keeps_status() {
+        let problem = Problem::from_rejection(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Failed to deserialize the JSON body into the target type: missing field `holders`"
+                .to_string(),
+        );
+
+        assert_eq!(problem.status, 422);
+        assert_eq!(problem.code, "INVALID_INPUT");
+        assert!(problem.message.contains("missing field"));
+
+        let problem = Problem::from_rejection(StatusCode::NOT_FOUND, String::new());
+        assert_eq!(problem.code, "NOT_FOUND");
+        assert_eq!(problem.message, "Not Found");
+    }
+}
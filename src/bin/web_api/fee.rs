@@ -0,0 +1,157 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use bank_case::internal::{
+    account::{account::AccountManager, domain::AccountType},
+    fee::{
+        domain::{FeeEvent, FeeRule, FeeSchedule},
+        fee::FeeManager,
+    },
+    money::domain::{Currency, Money},
+};
+use chrono::{Datelike, Months, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::ApiError,
+    transaction::{parse_amount, AssessedFeeResponse},
+    AppState,
+};
+
+/// How often the maintenance fee job checks for finished months
+pub const MAINTENANCE_FEE_JOB_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(60 * 60);
+
+pub async fn list_fee_schedules(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<Vec<FeeSchedule>>), ApiError> {
+    match FeeManager::new(&state.pg_pool).list_schedules().await {
+        Ok(schedules) => Ok((StatusCode::OK, Json(schedules))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn set_fee_schedule(
+    State(state): State<Arc<AppState>>,
+    Path((account_type, event, currency)): Path<(AccountType, FeeEvent, Currency)>,
+    Json(schedule): Json<FeeScheduleDto>,
+) -> Result<(StatusCode, Json<FeeSchedule>), ApiError> {
+    let schedule = FeeSchedule {
+        account_type,
+        event,
+        currency,
+        rule: schedule.rule,
+        min_fee: schedule.min_fee,
+        max_fee: schedule.max_fee,
+    };
+
+    match FeeManager::new(&state.pg_pool)
+        .set_schedule(&schedule)
+        .await
+    {
+        Ok(schedule) => Ok((StatusCode::OK, Json(schedule))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn remove_fee_schedule(
+    State(state): State<Arc<AppState>>,
+    Path((account_type, event, currency)): Path<(AccountType, FeeEvent, Currency)>,
+) -> Result<StatusCode, ApiError> {
+    match FeeManager::new(&state.pg_pool)
+        .remove_schedule(account_type, event, currency)
+        .await
+    {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn quote_fees(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Query(query): Query<QuoteFeesQuery>,
+) -> Result<(StatusCode, Json<FeeQuoteResponse>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Ok(account) => account,
+        Err(e) => return Err(e.into()),
+    };
+
+    let currency = *account.currency();
+    let amount = parse_amount(&query.amount, currency)?;
+
+    match FeeManager::new(&state.pg_pool)
+        .quote(&account, query.r#type, amount)
+        .await
+    {
+        Ok(quote) => Ok((
+            StatusCode::OK,
+            Json(FeeQuoteResponse {
+                fees: quote
+                    .fees
+                    .into_iter()
+                    .map(|fee| AssessedFeeResponse::new(fee, currency))
+                    .collect(),
+                total: Money::new(quote.total, currency),
+            }),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Charges the maintenance fee of the previous month, re-running is a no-op
+pub async fn run_maintenance_fee_job(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(MAINTENANCE_FEE_JOB_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let previous_month = Utc::now()
+            .date_naive()
+            .with_day(1)
+            .and_then(|month| month.checked_sub_months(Months::new(1)));
+
+        let Some(previous_month) = previous_month else {
+            continue;
+        };
+
+        if let Err(e) = FeeManager::new(&state.pg_pool)
+            .charge_monthly_maintenance(previous_month)
+            .await
+        {
+            tracing::error!("Error charging maintenance fees: {}", e.message());
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FeeScheduleDto {
+    rule: FeeRule,
+    min_fee: Option<i64>,
+    max_fee: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct QuoteFeesQuery {
+    /// `withdraw` or `transfer`
+    r#type: FeeEvent,
+    /// Decimal string in the currency of the account
+    amount: String,
+}
+
+#[derive(Serialize)]
+pub struct FeeQuoteResponse {
+    fees: Vec<AssessedFeeResponse>,
+    total: Money,
+}
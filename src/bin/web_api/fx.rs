@@ -0,0 +1,117 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bank_case::internal::{
+    fx::{
+        domain::{FxQuote, FxRate},
+        fx::FxManager,
+    },
+    money::domain::{Currency, Money},
+};
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{error::ApiError, transaction::parse_amount, AppState};
+
+/// Loads the rates of the file, so a restart picks up the latest ones
+pub async fn load_rates_file(state: &AppState, path: &std::path::Path) {
+    match FxManager::new(&state.pg_pool)
+        .load_rates_from_file(path)
+        .await
+    {
+        Ok(count) => tracing::info!("Loaded {} fx rates from [{}]", count, path.display()),
+        Err(e) => tracing::error!("Error loading fx rates: {}", e.message()),
+    }
+}
+
+pub async fn load_rates(
+    State(state): State<Arc<AppState>>,
+    Json(rates): Json<Vec<FxRate>>,
+) -> Result<StatusCode, ApiError> {
+    match FxManager::new(&state.pg_pool).load_rates(&rates).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_rate(
+    State(state): State<Arc<AppState>>,
+    Path((from, to)): Path<(Currency, Currency)>,
+) -> Result<(StatusCode, Json<FxRateResponse>), ApiError> {
+    match FxManager::new(&state.pg_pool).get_rate(from, to).await {
+        Ok(rate) => Ok((StatusCode::OK, Json(FxRateResponse { from, to, rate }))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn create_quote(
+    State(state): State<Arc<AppState>>,
+    Json(quote): Json<CreateQuoteDto>,
+) -> Result<(StatusCode, Json<FxQuoteResponse>), ApiError> {
+    let amount = parse_amount(&quote.amount, quote.from)?;
+
+    match FxManager::new(&state.pg_pool)
+        .create_quote(quote.from, quote.to, amount)
+        .await
+    {
+        Ok(quote) => Ok((StatusCode::CREATED, Json(FxQuoteResponse::from(quote)))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_quote(
+    State(state): State<Arc<AppState>>,
+    Path(quote_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<FxQuoteResponse>), ApiError> {
+    match FxManager::new(&state.pg_pool).get_quote(&quote_id).await {
+        Ok(quote) => Ok((StatusCode::OK, Json(FxQuoteResponse::from(quote)))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Serialize)]
+pub struct FxRateResponse {
+    from: Currency,
+    to: Currency,
+    /// Units of `to` per unit of `from`
+    rate: BigDecimal,
+}
+
+#[derive(Deserialize)]
+pub struct CreateQuoteDto {
+    from: Currency,
+    to: Currency,
+    /// Decimal string in the `from` currency
+    amount: String,
+}
+
+#[derive(Serialize)]
+pub struct FxQuoteResponse {
+    id: Uuid,
+    amount: Money,
+    converted_amount: Money,
+    rate: BigDecimal,
+    expires_at: DateTime<Utc>,
+    /// Set once a transfer executed the quote
+    journal_entry_id: Option<Uuid>,
+}
+
+impl From<FxQuote> for FxQuoteResponse {
+    fn from(quote: FxQuote) -> Self {
+        Self {
+            id: quote.id,
+            amount: Money::new(quote.amount, quote.from_currency),
+            converted_amount: Money::new(quote.converted_amount, quote.to_currency),
+            rate: quote.rate,
+            expires_at: quote.expires_at,
+            journal_entry_id: quote.journal_entry_id,
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bank_case::internal::{
+    account::account::AccountManager,
+    hold::{
+        domain::{Hold, HoldStatus},
+        hold::HoldManager,
+    },
+    money::domain::Money,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{error::ApiError, transaction::parse_amount, AppState};
+
+/// How often expired holds are closed
+pub const HOLD_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[axum::debug_handler]
+pub async fn place_hold(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Json(hold): Json<PlaceHoldDto>,
+) -> Result<(StatusCode, Json<HoldResponse>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+    let hold_manager = HoldManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Ok(account) => account,
+        Err(e) => return Err(e.into()),
+    };
+
+    let amount = parse_amount(&hold.amount, *account.currency())?;
+
+    match hold_manager
+        .place_hold(&account, amount, hold.expires_at)
+        .await
+    {
+        Ok(hold) => Ok((StatusCode::CREATED, Json(HoldResponse::from(hold)))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn capture_hold(
+    State(state): State<Arc<AppState>>,
+    Path(hold_id): Path<Uuid>,
+    Json(capture): Json<CaptureHoldDto>,
+) -> Result<(StatusCode, Json<HoldResponse>), ApiError> {
+    let hold_manager = HoldManager::new(&state.pg_pool);
+
+    let hold = match hold_manager.get(&hold_id).await {
+        Ok(hold) => hold,
+        Err(e) => return Err(e.into()),
+    };
+    let amount = parse_amount(&capture.amount, hold.currency)?;
+
+    match hold_manager.capture_hold(&hold_id, amount).await {
+        Ok(hold) => Ok((StatusCode::OK, Json(HoldResponse::from(hold)))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn release_hold(
+    State(state): State<Arc<AppState>>,
+    Path(hold_id): Path<Uuid>,
+) -> Result<(StatusCode, Json<HoldResponse>), ApiError> {
+    let hold_manager = HoldManager::new(&state.pg_pool);
+
+    match hold_manager.release_hold(&hold_id).await {
+        Ok(hold) => Ok((StatusCode::OK, Json(HoldResponse::from(hold)))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Closes the expired holds every `HOLD_SWEEP_INTERVAL`, until the server stops
+pub async fn sweep_expired_holds(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(HOLD_SWEEP_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match HoldManager::new(&state.pg_pool)
+            .release_expired_holds()
+            .await
+        {
+            Ok(0) => {}
+            Ok(released) => tracing::debug!("Released {} expired holds", released),
+            Err(e) => tracing::error!("Error releasing expired holds: {}", e.message()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct PlaceHoldDto {
+    /// Decimal string in the currency of the account
+    amount: String,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize)]
+pub struct CaptureHoldDto {
+    /// Decimal string in the currency of the account, at most the held amount
+    amount: String,
+}
+
+#[derive(Serialize)]
+pub struct HoldResponse {
+    id: Uuid,
+    account_id: Uuid,
+    amount: Money,
+    status: HoldStatus,
+    captured_amount: Option<Money>,
+    journal_entry_id: Option<Uuid>,
+    expires_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+impl From<Hold> for HoldResponse {
+    fn from(hold: Hold) -> Self {
+        Self {
+            id: hold.id,
+            account_id: hold.account_id,
+            amount: Money::new(hold.amount, hold.currency),
+            status: hold.status,
+            captured_amount: hold
+                .captured_amount
+                .map(|amount| Money::new(amount, hold.currency)),
+            journal_entry_id: hold.journal_entry_id,
+            expires_at: hold.expires_at,
+            created_at: hold.created_at,
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::Arc;
+
+use bank_case::internal::interest::{domain::InterestSettings, interest::InterestManager};
+
+use crate::AppState;
+
+/// How often the interest job checks for finished days and months
+pub const INTEREST_JOB_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// Accrues the previous day and capitalizes the previous month, re-running is a no-op
+pub async fn run_interest_job(state: Arc<AppState>, settings: InterestSettings) {
+    let mut interval = tokio::time::interval(INTEREST_JOB_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        if let Err(e) = InterestManager::new(&state.pg_pool, settings.clone())
+            .run()
+            .await
+        {
+            tracing::error!("Error running interest job: {}", e.message());
+        }
+    }
+}
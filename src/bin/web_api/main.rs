@@ -1,40 +1,129 @@
 mod account;
+mod customer;
+mod demo;
+mod error;
+mod fee;
+mod fx;
+mod hold;
+mod interest;
+mod schedule;
 mod transaction;
 
 use axum::{
-    routing::{get, post},
+    middleware,
+    routing::{get, patch, post, put},
     Router,
 };
-use bank_case::internal::config::database::{Database, DatabaseParams};
+#[cfg(feature = "sqlite")]
+use bank_case::internal::storage::sqlite::SqliteStorage;
+use bank_case::internal::{
+    config::{
+        database::Database,
+        settings::{LogLevel, Settings, Storage},
+    },
+    storage::memory::MemoryStorage,
+};
 use std::sync::Arc;
+use tracing_subscriber::filter::LevelFilter;
 
 pub struct AppState {
     pg_pool: sqlx::PgPool,
+    idempotency_key_ttl: chrono::Duration,
 }
 
 #[tokio::main]
 async fn main() {
+    let settings = Settings::load().unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+
     // initialize tracing
-    tracing_subscriber::fmt::init();
-
-    let database = Database::build(DatabaseParams {
-        db_name: "db".to_string(),
-        host: "localhost".to_string(),
-        password: "pass".to_string(),
-        user: "user".to_string(),
-        port: 5432,
-    })
+    tracing_subscriber::fmt()
+        .with_max_level(level_filter(settings.log_level))
+        .init();
+
+    let app = match &settings.storage {
+        Storage::Memory => {
+            tracing::info!("Running on in-memory storage, nothing is kept after a restart");
+            demo::router(Box::new(MemoryStorage::new()))
+        }
+        #[cfg(feature = "sqlite")]
+        Storage::Sqlite { path } => {
+            tracing::info!("Running on SQLite database [{}]", path.display());
+
+            let storage = SqliteStorage::open(path)
+                .await
+                .expect("Failed to open SQLite database");
+            demo::router(Box::new(storage))
+        }
+        Storage::Postgres => postgres_router(&settings).await,
+    };
+
+    // run our app with hyper, on the configured address
+    let listener = tokio::net::TcpListener::bind(settings.bind_address)
+        .await
+        .expect("Failed to bind port");
+    tracing::info!("Running server on {}", settings.bind_address);
+
+    axum::serve(
+        listener,
+        app.layer(middleware::from_fn(error::problem_details)),
+    )
     .await
-    .expect("Failed to connect to Database");
+    .expect("Failed to serve app");
+}
+
+fn level_filter(log_level: LogLevel) -> LevelFilter {
+    match log_level {
+        LogLevel::Error => LevelFilter::ERROR,
+        LogLevel::Warn => LevelFilter::WARN,
+        LogLevel::Info => LevelFilter::INFO,
+        LogLevel::Debug => LevelFilter::DEBUG,
+        LogLevel::Trace => LevelFilter::TRACE,
+    }
+}
+
+/// Every route, on Postgres, with the background jobs turned on in the settings running
+async fn postgres_router(settings: &Settings) -> Router {
+    tracing::info!("Connecting to {:?}", settings.database);
+    let database = Database::build(settings.database.clone())
+        .await
+        .expect("Failed to connect to Database");
 
     let pool = database.get_pool();
 
     let app_state = Arc::new(AppState {
         pg_pool: pool.clone(),
+        idempotency_key_ttl: settings.idempotency_key_ttl,
     });
 
+    // Rates can also be loaded later through `POST /fx/rates`
+    if let Some(path) = &settings.fx_rates_file {
+        fx::load_rates_file(&app_state, path).await;
+    }
+
+    let features = settings.features;
+    if features.hold_sweeper {
+        tokio::spawn(hold::sweep_expired_holds(app_state.clone()));
+    }
+    if features.interest_job {
+        tokio::spawn(interest::run_interest_job(
+            app_state.clone(),
+            settings.interest.clone(),
+        ));
+    }
+    if features.maintenance_fee_job {
+        tokio::spawn(fee::run_maintenance_fee_job(app_state.clone()));
+    }
+    if features.scheduler {
+        tokio::spawn(schedule::run_scheduler(app_state.clone()));
+    }
+    if features.idempotency_key_purge {
+        tokio::spawn(transaction::purge_expired_idempotency_keys(
+            app_state.clone(),
+        ));
+    }
+
     // build our application with a route
-    let app = Router::new()
+    Router::new()
         // `GET /` goes to `root`
         // .route("/", get(root))
         // `POST /users` goes to `create_user`
@@ -43,18 +132,68 @@ async fn main() {
             "/account/:account_number/balance",
             get(account::get_balance),
         )
+        .route(
+            "/account/:account_number/limits",
+            patch(account::update_limits),
+        )
+        .route(
+            "/account/:account_number/freeze",
+            post(account::freeze_account),
+        )
+        .route(
+            "/account/:account_number/unfreeze",
+            post(account::unfreeze_account),
+        )
+        .route(
+            "/account/:account_number/close",
+            post(account::close_account),
+        )
+        .route(
+            "/account/:account_number/transactions",
+            get(transaction::list_transactions),
+        )
+        .route("/account/:account_number/holds", post(hold::place_hold))
+        .route("/account/:account_number/fees/quote", get(fee::quote_fees))
+        .route(
+            "/account/:account_number/scheduled-transactions",
+            get(schedule::list_account_scheduled_transactions),
+        )
+        .route("/hold/:hold_id/capture", post(hold::capture_hold))
+        .route("/hold/:hold_id/release", post(hold::release_hold))
         .route("/accounts", get(account::list_accounts_controller))
+        .route("/customers", post(customer::create_customer))
+        .route(
+            "/customers/:customer_id/accounts",
+            get(customer::list_customer_accounts),
+        )
+        .route("/fee-schedules", get(fee::list_fee_schedules))
+        .route(
+            "/fee-schedules/:account_type/:event/:currency",
+            put(fee::set_fee_schedule).delete(fee::remove_fee_schedule),
+        )
+        .route("/fx/rates", post(fx::load_rates))
+        .route("/fx/rates/:from/:to", get(fx::get_rate))
+        .route("/fx/quotes", post(fx::create_quote))
+        .route("/fx/quotes/:quote_id", get(fx::get_quote))
+        .route(
+            "/scheduled-transactions",
+            post(schedule::create_scheduled_transaction),
+        )
+        .route(
+            "/scheduled-transactions/:id",
+            get(schedule::get_scheduled_transaction)
+                .patch(schedule::update_scheduled_transaction)
+                .delete(schedule::cancel_scheduled_transaction),
+        )
+        .route(
+            "/scheduled-transactions/:id/executions",
+            get(schedule::list_executions),
+        )
         .route("/transaction", post(transaction::create_transaction))
-        .with_state(app_state);
-
-    let uri = "0.0.0.0:3000";
-    // run our app with hyper, listening globally on port 3000
-    let listener = tokio::net::TcpListener::bind(uri)
-        .await
-        .expect("Failed to bind port");
-    println!("Running server on {}", uri);
-
-    axum::serve(listener, app)
-        .await
-        .expect("Failed to serve app");
+        .route(
+            "/transaction/:transaction_id/reversal",
+            post(transaction::reverse_transaction),
+        )
+        .route("/ledger/trial-balance", get(transaction::trial_balance))
+        .with_state(app_state)
 }
@@ -0,0 +1,236 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use bank_case::internal::{
+    account::account::AccountManager,
+    money::domain::Money,
+    schedule::{
+        domain::{
+            Recurrence, ScheduleChanges, ScheduleStatus, ScheduledTransaction,
+            ScheduledTransactionExecution, ScheduledTransactionType,
+        },
+        schedule::ScheduleManager,
+    },
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    error::ApiError,
+    transaction::{parse_amount, parse_transaction, TransactionEnum},
+    AppState,
+};
+
+/// How often the scheduler looks for due scheduled transactions
+pub const SCHEDULER_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+#[axum::debug_handler]
+pub async fn create_scheduled_transaction(
+    State(state): State<Arc<AppState>>,
+    Json(scheduled): Json<CreateScheduledTransactionDto>,
+) -> Result<(StatusCode, Json<ScheduledTransactionResponse>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+    let schedule_manager = ScheduleManager::new(&state.pg_pool);
+
+    let transaction = parse_transaction(&account_manager, scheduled.transaction).await?;
+
+    match schedule_manager
+        .create(
+            &transaction,
+            scheduled.first_run_at,
+            scheduled.recurrence,
+            scheduled.ends_at,
+        )
+        .await
+    {
+        Ok(scheduled) => Ok((
+            StatusCode::CREATED,
+            Json(ScheduledTransactionResponse::from(scheduled)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn get_scheduled_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<ScheduledTransactionResponse>), ApiError> {
+    match ScheduleManager::new(&state.pg_pool).get(&id).await {
+        Ok(scheduled) => Ok((
+            StatusCode::OK,
+            Json(ScheduledTransactionResponse::from(scheduled)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn update_scheduled_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(changes): Json<UpdateScheduledTransactionDto>,
+) -> Result<(StatusCode, Json<ScheduledTransactionResponse>), ApiError> {
+    let schedule_manager = ScheduleManager::new(&state.pg_pool);
+
+    let amount = match changes.amount {
+        Some(amount) => {
+            let scheduled = match schedule_manager.get(&id).await {
+                Ok(scheduled) => scheduled,
+                Err(e) => return Err(e.into()),
+            };
+            Some(parse_amount(&amount, scheduled.currency)?)
+        }
+        None => None,
+    };
+
+    let changes = ScheduleChanges {
+        amount,
+        recurrence: changes.recurrence,
+        next_run_at: changes.next_run_at,
+        ends_at: changes.ends_at,
+    };
+
+    match schedule_manager.update(&id, &changes).await {
+        Ok(scheduled) => Ok((
+            StatusCode::OK,
+            Json(ScheduledTransactionResponse::from(scheduled)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn cancel_scheduled_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<ScheduledTransactionResponse>), ApiError> {
+    match ScheduleManager::new(&state.pg_pool).cancel(&id).await {
+        Ok(scheduled) => Ok((
+            StatusCode::OK,
+            Json(ScheduledTransactionResponse::from(scheduled)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn list_executions(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<(StatusCode, Json<Vec<ScheduledTransactionExecution>>), ApiError> {
+    match ScheduleManager::new(&state.pg_pool)
+        .list_executions(&id)
+        .await
+    {
+        Ok(executions) => Ok((StatusCode::OK, Json(executions))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub async fn list_account_scheduled_transactions(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+) -> Result<(StatusCode, Json<Vec<ScheduledTransactionResponse>>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Ok(account) => account,
+        Err(e) => return Err(e.into()),
+    };
+
+    match ScheduleManager::new(&state.pg_pool)
+        .list_for_account(&account)
+        .await
+    {
+        Ok(scheduled) => Ok((
+            StatusCode::OK,
+            Json(
+                scheduled
+                    .into_iter()
+                    .map(ScheduledTransactionResponse::from)
+                    .collect(),
+            ),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Executes the due scheduled transactions every `SCHEDULER_INTERVAL`, any number of servers can
+/// run it at the same time
+pub async fn run_scheduler(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(SCHEDULER_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match ScheduleManager::new(&state.pg_pool).run_due().await {
+            Ok(0) => {}
+            Ok(executed) => tracing::debug!("Executed {} scheduled transactions", executed),
+            Err(e) => tracing::error!("Error running scheduled transactions: {}", e.message()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateScheduledTransactionDto {
+    transaction: TransactionEnum,
+    first_run_at: DateTime<Utc>,
+    #[serde(default = "once")]
+    recurrence: Recurrence,
+    ends_at: Option<DateTime<Utc>>,
+}
+
+fn once() -> Recurrence {
+    Recurrence::Once
+}
+
+/// Fields left empty are kept as they are
+#[derive(Deserialize)]
+pub struct UpdateScheduledTransactionDto {
+    /// Decimal string in the currency of the schedule
+    amount: Option<String>,
+    recurrence: Option<Recurrence>,
+    next_run_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize)]
+pub struct ScheduledTransactionResponse {
+    id: Uuid,
+    transaction_type: ScheduledTransactionType,
+    amount: Money,
+    origin_account_id: Option<Uuid>,
+    destination_account_id: Option<Uuid>,
+    recurrence: Recurrence,
+    next_run_at: Option<DateTime<Utc>>,
+    next_attempt_at: Option<DateTime<Utc>>,
+    attempts: i32,
+    ends_at: Option<DateTime<Utc>>,
+    status: ScheduleStatus,
+    created_at: DateTime<Utc>,
+}
+
+impl From<ScheduledTransaction> for ScheduledTransactionResponse {
+    fn from(scheduled: ScheduledTransaction) -> Self {
+        Self {
+            id: scheduled.id,
+            transaction_type: scheduled.transaction_type,
+            amount: Money::new(scheduled.amount, scheduled.currency),
+            origin_account_id: scheduled.origin_account_id,
+            destination_account_id: scheduled.destination_account_id,
+            recurrence: scheduled.recurrence,
+            next_run_at: scheduled.next_run_at,
+            next_attempt_at: scheduled.next_attempt_at,
+            attempts: scheduled.attempts,
+            ends_at: scheduled.ends_at,
+            status: scheduled.status,
+            created_at: scheduled.created_at,
+        }
+    }
+}
@@ -1,48 +1,175 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
 use bank_case::internal::{
-    account::account::AccountManager,
-    transaction::{domain::Transaction, transaction::TransactionManager},
+    account::{account::AccountManager, domain::Account},
+    error::{BankError, ErrorKind},
+    fee::domain::{AssessedFee, FeeEvent},
+    money::domain::{Amount, Currency, Money},
+    storage::bank::Bank,
+    transaction::{
+        domain::{
+            Transaction, TransactionCursor, TransactionFilter, TransactionPage, TransactionReceipt,
+            TransactionType, TrialBalance,
+        },
+        transaction::TransactionManager,
+    },
 };
-use serde::Deserialize;
+use bigdecimal::ToPrimitive;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{error::ApiError, AppState};
 
-use crate::AppState;
+/// How often the expired idempotency keys are deleted
+pub const IDEMPOTENCY_KEY_PURGE_INTERVAL: std::time::Duration =
+    std::time::Duration::from_secs(60 * 60);
 
 #[axum::debug_handler]
 pub async fn create_transaction(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Json(transaction): Json<TransactionDto>,
-) -> (StatusCode, String) {
+) -> Result<(StatusCode, Json<TransactionReceiptResponse>), ApiError> {
+    let idempotency_key = match headers.get("Idempotency-Key") {
+        None => None,
+        Some(key) => match key.to_str() {
+            Ok(key) => Some(key.to_string()),
+            Err(_) => {
+                return Err(ApiError::new(
+                    ErrorKind::InvalidInput,
+                    "Idempotency-Key header must be visible ASCII".to_string(),
+                ))
+            }
+        },
+    };
+
     let account_manager = AccountManager::new(&state.pg_pool);
-    let transaction_manager = TransactionManager::new(&state.pg_pool);
+    let transaction_manager =
+        TransactionManager::new(&state.pg_pool).with_idempotency_key_ttl(state.idempotency_key_ttl);
+
+    let transaction_parsed = parse_transaction(&account_manager, transaction.transaction).await?;
+    let currency = match &transaction_parsed {
+        Transaction::Deposit { destination, .. } => *destination.currency(),
+        Transaction::Withdraw { origin, .. }
+        | Transaction::Transfer { origin, .. }
+        | Transaction::FxTransfer { origin, .. } => *origin.currency(),
+    };
+
+    let result = match idempotency_key {
+        Some(idempotency_key) => {
+            transaction_manager
+                .create_idempotent_transaction(transaction_parsed, &idempotency_key)
+                .await
+        }
+        None => {
+            transaction_manager
+                .create_transaction(transaction_parsed)
+                .await
+        }
+    };
+
+    match result {
+        Ok(receipt) => Ok((
+            StatusCode::CREATED,
+            Json(TransactionReceiptResponse::new(receipt, currency)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Minor units of a decimal `amount` in `currency`, which must be greater than zero
+pub fn parse_amount(amount: &str, currency: Currency) -> Result<Amount, ApiError> {
+    match Amount::parse(amount, currency) {
+        Ok(amount) => Ok(amount),
+        Err(e) => Err(ApiError::from_error(&e)),
+    }
+}
+
+/// An account number, `1000482712`, or its IBAN, `"BR49 0000 0001 0000 1000 0482 712C 1"`
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum AccountIdentifier {
+    Number(i64),
+    Iban(String),
+}
+
+/// Where the accounts named in a request are looked up, the Postgres managers or a `Bank`
+pub trait AccountLookup: Sync {
+    fn get_account_from_number(
+        &self,
+        number: i64,
+    ) -> impl std::future::Future<Output = Result<Account, Box<dyn BankError>>> + Send;
 
-    let transaction_parsed: Transaction = match transaction.transaction {
+    fn get_account_from_iban(
+        &self,
+        iban: &str,
+    ) -> impl std::future::Future<Output = Result<Account, Box<dyn BankError>>> + Send;
+}
+
+impl AccountLookup for AccountManager<'_> {
+    async fn get_account_from_number(&self, number: i64) -> Result<Account, Box<dyn BankError>> {
+        AccountManager::get_account_from_number(self, number).await
+    }
+
+    async fn get_account_from_iban(&self, iban: &str) -> Result<Account, Box<dyn BankError>> {
+        AccountManager::get_account_from_iban(self, iban).await
+    }
+}
+
+impl AccountLookup for Bank<'_> {
+    async fn get_account_from_number(&self, number: i64) -> Result<Account, Box<dyn BankError>> {
+        Bank::get_account_from_number(self, number).await
+    }
+
+    async fn get_account_from_iban(&self, iban: &str) -> Result<Account, Box<dyn BankError>> {
+        Bank::get_account_from_iban(self, iban).await
+    }
+}
+
+pub async fn find_account(
+    accounts: &impl AccountLookup,
+    account: &AccountIdentifier,
+) -> Result<Account, ApiError> {
+    let account = match account {
+        AccountIdentifier::Number(number) => accounts.get_account_from_number(*number).await,
+        AccountIdentifier::Iban(iban) => accounts.get_account_from_iban(iban).await,
+    };
+
+    match account {
+        Ok(account) => Ok(account),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Resolves the account numbers and IBANs of the request into accounts, and the amount into minor units of
+/// the currency of the account the money comes from
+pub async fn parse_transaction(
+    account_manager: &impl AccountLookup,
+    transaction: TransactionEnum,
+) -> Result<Transaction, ApiError> {
+    let transaction = match transaction {
         TransactionEnum::Deposit {
             amount,
             destination,
         } => {
-            let account = match account_manager
-                .get_account_from_number(destination.into())
-                .await
-            {
-                Ok(account) => account,
-                Err(e) => return (e.status().clone(), e.message().to_string()),
-            };
+            let account = find_account(account_manager, &destination).await?;
 
             Transaction::Deposit {
-                amount,
+                amount: parse_amount(&amount, *account.currency())?,
                 destination: account,
             }
         }
         TransactionEnum::Withdraw { amount, origin } => {
-            let account = match account_manager.get_account_from_number(origin.into()).await {
-                Ok(account) => account,
-                Err(e) => return (e.status().clone(), e.message().to_string()),
-            };
+            let account = find_account(account_manager, &origin).await?;
 
             Transaction::Withdraw {
-                amount,
+                amount: parse_amount(&amount, *account.currency())?,
                 origin: account,
             }
         }
@@ -52,56 +179,314 @@ pub async fn create_transaction(
             destination,
         } => {
             // Should not transfer for self
-            let origin_account = match account_manager.get_account_from_number(origin.into()).await
-            {
-                Ok(account) => account,
-                Err(e) => return (e.status().clone(), e.message().to_string()),
-            };
-            let destiny_account = match account_manager
-                .get_account_from_number(destination.into())
-                .await
-            {
-                Ok(account) => account,
-                Err(e) => return (e.status().clone(), e.message().to_string()),
-            };
+            let origin_account = find_account(account_manager, &origin).await?;
+            let destiny_account = find_account(account_manager, &destination).await?;
 
             Transaction::Transfer {
-                amount,
+                amount: parse_amount(&amount, *origin_account.currency())?,
                 origin: origin_account,
                 destination: destiny_account,
             }
         }
+        TransactionEnum::FxTransfer {
+            quote_id,
+            origin,
+            destination,
+        } => {
+            let origin_account = find_account(account_manager, &origin).await?;
+            let destination_account = find_account(account_manager, &destination).await?;
+
+            Transaction::FxTransfer {
+                quote_id,
+                origin: origin_account,
+                destination: destination_account,
+            }
+        }
     };
 
-    let result = transaction_manager
-        .create_transaction(transaction_parsed)
-        .await;
+    Ok(transaction)
+}
 
-    match result {
-        Ok(_) => (StatusCode::CREATED, "".to_string()),
-        Err(e) => (e.status().clone(), e.message().to_string()),
+/// Deletes the idempotency keys past their TTL every `IDEMPOTENCY_KEY_PURGE_INTERVAL`, until
+/// the server stops
+pub async fn purge_expired_idempotency_keys(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(IDEMPOTENCY_KEY_PURGE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        match TransactionManager::new(&state.pg_pool)
+            .purge_expired_idempotency_keys()
+            .await
+        {
+            Ok(0) => {}
+            Ok(purged) => tracing::debug!("Purged {} expired idempotency keys", purged),
+            Err(e) => tracing::error!("Error purging idempotency keys: {}", e.message()),
+        }
     }
 }
 
 #[derive(Deserialize)]
 pub struct TransactionDto {
-    transaction: TransactionEnum,
+    pub transaction: TransactionEnum,
 }
 
 #[derive(Deserialize)]
 #[serde(tag = "type")]
-enum TransactionEnum {
+pub enum TransactionEnum {
+    /// Amounts are decimal strings in the currency of the account, `"12.34"`
     Deposit {
-        amount: u32,
-        destination: u32,
+        amount: String,
+        destination: AccountIdentifier,
     },
     Withdraw {
-        amount: u32,
-        origin: u32,
+        amount: String,
+        origin: AccountIdentifier,
     },
     Transfer {
-        amount: u32,
-        origin: u32,
-        destination: u32,
+        amount: String,
+        origin: AccountIdentifier,
+        destination: AccountIdentifier,
     },
+    /// Moves the amount of the quote, converted at its rate
+    FxTransfer {
+        quote_id: Uuid,
+        origin: AccountIdentifier,
+        destination: AccountIdentifier,
+    },
+}
+
+#[axum::debug_handler]
+pub async fn list_transactions(
+    State(state): State<Arc<AppState>>,
+    Path(account_number): Path<i64>,
+    Query(query): Query<ListTransactionsQuery>,
+) -> Result<(StatusCode, Json<TransactionPageResponse>), ApiError> {
+    let account_manager = AccountManager::new(&state.pg_pool);
+    let transaction_manager = TransactionManager::new(&state.pg_pool);
+
+    let account = match account_manager
+        .get_account_from_number(account_number)
+        .await
+    {
+        Ok(account) => account,
+        Err(e) => return Err(e.into()),
+    };
+
+    let currency = *account.currency();
+    let (filter, cursor) = parse_filter(query, currency)?;
+
+    match transaction_manager
+        .list_transactions(&account, &filter, cursor.as_ref())
+        .await
+    {
+        Ok(page) => match TransactionPageResponse::new(page, currency) {
+            Some(page) => Ok((StatusCode::OK, Json(page))),
+            None => Err(ApiError::new(
+                ErrorKind::Internal,
+                "Running balance doesn't fit in minor units".to_string(),
+            )),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Filter and cursor of a history request, amounts are in the currency of the account
+pub fn parse_filter(
+    query: ListTransactionsQuery,
+    currency: Currency,
+) -> Result<(TransactionFilter, Option<TransactionCursor>), ApiError> {
+    let types = match query.r#type {
+        None => None,
+        Some(types) => {
+            let mut parsed = Vec::new();
+            for transaction_type in types.split(',') {
+                match transaction_type.trim().parse::<TransactionType>() {
+                    Ok(transaction_type) => parsed.push(transaction_type),
+                    Err(e) => return Err(ApiError::from_error(&e)),
+                }
+            }
+            Some(parsed)
+        }
+    };
+
+    let cursor = match query.cursor {
+        None => None,
+        Some(cursor) => match cursor.parse::<TransactionCursor>() {
+            Ok(cursor) => Some(cursor),
+            Err(e) => return Err(ApiError::from_error(&e)),
+        },
+    };
+
+    let min_amount = match query.min_amount {
+        Some(amount) => Some(parse_amount(&amount, currency)?.into()),
+        None => None,
+    };
+    let max_amount = match query.max_amount {
+        Some(amount) => Some(parse_amount(&amount, currency)?.into()),
+        None => None,
+    };
+
+    let filter = TransactionFilter {
+        from: query.from,
+        to: query.to,
+        types,
+        min_amount,
+        max_amount,
+        limit: query.limit,
+    };
+
+    Ok((filter, cursor))
+}
+
+#[derive(Deserialize)]
+pub struct ListTransactionsQuery {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    /// Comma separated list of types
+    r#type: Option<String>,
+    /// Decimal strings in the currency of the account
+    min_amount: Option<String>,
+    max_amount: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+pub async fn trial_balance(
+    State(state): State<Arc<AppState>>,
+) -> Result<(StatusCode, Json<TrialBalance>), ApiError> {
+    let transaction_manager = TransactionManager::new(&state.pg_pool);
+
+    match transaction_manager.trial_balance().await {
+        Ok(trial_balance) => Ok((StatusCode::OK, Json(trial_balance))),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[axum::debug_handler]
+pub async fn reverse_transaction(
+    State(state): State<Arc<AppState>>,
+    Path(transaction_id): Path<Uuid>,
+    Json(reversal): Json<ReversalDto>,
+) -> Result<(StatusCode, Json<TransactionReceiptResponse>), ApiError> {
+    let transaction_manager = TransactionManager::new(&state.pg_pool);
+
+    let currency = match transaction_manager
+        .get_transaction_currency(&transaction_id)
+        .await
+    {
+        Ok(currency) => currency,
+        Err(e) => return Err(e.into()),
+    };
+
+    let result = match reversal.amount {
+        None => {
+            transaction_manager
+                .reverse(&transaction_id, &reversal.reason)
+                .await
+        }
+        Some(amount) => {
+            let amount = parse_amount(&amount, currency)?;
+            transaction_manager
+                .refund(&transaction_id, amount, &reversal.reason)
+                .await
+        }
+    };
+
+    match result {
+        Ok(receipt) => Ok((
+            StatusCode::CREATED,
+            Json(TransactionReceiptResponse::new(receipt, currency)),
+        )),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ReversalDto {
+    reason: String,
+    /// Refunds only part of a transfer when present, as a decimal string in the currency of the
+    /// account that was credited, the destination for a conversion
+    amount: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AssessedFeeResponse {
+    event: FeeEvent,
+    amount: Money,
+}
+
+#[derive(Serialize)]
+pub struct TransactionReceiptResponse {
+    journal_entry_id: Uuid,
+    transaction_ids: Vec<i32>,
+    transfer_id: Option<Uuid>,
+    fees: Vec<AssessedFeeResponse>,
+}
+
+impl AssessedFeeResponse {
+    pub fn new(fee: AssessedFee, currency: Currency) -> Self {
+        Self {
+            event: fee.event,
+            amount: Money::new(fee.amount, currency),
+        }
+    }
+}
+
+impl TransactionReceiptResponse {
+    pub fn new(receipt: TransactionReceipt, currency: Currency) -> Self {
+        Self {
+            journal_entry_id: receipt.journal_entry_id,
+            transaction_ids: receipt.transaction_ids,
+            transfer_id: receipt.transfer_id,
+            fees: receipt
+                .fees
+                .into_iter()
+                .map(|fee| AssessedFeeResponse::new(fee, currency))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct TransactionEntryResponse {
+    id: i32,
+    journal_entry_id: Uuid,
+    amount: Money,
+    #[serde(rename = "type")]
+    transaction_type: TransactionType,
+    created_at: DateTime<Utc>,
+    transfer_id: Option<Uuid>,
+    counterparty_account_number: Option<i64>,
+    balance: Money,
+}
+
+#[derive(Serialize)]
+pub struct TransactionPageResponse {
+    entries: Vec<TransactionEntryResponse>,
+    next_cursor: Option<String>,
+}
+
+impl TransactionPageResponse {
+    /// None if a running balance doesn't fit in minor units, which the ledger never allows
+    pub fn new(page: TransactionPage, currency: Currency) -> Option<Self> {
+        let mut entries = Vec::with_capacity(page.entries.len());
+        for entry in page.entries {
+            entries.push(TransactionEntryResponse {
+                id: entry.id,
+                journal_entry_id: entry.journal_entry_id,
+                amount: Money::new(entry.amount, currency),
+                transaction_type: entry.transaction_type,
+                created_at: entry.created_at,
+                transfer_id: entry.transfer_id,
+                counterparty_account_number: entry.counterparty_account_number,
+                balance: Money::new(entry.balance.to_i64()?, currency),
+            });
+        }
+
+        Some(Self {
+            entries,
+            next_cursor: page.next_cursor,
+        })
+    }
 }
@@ -1,165 +1,603 @@
-use sqlx::{types::BigDecimal, Acquire};
+use uuid::Uuid;
 
-use crate::internal::error::BankError;
+use crate::internal::{
+    error::{BankError, ErrorKind},
+    money::domain::{Amount, Currency, Money, DEFAULT_CURRENCY},
+    storage::repository::{AccountRepository, Repositories},
+    transaction::transaction::TransactionManager,
+};
 
-use super::{domain::Account, error::AccountError};
+use super::{
+    domain::{
+        account_number, account_number_from_iban, validate_account_number, validate_iban, Account,
+        AccountLimits, AccountStatus, AccountStatusChange, AccountType, BalanceDrift,
+        SystemAccount, DEFAULT_BANK_CODE, DEFAULT_BRANCH_CODE, MAX_BRANCH_CODE,
+    },
+    error::AccountError,
+};
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(AccountError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
 
 pub struct AccountManager<'a> {
     db_pool: &'a sqlx::PgPool,
+    bank_code: u32,
+    branch_code: u16,
 }
 
 impl<'a> AccountManager<'a> {
     pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
-        Self { db_pool }
+        Self {
+            db_pool,
+            bank_code: DEFAULT_BANK_CODE,
+            branch_code: DEFAULT_BRANCH_CODE,
+        }
+    }
+
+    /// Sets the bank of the IBANs issued and accepted
+    pub fn with_bank_code(mut self, bank_code: u32) -> Self {
+        self.bank_code = bank_code;
+        self
+    }
+
+    /// Sets the branch of the accounts opened, up to `MAX_BRANCH_CODE`
+    pub fn with_branch_code(mut self, branch_code: u16) -> Self {
+        self.branch_code = branch_code;
+        self
     }
 
+    async fn acquire(
+        &self,
+    ) -> Result<sqlx::pool::PoolConnection<sqlx::Postgres>, Box<dyn BankError>> {
+        match self.db_pool.acquire().await {
+            Ok(conn) => Ok(conn),
+            Err(e) => {
+                println!("Error getting database connection: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Numbers with a wrong check digit are rejected before reaching the database
     pub async fn get_account_from_number(
         &self,
         number: i64,
     ) -> Result<Account, Box<dyn BankError>> {
-        let account = sqlx::query_as!(
-            Account,
-            "SELECT id, number FROM account WHERE number = $1",
-            number
+        let mut conn = self.acquire().await?;
+
+        AccountManager::find_account_by_number(number, &mut *conn).await
+    }
+
+    /// Same as `get_account_from_number`, on the caller's unit of work
+    pub(crate) async fn find_account_by_number<R: AccountRepository + ?Sized>(
+        number: i64,
+        conn: &mut R,
+    ) -> Result<Account, Box<dyn BankError>> {
+        validate_account_number(number)?;
+
+        match conn.find_account_by_number(number).await? {
+            Some(account) => Ok(account),
+            None => Err(Box::new(AccountError::new(
+                format!("Account [{}] not found", number),
+                ErrorKind::NotFound,
+            ))),
+        }
+    }
+
+    pub fn iban(&self, account: &Account) -> String {
+        account.iban(self.bank_code)
+    }
+
+    /// Accepts the printed and electronic forms, only IBANs issued by this bank are found
+    pub async fn get_account_from_iban(&self, iban: &str) -> Result<Account, Box<dyn BankError>> {
+        let mut conn = self.acquire().await?;
+
+        AccountManager::find_account_by_iban(iban, self.bank_code, &mut *conn).await
+    }
+
+    /// Same as `get_account_from_iban`, on the caller's unit of work
+    pub(crate) async fn find_account_by_iban<R: AccountRepository + ?Sized>(
+        iban: &str,
+        bank_code: u32,
+        conn: &mut R,
+    ) -> Result<Account, Box<dyn BankError>> {
+        let iban = validate_iban(iban)?;
+        let number = account_number_from_iban(&iban, bank_code)?;
+
+        let not_found = || -> Box<dyn BankError> {
+            Box::new(AccountError::new(
+                format!("Account [{}] not found", iban),
+                ErrorKind::NotFound,
+            ))
+        };
+
+        match AccountManager::find_account_by_number(number, conn).await {
+            // The account type and holder position must match too
+            Ok(account) if account.iban(bank_code) == iban => Ok(account),
+            Ok(_) => Err(not_found()),
+            Err(e) if e.kind() == &ErrorKind::Internal => Err(e),
+            // Including a wrong check digit of the account number
+            Err(_) => Err(not_found()),
+        }
+    }
+
+    pub async fn get_account_from_id<R: AccountRepository + ?Sized>(
+        id: &uuid::Uuid,
+        conn: &mut R,
+    ) -> Result<Account, Box<dyn BankError>> {
+        match conn.find_account_by_id(id).await? {
+            Some(account) => Ok(account),
+            None => Err(Box::new(AccountError::new(
+                format!("Account [{}] not found", id),
+                ErrorKind::NotFound,
+            ))),
+        }
+    }
+
+    /// Opens a new account in `DEFAULT_CURRENCY` held by the given customers, more than one makes
+    /// it a joint account
+    pub async fn create_account(
+        &self,
+        account_type: AccountType,
+        holders: &[Uuid],
+    ) -> Result<Account, Box<dyn BankError>> {
+        self.create_account_with_currency(account_type, DEFAULT_CURRENCY, holders)
+            .await
+    }
+
+    /// Opens a new account held by the given customers, every amount booked on it is in `currency`
+    pub async fn create_account_with_currency(
+        &self,
+        account_type: AccountType,
+        currency: Currency,
+        holders: &[Uuid],
+    ) -> Result<Account, Box<dyn BankError>> {
+        let mut tx = self.begin().await?;
+
+        let account = AccountManager::open_account(
+            self.branch_code,
+            account_type,
+            currency,
+            holders,
+            &mut *tx,
         )
-        .fetch_optional(self.db_pool)
-        .await;
+        .await?;
+
+        AccountManager::commit(tx).await?;
+
+        Ok(account)
+    }
+
+    /// Same as `create_account_with_currency`, on the caller's unit of work
+    pub(crate) async fn open_account<R: Repositories + ?Sized>(
+        branch_code: u16,
+        account_type: AccountType,
+        currency: Currency,
+        holders: &[Uuid],
+        conn: &mut R,
+    ) -> Result<Account, Box<dyn BankError>> {
+        if account_type == AccountType::System {
+            return Err(Box::new(AccountError::new(
+                "System accounts can't be created".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        if holders.is_empty() {
+            return Err(Box::new(AccountError::new(
+                "An account needs at least one holder".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        if let Some(missing) = conn.find_missing_customers(holders).await?.first() {
+            return Err(Box::new(AccountError::new(
+                format!("Customer [{}] not found", missing),
+                ErrorKind::NotFound,
+            )));
+        }
+
+        if branch_code > MAX_BRANCH_CODE {
+            println!("Invalid branch code [{}]", branch_code);
+            return Err(unexpected_error());
+        }
+
+        let sequence = conn.next_account_sequence().await?;
+
+        let account = Account::new(
+            account_number(branch_code, sequence),
+            account_type,
+            currency,
+        );
+
+        conn.insert_account(&account, holders).await?;
+
+        Ok(account)
+    }
+
+    pub async fn list_accounts(&self) -> Result<Vec<Account>, Box<dyn BankError>> {
+        let mut conn = self.acquire().await?;
 
-        match account {
-            Ok(account) => match account {
-                Some(account) => Ok(account),
-                None => Err(Box::new(AccountError::new(
-                    format!("Account [{}] not found", number),
-                    axum::http::StatusCode::NOT_FOUND,
-                ))),
-            },
-            Err(e) => Err(Box::new(AccountError::new(
-                e.to_string(),
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+        conn.list_accounts().await
+    }
+
+    /// Locks the given accounts until the end of the current unit of work.
+    ///
+    /// Accounts are always locked ordered by id, so two units of work locking the same set of
+    /// accounts can't deadlock each other.
+    pub async fn lock_accounts<R: AccountRepository + ?Sized>(
+        accounts: &[&Account],
+        conn: &mut R,
+    ) -> Result<(), Box<dyn BankError>> {
+        let mut ids: Vec<uuid::Uuid> = accounts.iter().map(|account| *account.id()).collect();
+        ids.sort();
+        ids.dedup();
+
+        conn.lock_accounts(&ids).await
+    }
+
+    /// Sum of the ledger postings of the account, in its currency. Customer accounts keep it on
+    /// their row as postings are booked, negative while they use the overdraft.
+    pub async fn get_balance<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<Money, Box<dyn BankError>> {
+        let balance = conn.get_balance(account.id()).await?;
+
+        Ok(Money::new(balance, account.currency))
+    }
+
+    /// The system account of the currency, each currency has its own set
+    pub async fn get_system_account<R: AccountRepository + ?Sized>(
+        system_account: SystemAccount,
+        currency: Currency,
+        conn: &mut R,
+    ) -> Result<Account, Box<dyn BankError>> {
+        match conn.find_system_account(system_account, currency).await? {
+            Some(account) => Ok(account),
+            None => {
+                println!(
+                    "System account [{}] in {} not found",
+                    system_account.code(),
+                    currency
+                );
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Balance minus the funds reserved by active holds, what the account can actually spend
+    pub async fn get_available_balance<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<Money, Box<dyn BankError>> {
+        let available = conn.get_available_balance(account.id()).await?;
+
+        Ok(Money::new(available, account.currency))
+    }
+
+    /// Available balance plus the overdraft limit, the most a withdraw or transfer may take
+    pub async fn get_spendable_amount<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<Money, Box<dyn BankError>> {
+        let available_balance = AccountManager::get_available_balance(account, conn).await?;
+        let limits = AccountManager::get_limits(account, conn).await?;
+
+        Ok(available_balance.checked_add(&Money::new(limits.overdraft_limit, account.currency))?)
+    }
+
+    pub async fn get_limits<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<AccountLimits, Box<dyn BankError>> {
+        conn.get_limits(account.id()).await
+    }
+
+    /// Lowering the limit below what is already in use is allowed, the account just can't take
+    /// new debits until it is back within the limit
+    pub async fn update_limits(
+        &self,
+        account: &Account,
+        limits: &AccountLimits,
+    ) -> Result<AccountLimits, Box<dyn BankError>> {
+        let mut conn = self.acquire().await?;
+
+        AccountManager::set_limits(account, limits, &mut *conn).await
+    }
+
+    /// Same as `update_limits`, on the caller's unit of work
+    pub(crate) async fn set_limits<R: AccountRepository + ?Sized>(
+        account: &Account,
+        limits: &AccountLimits,
+        conn: &mut R,
+    ) -> Result<AccountLimits, Box<dyn BankError>> {
+        if limits.overdraft_limit < 0 {
+            return Err(Box::new(AccountError::new(
+                "Overdraft limit can't be negative".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        match conn.update_limits(account.id(), limits).await? {
+            Some(limits) => Ok(limits),
+            None => Err(Box::new(AccountError::new(
+                format!("Account [{}] not found", account.number()),
+                ErrorKind::NotFound,
             ))),
         }
     }
 
-    pub async fn create_account(&self) -> Result<Account, Box<dyn BankError>> {
-        let mut tx = match self.db_pool.begin().await {
-            Ok(tx) => tx,
+    /// Adds `amount` to the stored balance of a customer account, it must run on the same unit of
+    /// work that books the matching postings on the ledger
+    pub async fn apply_balance_change<R: AccountRepository + ?Sized>(
+        account_id: &uuid::Uuid,
+        amount: i64,
+        conn: &mut R,
+    ) -> Result<(), Box<dyn BankError>> {
+        conn.apply_balance_change(account_id, amount).await
+    }
+
+    /// Current status, read from the storage instead of the possibly stale `Account`
+    pub async fn get_status<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<AccountStatus, Box<dyn BankError>> {
+        conn.get_status(account.id()).await
+    }
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'a, sqlx::Postgres>, Box<dyn BankError>> {
+        match self.db_pool.begin().await {
+            Ok(tx) => Ok(tx),
             Err(e) => {
                 println!("Error starting database transaction: {}", e);
-                return Err(Box::new(AccountError::new(
-                    "An unexpected error happened, please try again".to_string(),
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                )));
+                Err(unexpected_error())
             }
-        };
+        }
+    }
 
-        let conn = match tx.acquire().await {
-            Ok(conn) => conn,
+    async fn commit(tx: sqlx::Transaction<'a, sqlx::Postgres>) -> Result<(), Box<dyn BankError>> {
+        match tx.commit().await {
+            Ok(_) => Ok(()),
             Err(e) => {
-                println!("Error getting database connection: {}", e);
-                return Err(Box::new(AccountError::new(
-                    "An unexpected error happened, please try again".to_string(),
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                )));
+                println!("Error committing transaction: {}", e);
+                Err(unexpected_error())
             }
-        };
+        }
+    }
 
-        let latest_number = sqlx::query!("SELECT number FROM account ORDER BY number DESC LIMIT 1")
-            .fetch_optional(&mut *conn)
-            .await;
+    /// Checks the transition against the current status, the account must already be locked
+    async fn ensure_transition<R: AccountRepository + ?Sized>(
+        account: &Account,
+        status: AccountStatus,
+        reason: &str,
+        conn: &mut R,
+    ) -> Result<AccountStatus, Box<dyn BankError>> {
+        if reason.trim().is_empty() || reason.len() > 255 {
+            return Err(Box::new(AccountError::new(
+                "Status change reason must have between 1 and 255 characters".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
 
-        let latest_number = match latest_number {
-            Ok(latest_number) => latest_number,
-            Err(e) => {
-                println!("Error getting latest account number: {}", e);
+        let current = AccountManager::get_status(account, conn).await?;
+
+        if !current.can_transition_to(status) {
+            return Err(Box::new(AccountError::new(
+                format!(
+                    "Account [{}] can't go from {} to {}",
+                    account.number(),
+                    current.as_str(),
+                    status.as_str()
+                ),
+                ErrorKind::Conflict,
+            )));
+        }
+
+        Ok(current)
+    }
+
+    async fn change_status(
+        &self,
+        account: &Account,
+        status: AccountStatus,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        let mut tx = self.begin().await?;
+
+        let change =
+            AccountManager::change_account_status(account, status, reason, &mut *tx).await?;
+
+        AccountManager::commit(tx).await?;
+
+        Ok(change)
+    }
+
+    /// Freezes or unfreezes the account on the caller's unit of work
+    pub(crate) async fn change_account_status<R: AccountRepository + ?Sized>(
+        account: &Account,
+        status: AccountStatus,
+        reason: &str,
+        conn: &mut R,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        AccountManager::lock_accounts(&[account], conn).await?;
+        let current = AccountManager::ensure_transition(account, status, reason, conn).await?;
+
+        conn.record_status_change(account, current, status, reason, None)
+            .await
+    }
+
+    /// Blocks every outgoing movement, the account keeps receiving money
+    pub async fn freeze(
+        &self,
+        account: &Account,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        self.change_status(account, AccountStatus::Frozen, reason)
+            .await
+    }
+
+    pub async fn unfreeze(
+        &self,
+        account: &Account,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        self.change_status(account, AccountStatus::Active, reason)
+            .await
+    }
+
+    /// Closes the account for good. The balance must be zero, unless `sweep_to` is given, in which
+    /// case whatever is left is transferred there first.
+    ///
+    /// Accounts with active holds or a negative balance can't be closed.
+    pub async fn close(
+        &self,
+        account: &Account,
+        sweep_to: Option<&Account>,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        if let Some(sweep_to) = sweep_to {
+            if sweep_to.id() == account.id() {
                 return Err(Box::new(AccountError::new(
-                    "An unexpected error happened, please try again".to_string(),
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    "Can't sweep the balance to the account being closed".to_string(),
+                    ErrorKind::InvalidInput,
                 )));
             }
-        };
 
-        let account: Account = Account::new(match latest_number {
-            None => 1,
-            Some(latest_number) => latest_number.number + 1,
-        });
+            TransactionManager::ensure_same_currency(account, sweep_to)?;
+        }
 
-        let res = sqlx::query!(
-            "INSERT INTO account (id, number) VALUES ($1, $2)",
-            account.id(),
-            account.number()
+        let mut tx = self.begin().await?;
+
+        let mut accounts = vec![account];
+        accounts.extend(sweep_to);
+        AccountManager::lock_accounts(&accounts, &mut *tx).await?;
+
+        let current =
+            AccountManager::ensure_transition(account, AccountStatus::Closed, reason, &mut *tx)
+                .await?;
+
+        let active_holds = sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!" FROM hold WHERE account_id = $1 AND status = 'active' AND expires_at > NOW()"#,
+            account.id()
         )
-        .execute(conn)
+        .fetch_one(&mut *tx)
         .await;
 
-        match res {
-            Ok(_) => {
-                match tx.commit().await {
-                    Ok(_) => {
-                        return Ok(account);
-                    }
-                    Err(e) => {
-                        println!("Error committing transaction: {}", e);
-                        return Err(Box::new(AccountError::new(
-                            "An unexpected error happened, please try again".to_string(),
-                            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        )));
-                    }
-                };
+        match active_holds {
+            Ok(row) if row.count > 0 => {
+                return Err(Box::new(AccountError::new(
+                    format!("Account [{}] has active holds", account.number()),
+                    ErrorKind::Conflict,
+                )))
             }
+            Ok(_) => {}
             Err(e) => {
-                if let Err(e) = tx.rollback().await {
-                    println!("Error rolling back transaction: {}", e);
-                }
-
-                return Err(Box::new(AccountError::new(
-                    e.to_string(),
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                )));
+                println!("Error counting active holds: {}", e);
+                return Err(unexpected_error());
             }
         }
-    }
 
-    pub async fn list_accounts(&self) -> Result<Vec<Account>, Box<dyn BankError>> {
-        let accounts = sqlx::query_as!(Account, "SELECT id, number FROM account")
-            .fetch_all(self.db_pool)
-            .await;
+        let balance = AccountManager::get_balance(account, &mut *tx)
+            .await?
+            .amount_minor;
 
-        match accounts {
-            Ok(accounts) => Ok(accounts),
-            Err(e) => Err(Box::new(AccountError::new(
-                e.to_string(),
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))),
+        if balance < 0 {
+            return Err(Box::new(AccountError::new(
+                format!(
+                    "Account [{}] has a negative balance, it must be settled before closing",
+                    account.number()
+                ),
+                ErrorKind::Conflict,
+            )));
         }
+
+        let sweep_journal_entry_id = match (balance, sweep_to) {
+            (0, _) => None,
+            (_, None) => {
+                return Err(Box::new(AccountError::new(
+                    format!(
+                        "Account [{}] still has a balance of {}, it must be zero or swept to another account",
+                        account.number(),
+                        Money::new(balance, account.currency)
+                    ),
+                    ErrorKind::Conflict,
+                )))
+            }
+            (_, Some(sweep_to)) => {
+                TransactionManager::ensure_can_receive(sweep_to, &mut *tx).await?;
+
+                // Zero and negative balances were handled above
+                let amount = Amount::new(balance)?;
+
+                let receipt = TransactionManager::create_transfer(
+                    amount,
+                    account,
+                    sweep_to,
+                    Vec::new(),
+                    &mut *tx,
+                )
+                .await?;
+
+                Some(receipt.journal_entry_id)
+            }
+        };
+
+        let change = tx
+            .record_status_change(
+                account,
+                current,
+                AccountStatus::Closed,
+                reason,
+                sweep_journal_entry_id,
+            )
+            .await?;
+
+        AccountManager::commit(tx).await?;
+
+        Ok(change)
     }
 
-    pub async fn get_balance(
-        account: &Account,
-        conn: &mut sqlx::PgConnection,
-    ) -> Result<BigDecimal, Box<dyn BankError>> {
-        let balance = sqlx::query!(
-            "SELECT SUM(amount) FROM transaction WHERE account_id = $1",
-            account.id()
+    /// Recomputes every customer balance from the `transaction` table and returns the accounts
+    /// where it doesn't match the stored one
+    pub async fn find_balance_drifts(&self) -> Result<Vec<BalanceDrift>, Box<dyn BankError>> {
+        let drifts = sqlx::query_as!(
+            BalanceDrift,
+            r#"SELECT account.id AS account_id, account.number AS account_number, account.balance AS stored_balance,
+            COALESCE(SUM(transaction.amount), 0) AS "ledger_balance!"
+            FROM account
+            LEFT JOIN transaction ON transaction.account_id = account.id
+            WHERE account.type <> 'system'
+            GROUP BY account.id
+            HAVING account.balance <> COALESCE(SUM(transaction.amount), 0)
+            ORDER BY account.number"#
         )
-        .fetch_one(conn)
+        .fetch_all(self.db_pool)
         .await;
 
-        match balance {
-            Ok(balance) => {
-                let balance = balance.sum.unwrap_or(0.into());
-                Ok(balance)
+        match drifts {
+            Ok(drifts) => Ok(drifts),
+            Err(e) => {
+                println!("Error checking balances: {}", e);
+                Err(Box::new(AccountError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )))
             }
-            Err(_) => Err(Box::new(AccountError::new(
-                "Failed to get balance".to_string(),
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::internal::test_util::get_conn_with_new_db;
+    use super::{account_number, AccountType, ErrorKind, TransactionManager, DEFAULT_BRANCH_CODE};
+    use crate::internal::money::domain::Amount;
+    use crate::internal::test_util::{create_customer, get_conn_with_new_db};
+    use crate::internal::transaction::domain::Transaction;
 
     #[tokio::test]
     async fn test_create_account() {
@@ -167,13 +605,158 @@ mod tests {
 
         let account_manager = super::AccountManager::new(database.get_pool());
 
-        let account = account_manager.create_account().await.unwrap();
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(account.number(), &account_number(DEFAULT_BRANCH_CODE, 1));
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(account.number(), &account_number(DEFAULT_BRANCH_CODE, 2));
+    }
+
+    #[tokio::test]
+    async fn test_account_numbers() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = super::AccountManager::new(db_pool);
+        let branch_manager = super::AccountManager::new(db_pool).with_branch_code(42);
+
+        let holders = [create_customer(db_pool).await];
+        let open = || account_manager.create_account(AccountType::Checking, &holders);
+
+        // Concurrent openings never get the same number
+        let (first, second, third, fourth) = tokio::join!(open(), open(), open(), open());
+        let mut numbers: Vec<i64> = [first, second, third, fourth]
+            .into_iter()
+            .map(|account| *account.unwrap().number())
+            .collect();
+        numbers.sort();
+        numbers.dedup();
+        assert_eq!(numbers.len(), 4);
+
+        let account = branch_manager
+            .create_account(AccountType::Checking, &holders)
+            .await
+            .unwrap();
+        assert_eq!(account.number(), &account_number(42, 5));
+
+        let found = account_manager
+            .get_account_from_number(*account.number())
+            .await
+            .unwrap();
+        assert_eq!(found.id(), account.id());
+
+        let typo = account.number() + if account.number() % 10 == 9 { -1 } else { 1 };
+        let result = account_manager.get_account_from_number(typo).await;
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!("Invalid account number [{}]", typo)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_account_from_iban() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = super::AccountManager::new(db_pool);
+        let holders = [create_customer(db_pool).await];
+
+        let checking = account_manager
+            .create_account(AccountType::Checking, &holders)
+            .await
+            .unwrap();
+        let savings = account_manager
+            .create_account(AccountType::Savings, &holders)
+            .await
+            .unwrap();
+
+        let iban = account_manager.iban(&checking);
+        assert_eq!(iban, "BR4900000001000010000482712C1");
+
+        // Printed form, grouped by 4 and in any case
+        let printed = iban
+            .to_lowercase()
+            .chars()
+            .collect::<Vec<char>>()
+            .chunks(4)
+            .map(|chunk| chunk.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join(" ");
+        let found = account_manager
+            .get_account_from_iban(&printed)
+            .await
+            .unwrap();
+        assert_eq!(found.id(), checking.id());
+
+        let found = account_manager
+            .get_account_from_iban(&account_manager.iban(&savings))
+            .await
+            .unwrap();
+        assert_eq!(found.id(), savings.id());
+
+        let result = account_manager
+            .get_account_from_iban("BR4900000001000010000482713C1")
+            .await;
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        // A valid IBAN with the savings number and the checking type
+        let mismatched = super::Account::from_existing(
+            *savings.id(),
+            *savings.number(),
+            AccountType::Checking,
+            *savings.status(),
+            *savings.currency(),
+        );
+        let result = account_manager
+            .get_account_from_iban(&account_manager.iban(&mismatched))
+            .await;
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::NotFound);
+
+        let other_bank = super::AccountManager::new(db_pool).with_bank_code(2);
+        let result = other_bank.get_account_from_iban(&iban).await;
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_create_account_requires_holders() {
+        let database = get_conn_with_new_db().await;
+
+        let account_manager = super::AccountManager::new(database.get_pool());
+
+        let result = account_manager
+            .create_account(AccountType::Checking, &[])
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
 
-        assert_eq!(account.number(), &1);
+        let unknown = uuid::Uuid::now_v7();
+        let result = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await, unknown],
+            )
+            .await;
 
-        let account = account_manager.create_account().await.unwrap();
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!("Customer [{}] not found", unknown)
+        );
 
-        assert_eq!(account.number(), &2);
+        assert!(account_manager.list_accounts().await.unwrap().is_empty());
     }
 
     #[tokio::test]
@@ -182,15 +765,22 @@ mod tests {
 
         let account_manager = super::AccountManager::new(database.get_pool());
 
-        let account = account_manager.create_account().await.unwrap();
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let mut conn = database.get_pool().acquire().await.unwrap();
 
-        let balance = super::AccountManager::get_balance(&account, &mut conn)
+        let balance = super::AccountManager::get_balance(&account, &mut *conn)
             .await
-            .unwrap();
+            .unwrap()
+            .amount_minor;
 
-        assert_eq!(balance, 0.into());
+        assert_eq!(balance, 0);
     }
 
     #[tokio::test]
@@ -199,19 +789,329 @@ mod tests {
 
         let account_manager = super::AccountManager::new(database.get_pool());
 
-        account_manager.create_account().await.unwrap();
+        account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let accounts = account_manager.list_accounts().await.unwrap();
 
         assert_eq!(accounts.len(), 1);
-        assert_eq!(accounts[0].number(), &1);
+        assert_eq!(
+            accounts[0].number(),
+            &account_number(DEFAULT_BRANCH_CODE, 1)
+        );
 
-        account_manager.create_account().await.unwrap();
+        account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let accounts = account_manager.list_accounts().await.unwrap();
 
         assert_eq!(accounts.len(), 2);
-        assert_eq!(accounts[0].number(), &1);
-        assert_eq!(accounts[1].number(), &2);
+        assert_eq!(
+            accounts[0].number(),
+            &account_number(DEFAULT_BRANCH_CODE, 1)
+        );
+        assert_eq!(
+            accounts[1].number(),
+            &account_number(DEFAULT_BRANCH_CODE, 2)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_limits() {
+        let database = get_conn_with_new_db().await;
+
+        let account_manager = super::AccountManager::new(database.get_pool());
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let mut conn = database.get_pool().acquire().await.unwrap();
+
+        let limits = super::AccountManager::get_limits(&account, &mut *conn)
+            .await
+            .unwrap();
+
+        assert_eq!(limits.overdraft_limit, 0);
+
+        let limits = account_manager
+            .update_limits(
+                &account,
+                &super::AccountLimits {
+                    overdraft_limit: 500,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(limits.overdraft_limit, 500);
+
+        let spendable = super::AccountManager::get_spendable_amount(&account, &mut *conn)
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(spendable, 500);
+
+        let result = account_manager
+            .update_limits(
+                &account,
+                &super::AccountLimits {
+                    overdraft_limit: -1,
+                },
+            )
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_find_balance_drifts() {
+        let database = get_conn_with_new_db().await;
+
+        let account_manager = super::AccountManager::new(database.get_pool());
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        assert!(account_manager
+            .find_balance_drifts()
+            .await
+            .unwrap()
+            .is_empty());
+
+        sqlx::query!(
+            "UPDATE account SET balance = 42 WHERE id = $1",
+            account.id()
+        )
+        .execute(database.get_pool())
+        .await
+        .unwrap();
+
+        let drifts = account_manager.find_balance_drifts().await.unwrap();
+
+        assert_eq!(drifts.len(), 1);
+        assert_eq!(&drifts[0].account_number, account.number());
+        assert_eq!(drifts[0].stored_balance, 42);
+        assert_eq!(drifts[0].ledger_balance, 0.into());
+    }
+
+    #[tokio::test]
+    async fn test_status_transitions() {
+        let database = get_conn_with_new_db().await;
+
+        let account_manager = super::AccountManager::new(database.get_pool());
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(account.status(), &super::AccountStatus::Active);
+
+        let change = account_manager
+            .freeze(&account, "Suspicious activity")
+            .await
+            .unwrap();
+
+        assert_eq!(change.from_status, super::AccountStatus::Active);
+        assert_eq!(change.to_status, super::AccountStatus::Frozen);
+        assert_eq!(change.reason, "Suspicious activity");
+
+        // Frozen accounts must be unfrozen before closing
+        let result = account_manager
+            .close(&account, None, "Customer request")
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!(
+                "Account [{}] can't go from frozen to closed",
+                account.number()
+            )
+        );
+
+        account_manager
+            .unfreeze(&account, "Activity verified")
+            .await
+            .unwrap();
+
+        let result = account_manager.freeze(&account, " ").await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        account_manager
+            .close(&account, None, "Customer request")
+            .await
+            .unwrap();
+
+        let result = account_manager.unfreeze(&account, "Reopen").await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!(
+                "Account [{}] can't go from closed to active",
+                account.number()
+            )
+        );
+
+        let account = account_manager
+            .get_account_from_number(*account.number())
+            .await
+            .unwrap();
+
+        assert_eq!(account.status(), &super::AccountStatus::Closed);
+
+        let audit = sqlx::query!(
+            "SELECT from_status, to_status FROM account_status_change WHERE account_id = $1 ORDER BY id",
+            account.id()
+        )
+        .fetch_all(database.get_pool())
+        .await
+        .unwrap();
+
+        let audit: Vec<(&str, &str)> = audit
+            .iter()
+            .map(|row| (row.from_status.as_str(), row.to_status.as_str()))
+            .collect();
+
+        assert_eq!(
+            audit,
+            vec![
+                ("active", "frozen"),
+                ("frozen", "active"),
+                ("active", "closed")
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_close_requires_zero_balance() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = super::AccountManager::new(db_pool);
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let result = account_manager
+            .close(&account, None, "Customer request")
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!(
+                "Account [{}] still has a balance of 1.00 BRL, it must be zero or swept to another account",
+                account.number()
+            )
+        );
+
+        let result = account_manager
+            .close(&account, Some(&account), "Customer request")
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        let account = account_manager
+            .get_account_from_number(*account.number())
+            .await
+            .unwrap();
+
+        assert_eq!(account.status(), &super::AccountStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn test_close_sweeps_balance() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = super::AccountManager::new(db_pool);
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let sweep_to = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let change = account_manager
+            .close(&account, Some(&sweep_to), "Customer request")
+            .await
+            .unwrap();
+
+        assert!(change.sweep_journal_entry_id.is_some());
+
+        let mut conn = db_pool.acquire().await.unwrap();
+
+        let balance = super::AccountManager::get_balance(&account, &mut *conn)
+            .await
+            .unwrap()
+            .amount_minor;
+        let swept = super::AccountManager::get_balance(&sweep_to, &mut *conn)
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 0);
+        assert_eq!(swept, 100);
     }
 }
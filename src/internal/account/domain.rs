@@ -1,22 +1,49 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::internal::{customer::domain::check_digit, error::ErrorKind, money::domain::Currency};
+
+use super::error::AccountError;
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Account {
     pub(crate) id: Uuid,
     pub(crate) number: i64,
+    #[serde(rename = "type")]
+    pub(crate) account_type: AccountType,
+    /// Status when the account was read, the transactions check it again under the row lock
+    pub(crate) status: AccountStatus,
+    /// Every amount booked on the account is in this currency
+    pub(crate) currency: Currency,
 }
 
 impl Account {
-    pub fn new(number: i64) -> Self {
+    pub fn new(number: i64, account_type: AccountType, currency: Currency) -> Self {
         Self {
             id: Uuid::now_v7(),
             number,
+            account_type,
+            status: AccountStatus::Active,
+            currency,
         }
     }
 
-    pub fn from_existing(id: Uuid, number: i64) -> Self {
-        Self { id, number }
+    pub fn from_existing(
+        id: Uuid,
+        number: i64,
+        account_type: AccountType,
+        status: AccountStatus,
+        currency: Currency,
+    ) -> Self {
+        Self {
+            id,
+            number,
+            account_type,
+            status,
+            currency,
+        }
     }
 
     pub fn number(&self) -> &i64 {
@@ -26,4 +53,376 @@ impl Account {
     pub fn id(&self) -> &Uuid {
         &self.id
     }
+
+    pub fn account_type(&self) -> &AccountType {
+        &self.account_type
+    }
+
+    pub fn status(&self) -> &AccountStatus {
+        &self.status
+    }
+
+    pub fn currency(&self) -> &Currency {
+        &self.currency
+    }
+
+    /// IBAN in the Brazilian layout, bank, branch and account followed by the account type and
+    /// the holder position, always `1`
+    pub fn iban(&self, bank_code: u32) -> String {
+        let account_type = match self.account_type {
+            AccountType::Savings => 'P',
+            AccountType::Checking | AccountType::System => 'C',
+        };
+        let bban = format!(
+            "{:08}{:05}{:010}{}1",
+            bank_code,
+            self.number / ACCOUNT_NUMBER_BRANCH_FACTOR,
+            self.number % ACCOUNT_NUMBER_BRANCH_FACTOR,
+            account_type
+        );
+
+        format!(
+            "{}{:02}{}",
+            IBAN_COUNTRY_CODE,
+            iban_check_digits(IBAN_COUNTRY_CODE, &bban),
+            bban
+        )
+    }
+}
+
+/// Savings accounts allow this many withdraws and outgoing transfers per calendar month
+pub const SAVINGS_MONTHLY_WITHDRAW_LIMIT: i64 = 6;
+
+/// Branch of the accounts opened without one
+pub const DEFAULT_BRANCH_CODE: u16 = 1;
+
+pub const MAX_BRANCH_CODE: u16 = 9999;
+
+/// Numbers allocated from `account_number_seq` are spread over 8 digits
+const ACCOUNT_SEQUENCE_MODULUS: i64 = 100_000_000;
+
+/// The branch code is what is left of the account number above its last 9 digits
+const ACCOUNT_NUMBER_BRANCH_FACTOR: i64 = ACCOUNT_SEQUENCE_MODULUS * 10;
+
+/// Coprime with 10, so multiplying by it modulo 10^8 gives every sequence a distinct number
+/// without consecutive accounts getting consecutive numbers
+const ACCOUNT_SEQUENCE_MULTIPLIER: i64 = 48_271;
+
+/// Weights of the 4 branch digits and the 8 sequence digits
+const ACCOUNT_NUMBER_WEIGHTS: [u32; 12] = [5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2];
+
+fn account_number_check_digit(base: i64) -> i64 {
+    let digits: Vec<u32> = format!("{:012}", base)
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .collect();
+
+    check_digit(&digits, &ACCOUNT_NUMBER_WEIGHTS).into()
+}
+
+/// Number of a customer account, the branch code, 8 digits derived from `sequence` and a modulo
+/// 11 check digit, `0001 00048271 2` for the first account of branch 1
+pub fn account_number(branch_code: u16, sequence: i64) -> i64 {
+    let base = i64::from(branch_code) * ACCOUNT_SEQUENCE_MODULUS
+        + (sequence * ACCOUNT_SEQUENCE_MULTIPLIER) % ACCOUNT_SEQUENCE_MODULUS;
+
+    base * 10 + account_number_check_digit(base)
+}
+
+/// Rejects numbers that can't belong to a customer account, typos included, without a lookup
+pub fn validate_account_number(number: i64) -> Result<(), AccountError> {
+    let base = number / 10;
+
+    if number <= 0
+        || base / ACCOUNT_SEQUENCE_MODULUS > i64::from(MAX_BRANCH_CODE)
+        || account_number_check_digit(base) != number % 10
+    {
+        return Err(AccountError::new(
+            format!("Invalid account number [{}]", number),
+            ErrorKind::InvalidInput,
+        ));
+    }
+
+    Ok(())
+}
+
+/// Bank of the IBANs when none is configured, the 8 digit ISPB of the Brazilian layout
+pub const DEFAULT_BANK_CODE: u32 = 1;
+
+const IBAN_COUNTRY_CODE: &str = "BR";
+
+/// Country, check digits, 8 bank digits, 5 branch digits, 10 account digits, type and holder
+const IBAN_LENGTH: usize = 29;
+
+/// Remainder by 97 of the IBAN with its first 4 characters moved to the end and letters counting
+/// as 10 to 35, computed piecewise so any length fits. None on characters other than 0-9 and A-Z.
+fn iban_remainder(iban: &str) -> Option<u32> {
+    let (head, tail) = iban.split_at(4);
+
+    tail.chars()
+        .chain(head.chars())
+        .try_fold(0, |remainder, c| {
+            let value = c.to_digit(36)?;
+            let shift = if value < 10 { 10 } else { 100 };
+
+            Some((remainder * shift + value) % 97)
+        })
+}
+
+/// ISO 13616 check digits of `bban` in `country_code`
+pub fn iban_check_digits(country_code: &str, bban: &str) -> u32 {
+    98 - iban_remainder(&format!("{}00{}", country_code, bban)).unwrap_or(0)
+}
+
+/// Accepts the printed form, with spaces and any case, and returns the electronic one once the
+/// ISO 13616 mod-97 check passes
+pub fn validate_iban(iban: &str) -> Result<String, AccountError> {
+    let normalized = iban
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>()
+        .to_uppercase();
+
+    let valid = (15..=34).contains(&normalized.len())
+        && normalized.chars().all(|c| c.is_ascii_alphanumeric())
+        && normalized[..2].chars().all(|c| c.is_ascii_alphabetic())
+        && normalized[2..4].chars().all(|c| c.is_ascii_digit())
+        && iban_remainder(&normalized) == Some(1);
+
+    if !valid {
+        return Err(AccountError::new(
+            format!("Invalid IBAN [{}]", iban),
+            ErrorKind::InvalidInput,
+        ));
+    }
+
+    Ok(normalized)
+}
+
+/// Number of the account of `bank_code` the IBAN points to, its check digit still unchecked
+pub fn account_number_from_iban(iban: &str, bank_code: u32) -> Result<i64, AccountError> {
+    let normalized = validate_iban(iban)?;
+
+    let not_ours = || {
+        AccountError::new(
+            format!("IBAN [{}] is not of an account of this bank", normalized),
+            ErrorKind::NotFound,
+        )
+    };
+
+    if normalized.len() != IBAN_LENGTH
+        || &normalized[..2] != IBAN_COUNTRY_CODE
+        || normalized[4..12] != format!("{:08}", bank_code)
+    {
+        return Err(not_ours());
+    }
+
+    let branch_code: i64 = normalized[12..17].parse().map_err(|_| not_ours())?;
+    let account: i64 = normalized[17..27].parse().map_err(|_| not_ours())?;
+
+    Ok(branch_code * ACCOUNT_NUMBER_BRANCH_FACTOR + account)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AccountType {
+    Checking,
+    /// Limited to `SAVINGS_MONTHLY_WITHDRAW_LIMIT` withdraws per month
+    Savings,
+    /// Internal accounts of the bank, never exposed to customers
+    System,
+}
+
+impl AccountType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Checking => "checking",
+            AccountType::Savings => "savings",
+            AccountType::System => "system",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum AccountStatus {
+    Active,
+    /// Can still receive money, but can't send it
+    Frozen,
+    /// Rejects every movement, there is no way back
+    Closed,
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Frozen => "frozen",
+            AccountStatus::Closed => "closed",
+        }
+    }
+
+    pub fn can_transition_to(&self, status: AccountStatus) -> bool {
+        matches!(
+            (self, status),
+            (AccountStatus::Active, AccountStatus::Frozen)
+                | (AccountStatus::Frozen, AccountStatus::Active)
+                | (AccountStatus::Active, AccountStatus::Closed)
+        )
+    }
+}
+
+/// Audit record of a status transition
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountStatusChange {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    pub from_status: AccountStatus,
+    pub to_status: AccountStatus,
+    pub reason: String,
+    /// Journal entry that moved the remaining balance out when closing the account
+    pub sweep_journal_entry_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Limits configured for an account
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountLimits {
+    /// How far below zero withdraws and transfers may take the balance
+    pub overdraft_limit: i64,
+}
+
+/// An account whose stored balance doesn't match the sum of its transactions
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BalanceDrift {
+    pub account_id: Uuid,
+    pub account_number: i64,
+    pub stored_balance: i64,
+    pub ledger_balance: BigDecimal,
+}
+
+/// Internal accounts on the other side of the money entering, leaving or being kept by the bank
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemAccount {
+    CashVault,
+    FeeIncome,
+    /// Holds movements whose other side is unknown until someone reconciles them
+    Suspense,
+    /// Receives the captured holds, until they are paid to the merchants
+    Settlement,
+    /// Pays the interest credited to savings accounts
+    InterestExpense,
+    /// Currency bought and sold by conversions, valued at the market rate
+    FxPosition,
+    /// Difference between the market rate and the rate given to customers
+    FxGainLoss,
+}
+
+impl SystemAccount {
+    /// Every system account, numbered `-1` to `-7` in `DEFAULT_CURRENCY`
+    pub const ALL: [SystemAccount; 7] = [
+        SystemAccount::CashVault,
+        SystemAccount::FeeIncome,
+        SystemAccount::Suspense,
+        SystemAccount::Settlement,
+        SystemAccount::InterestExpense,
+        SystemAccount::FxPosition,
+        SystemAccount::FxGainLoss,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            SystemAccount::CashVault => "cash_vault",
+            SystemAccount::FeeIncome => "fee_income",
+            SystemAccount::Suspense => "suspense",
+            SystemAccount::Settlement => "settlement",
+            SystemAccount::InterestExpense => "interest_expense",
+            SystemAccount::FxPosition => "fx_position",
+            SystemAccount::FxGainLoss => "fx_gain_loss",
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "cash_vault" => Some(SystemAccount::CashVault),
+            "fee_income" => Some(SystemAccount::FeeIncome),
+            "suspense" => Some(SystemAccount::Suspense),
+            "settlement" => Some(SystemAccount::Settlement),
+            "interest_expense" => Some(SystemAccount::InterestExpense),
+            "fx_position" => Some(SystemAccount::FxPosition),
+            "fx_gain_loss" => Some(SystemAccount::FxGainLoss),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_number_check_digit() {
+        // Branch 0001, sequence 1 spread to 00048271 and check digit 2
+        assert_eq!(account_number(1, 1), 1000482712);
+        assert_eq!(account_number(1, 2), 1000965420);
+        assert_eq!(account_number(9999, 99_999_999), 9999999517290);
+
+        for sequence in 1..1000 {
+            let number = account_number(42, sequence);
+            assert!(validate_account_number(number).is_ok(), "{}", number);
+
+            let wrong_check_digit = number - number % 10 + (number + 1) % 10;
+            assert!(validate_account_number(wrong_check_digit).is_err());
+        }
+
+        // Swapped digits and numbers outside the layout
+        for invalid in [1000482172, 0, 1, -1000482712, 100000000000000] {
+            assert!(validate_account_number(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_iban() {
+        for valid in [
+            "BR1800360305000010009795493C1",
+            "br15 0000 0000 0000 1093 2840 814 p2",
+            "GB82 WEST 1234 5698 7654 32",
+        ] {
+            assert!(validate_iban(valid).is_ok(), "{}", valid);
+        }
+
+        for invalid in [
+            "BR1900360305000010009795493C1",
+            "GB82 WEST 1234 5698 7654 23",
+            "GB82-WEST-1234-5698-7654-32",
+            "GB82",
+            "",
+        ] {
+            assert!(validate_iban(invalid).is_err(), "{}", invalid);
+        }
+
+        let account = Account::new(account_number(1, 1), AccountType::Checking, Currency::Brl);
+        let iban = account.iban(DEFAULT_BANK_CODE);
+        assert_eq!(iban, "BR4900000001000010000482712C1");
+        assert_eq!(
+            account_number_from_iban(&iban.to_lowercase(), DEFAULT_BANK_CODE).unwrap(),
+            account_number(1, 1)
+        );
+
+        let savings = Account::new(account_number(42, 7), AccountType::Savings, Currency::Brl);
+        let iban = savings.iban(DEFAULT_BANK_CODE);
+        assert!(iban.ends_with("P1"));
+        assert_eq!(
+            account_number_from_iban(&iban, DEFAULT_BANK_CODE).unwrap(),
+            account_number(42, 7)
+        );
+
+        // Valid IBANs of other banks and countries
+        assert!(account_number_from_iban(&iban, 2).is_err());
+        assert!(account_number_from_iban("BR1800360305000010009795493C1", 1).is_err());
+        assert!(account_number_from_iban("GB82WEST12345698765432", 1).is_err());
+    }
 }
@@ -1,14 +1,14 @@
-use crate::internal::error::BankError;
+use crate::internal::error::{BankError, ErrorKind};
 
 #[derive(Debug)]
 pub struct AccountError {
     message: String,
-    status: axum::http::StatusCode,
+    kind: ErrorKind,
 }
 
 impl AccountError {
-    pub fn new(message: String, status: axum::http::StatusCode) -> Self {
-        Self { message, status }
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
     }
 }
 
@@ -16,7 +16,13 @@ impl BankError for AccountError {
     fn message(&self) -> &str {
         &self.message
     }
-    fn status(&self) -> &axum::http::StatusCode {
-        &self.status
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<AccountError> for Box<dyn BankError> {
+    fn from(error: AccountError) -> Self {
+        Box::new(error)
     }
 }
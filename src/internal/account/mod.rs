@@ -1,3 +1,4 @@
+#[allow(clippy::module_inception)]
 pub mod account;
+pub mod domain;
 pub mod error;
-pub mod domain;
\ No newline at end of file
@@ -1,53 +1,82 @@
-use sqlx::postgres::PgPoolOptions;
+use std::fmt;
+
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
 #[derive(Debug)]
 pub struct ConfigurationError {
     message: String,
 }
 
+impl ConfigurationError {
+    pub(crate) fn new(message: String) -> Self {
+        Self { message }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for ConfigurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ConfigurationError {}
+
+#[derive(Clone)]
 pub struct DatabaseParams {
     pub host: String,
     pub port: u16,
     pub user: String,
     pub password: String,
     pub db_name: String,
+    /// Most connections the pool keeps open
+    pub pool_size: u32,
+}
+
+impl DatabaseParams {
+    fn connect_options(&self, db_name: &str) -> PgConnectOptions {
+        PgConnectOptions::new_without_pgpass()
+            .host(&self.host)
+            .port(self.port)
+            .username(&self.user)
+            .password(&self.password)
+            .database(db_name)
+    }
+}
+
+/// The password is left out, so the params can be logged
+impl fmt::Debug for DatabaseParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DatabaseParams")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &"[redacted]")
+            .field("db_name", &self.db_name)
+            .field("pool_size", &self.pool_size)
+            .finish()
+    }
 }
 
+#[allow(dead_code)]
 pub struct Database {
     pool: sqlx::PgPool,
-    host: String,
-    port: u16,
-    user: String,
-    password: String,
-    db_name: String,
+    params: DatabaseParams,
 }
 
 impl Database {
     pub async fn build(params: DatabaseParams) -> Result<Self, ConfigurationError> {
         let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&format!(
-                "postgres://{user}:{password}@{host}:{port}/{db_name}",
-                user = &params.user,
-                password = &params.password,
-                host = &params.host,
-                port = &params.port,
-                db_name = &params.db_name
-            ))
+            .max_connections(params.pool_size)
+            .connect_with(params.connect_options(&params.db_name))
             .await;
 
         match pool {
-            Ok(pool) => Ok(Self {
-                pool,
-                host: params.host,
-                port: params.port,
-                user: params.user,
-                password: params.password,
-                db_name: params.db_name,
-            }),
-            Err(e) => Err(ConfigurationError {
-                message: e.to_string(),
-            }),
+            Ok(pool) => Ok(Self { pool, params }),
+            Err(e) => Err(ConfigurationError::new(e.to_string())),
         }
     }
 
@@ -55,14 +84,8 @@ impl Database {
         params: DatabaseParams,
     ) -> Result<Self, ConfigurationError> {
         let pool = PgPoolOptions::new()
-            .max_connections(5)
-            .connect(&format!(
-                "postgres://{user}:{password}@{host}:{port}/postgres",
-                user = &params.user,
-                password = &params.password,
-                host = &params.host,
-                port = &params.port,
-            ))
+            .max_connections(params.pool_size)
+            .connect_with(params.connect_options("postgres"))
             .await;
 
         match pool {
@@ -73,15 +96,8 @@ impl Database {
                     .expect("Failed to create database");
 
                 let pool = PgPoolOptions::new()
-                    .max_connections(5)
-                    .connect(&format!(
-                        "postgres://{user}:{password}@{host}:{port}/{db_name}",
-                        user = &params.user,
-                        password = &params.password,
-                        host = &params.host,
-                        port = &params.port,
-                        db_name = &params.db_name
-                    ))
+                    .max_connections(params.pool_size)
+                    .connect_with(params.connect_options(&params.db_name))
                     .await
                     .expect("Failed to connect to new database");
 
@@ -91,18 +107,9 @@ impl Database {
                     .await
                     .expect("Failed to run migrations");
 
-                Ok(Self {
-                    pool,
-                    host: params.host,
-                    port: params.port,
-                    user: params.user,
-                    password: params.password,
-                    db_name: params.db_name,
-                })
+                Ok(Self { pool, params })
             }
-            Err(e) => Err(ConfigurationError {
-                message: e.to_string(),
-            }),
+            Err(e) => Err(ConfigurationError::new(e.to_string())),
         }
     }
 
@@ -0,0 +1,199 @@
+use uuid::Uuid;
+
+use crate::internal::{
+    account::domain::{Account, AccountStatus, AccountType},
+    error::{BankError, ErrorKind},
+    money::domain::Currency,
+    storage::repository::CustomerRepository,
+};
+
+use super::{
+    domain::{Customer, NewCustomer},
+    error::CustomerError,
+};
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(CustomerError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+pub struct CustomerManager<'a> {
+    db_pool: &'a sqlx::PgPool,
+}
+
+impl<'a> CustomerManager<'a> {
+    pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    async fn acquire(
+        &self,
+    ) -> Result<sqlx::pool::PoolConnection<sqlx::Postgres>, Box<dyn BankError>> {
+        match self.db_pool.acquire().await {
+            Ok(conn) => Ok(conn),
+            Err(e) => {
+                println!("Error getting database connection: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    pub async fn create_customer(
+        &self,
+        new_customer: &NewCustomer,
+    ) -> Result<Customer, Box<dyn BankError>> {
+        let mut conn = self.acquire().await?;
+
+        CustomerManager::register_customer(new_customer, &mut *conn).await
+    }
+
+    /// Same as `create_customer`, on the caller's unit of work
+    pub(crate) async fn register_customer<R: CustomerRepository + ?Sized>(
+        new_customer: &NewCustomer,
+        conn: &mut R,
+    ) -> Result<Customer, Box<dyn BankError>> {
+        let document_number = new_customer.validate()?;
+
+        match conn.insert_customer(new_customer, &document_number).await? {
+            Some(customer) => Ok(customer),
+            None => Err(Box::new(CustomerError::new(
+                format!(
+                    "A customer with document [{}] already exists",
+                    document_number
+                ),
+                ErrorKind::Conflict,
+            ))),
+        }
+    }
+
+    pub async fn get_customer(&self, id: &Uuid) -> Result<Customer, Box<dyn BankError>> {
+        let mut conn = self.acquire().await?;
+
+        match conn.find_customer(id).await? {
+            Some(customer) => Ok(customer),
+            None => Err(Box::new(CustomerError::new(
+                format!("Customer [{}] not found", id),
+                ErrorKind::NotFound,
+            ))),
+        }
+    }
+
+    /// Accounts the customer holds, alone or jointly
+    pub async fn list_accounts(&self, id: &Uuid) -> Result<Vec<Account>, Box<dyn BankError>> {
+        self.get_customer(id).await?;
+
+        let accounts = sqlx::query_as!(
+            Account,
+            r#"SELECT account.id, account.number, account.type AS "account_type: AccountType", account.status AS "status: AccountStatus", account.currency AS "currency: Currency"
+            FROM account
+            JOIN account_holder ON account_holder.account_id = account.id
+            WHERE account_holder.customer_id = $1
+            ORDER BY account.number"#,
+            id
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        match accounts {
+            Ok(accounts) => Ok(accounts),
+            Err(e) => {
+                println!("Error listing customer accounts: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::internal::{account::account::AccountManager, test_util::get_conn_with_new_db};
+
+    fn new_customer(document_number: &str) -> NewCustomer {
+        NewCustomer {
+            name: "Maria Silva".to_string(),
+            document_number: document_number.to_string(),
+            email: "maria@example.com".to_string(),
+            date_of_birth: NaiveDate::from_ymd_opt(1990, 5, 17).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_create_customer() {
+        let database = get_conn_with_new_db().await;
+
+        let customer_manager = CustomerManager::new(database.get_pool());
+
+        let customer = customer_manager
+            .create_customer(&new_customer("529.982.247-25"))
+            .await
+            .unwrap();
+
+        assert_eq!(customer.document_number, "52998224725");
+
+        let found = customer_manager.get_customer(&customer.id).await.unwrap();
+
+        assert_eq!(found.name, "Maria Silva");
+
+        // The same document, formatted differently
+        let result = customer_manager
+            .create_customer(&new_customer("52998224725"))
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::Conflict);
+
+        let result = customer_manager
+            .create_customer(&new_customer("529.982.247-26"))
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_joint_accounts() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let customer_manager = CustomerManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+
+        let maria = customer_manager
+            .create_customer(&new_customer("529.982.247-25"))
+            .await
+            .unwrap();
+        let company = customer_manager
+            .create_customer(&new_customer("11.222.333/0001-81"))
+            .await
+            .unwrap();
+
+        let personal = account_manager
+            .create_account(AccountType::Checking, &[maria.id])
+            .await
+            .unwrap();
+        let joint = account_manager
+            .create_account(AccountType::Checking, &[maria.id, company.id])
+            .await
+            .unwrap();
+
+        let numbers = |accounts: Vec<Account>| -> Vec<i64> {
+            accounts.iter().map(|account| *account.number()).collect()
+        };
+
+        assert_eq!(
+            numbers(customer_manager.list_accounts(&maria.id).await.unwrap()),
+            vec![*personal.number(), *joint.number()]
+        );
+        assert_eq!(
+            numbers(customer_manager.list_accounts(&company.id).await.unwrap()),
+            vec![*joint.number()]
+        );
+
+        let result = customer_manager.list_accounts(&Uuid::now_v7()).await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,227 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::internal::error::ErrorKind;
+
+use super::error::CustomerError;
+
+const CPF_LENGTH: usize = 11;
+const CNPJ_LENGTH: usize = 14;
+
+/// Brazilian taxpayer id, CPF for people and CNPJ for companies, kept as digits only
+#[derive(Debug, Clone, PartialEq)]
+pub enum DocumentNumber {
+    Cpf(String),
+    Cnpj(String),
+}
+
+impl DocumentNumber {
+    pub fn as_str(&self) -> &str {
+        match self {
+            DocumentNumber::Cpf(digits) | DocumentNumber::Cnpj(digits) => digits,
+        }
+    }
+}
+
+impl fmt::Display for DocumentNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Modulo 11 check digit shared by CPF, CNPJ and account numbers
+pub(crate) fn check_digit(digits: &[u32], weights: &[u32]) -> u32 {
+    let sum: u32 = digits.iter().zip(weights).map(|(d, w)| d * w).sum();
+
+    match sum % 11 {
+        0 | 1 => 0,
+        remainder => 11 - remainder,
+    }
+}
+
+pub(crate) fn cpf_check_digits(digits: &[u32]) -> (u32, u32) {
+    let first = check_digit(&digits[..9], &[10, 9, 8, 7, 6, 5, 4, 3, 2]);
+    let second = check_digit(
+        &[&digits[..9], &[first]].concat(),
+        &[11, 10, 9, 8, 7, 6, 5, 4, 3, 2],
+    );
+
+    (first, second)
+}
+
+fn cnpj_check_digits(digits: &[u32]) -> (u32, u32) {
+    let first = check_digit(&digits[..12], &[5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2]);
+    let second = check_digit(
+        &[&digits[..12], &[first]].concat(),
+        &[6, 5, 4, 3, 2, 9, 8, 7, 6, 5, 4, 3, 2],
+    );
+
+    (first, second)
+}
+
+impl FromStr for DocumentNumber {
+    type Err = CustomerError;
+
+    /// Accepts the formatted (`123.456.789-09`, `12.345.678/0001-95`) and the bare forms
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            CustomerError::new(
+                format!("Invalid document number [{}]", value),
+                ErrorKind::InvalidInput,
+            )
+        };
+
+        let stripped: String = value
+            .chars()
+            .filter(|c| !matches!(c, '.' | '-' | '/' | ' '))
+            .collect();
+
+        let digits: Vec<u32> = match stripped.chars().map(|c| c.to_digit(10)).collect() {
+            Some(digits) => digits,
+            None => return Err(invalid()),
+        };
+
+        // Repeated digits pass the check digit validation, but are never issued
+        if digits.windows(2).all(|pair| pair[0] == pair[1]) {
+            return Err(invalid());
+        }
+
+        let (check_digits, document): ((u32, u32), fn(String) -> DocumentNumber) =
+            match digits.len() {
+                CPF_LENGTH => (cpf_check_digits(&digits), DocumentNumber::Cpf),
+                CNPJ_LENGTH => (cnpj_check_digits(&digits), DocumentNumber::Cnpj),
+                _ => return Err(invalid()),
+            };
+
+        if check_digits != (digits[digits.len() - 2], digits[digits.len() - 1]) {
+            return Err(invalid());
+        }
+
+        Ok(document(stripped))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Customer {
+    pub id: Uuid,
+    pub name: String,
+    pub document_number: String,
+    pub email: String,
+    /// Foundation date for companies
+    pub date_of_birth: NaiveDate,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NewCustomer {
+    pub name: String,
+    pub document_number: String,
+    pub email: String,
+    pub date_of_birth: NaiveDate,
+}
+
+impl NewCustomer {
+    /// Checks every field and returns the normalized document number
+    pub fn validate(&self) -> Result<DocumentNumber, CustomerError> {
+        let name = self.name.trim();
+        if name.is_empty() || name.len() > 255 {
+            return Err(CustomerError::new(
+                "Customer name must have between 1 and 255 characters".to_string(),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        let valid_email = match self.email.split_once('@') {
+            Some((user, domain)) => {
+                !user.is_empty()
+                    && domain.contains('.')
+                    && !domain.starts_with('.')
+                    && !domain.ends_with('.')
+                    && !self.email.contains(char::is_whitespace)
+            }
+            None => false,
+        };
+
+        if !valid_email || self.email.len() > 255 {
+            return Err(CustomerError::new(
+                format!("Invalid email [{}]", self.email),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        if self.date_of_birth > Utc::now().date_naive() {
+            return Err(CustomerError::new(
+                "Date of birth can't be in the future".to_string(),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        self.document_number.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::error::BankError;
+
+    #[test]
+    fn test_parse_cpf() {
+        assert_eq!(
+            "529.982.247-25".parse::<DocumentNumber>().unwrap(),
+            DocumentNumber::Cpf("52998224725".to_string())
+        );
+        assert_eq!(
+            "52998224725".parse::<DocumentNumber>().unwrap(),
+            DocumentNumber::Cpf("52998224725".to_string())
+        );
+
+        assert!("529.982.247-26".parse::<DocumentNumber>().is_err());
+        assert!("111.111.111-11".parse::<DocumentNumber>().is_err());
+        assert!("5299822472".parse::<DocumentNumber>().is_err());
+    }
+
+    #[test]
+    fn test_parse_cnpj() {
+        assert_eq!(
+            "11.222.333/0001-81".parse::<DocumentNumber>().unwrap(),
+            DocumentNumber::Cnpj("11222333000181".to_string())
+        );
+
+        assert!("11.222.333/0001-82".parse::<DocumentNumber>().is_err());
+        assert!("00.000.000/0000-00".parse::<DocumentNumber>().is_err());
+        assert!("11.222.333/0001-8a".parse::<DocumentNumber>().is_err());
+    }
+
+    #[test]
+    fn test_validate_new_customer() {
+        let customer = NewCustomer {
+            name: "Maria Silva".to_string(),
+            document_number: "529.982.247-25".to_string(),
+            email: "maria@example.com".to_string(),
+            date_of_birth: NaiveDate::from_ymd_opt(1990, 5, 17).unwrap(),
+        };
+
+        assert!(customer.validate().is_ok());
+
+        let invalid_email = NewCustomer {
+            email: "maria@example".to_string(),
+            ..customer.clone()
+        };
+
+        assert_eq!(
+            invalid_email.validate().unwrap_err().message(),
+            "Invalid email [maria@example]"
+        );
+
+        let empty_name = NewCustomer {
+            name: " ".to_string(),
+            ..customer
+        };
+
+        assert!(empty_name.validate().is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct CustomerError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl CustomerError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for CustomerError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<CustomerError> for Box<dyn BankError> {
+    fn from(error: CustomerError) -> Self {
+        Box::new(error)
+    }
+}
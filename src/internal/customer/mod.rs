@@ -0,0 +1,4 @@
+#[allow(clippy::module_inception)]
+pub mod customer;
+pub mod domain;
+pub mod error;
@@ -1,6 +1,34 @@
 use std::fmt::Debug;
 
+use crate::internal::money::domain::Money;
+
 pub trait BankError: Debug + Send {
     fn message(&self) -> &str;
-    fn status(&self) -> &axum::http::StatusCode; // Should change it to an internal status system, so the internal impl won't depend on axum
+    fn kind(&self) -> &ErrorKind;
+}
+
+/// What went wrong, so callers can react without parsing the message. How each kind is shown
+/// to a client is up to the client facing layer, the web API maps them to HTTP statuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The request itself is wrong, like a negative amount or an unknown currency
+    InvalidInput,
+    NotFound,
+    /// The origin can't cover the amount, `available` already counts the overdraft limit
+    InsufficientFunds {
+        available: Money,
+        requested: Money,
+    },
+    /// Frozen accounts can't send money until they are unfrozen
+    AccountFrozen,
+    /// The request is valid but clashes with the current state, like closing an account with
+    /// active holds or using an expired quote
+    Conflict,
+    /// The request is valid but doesn't match what was stored for it before, like an
+    /// idempotency key reused with another body
+    Unprocessable,
+    /// The operation isn't available on the storage in use
+    Unsupported,
+    /// Something failed on our side, the message may hold details that shouldn't reach clients
+    Internal,
 }
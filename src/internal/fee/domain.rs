@@ -0,0 +1,280 @@
+use bigdecimal::{BigDecimal, RoundingMode, ToPrimitive};
+use serde::{Deserialize, Serialize};
+
+use crate::internal::{account::domain::AccountType, error::ErrorKind, money::domain::Currency};
+
+use super::error::FeeError;
+
+/// What a fee is charged for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum FeeEvent {
+    Withdraw,
+    Transfer,
+    MonthlyMaintenance,
+    /// Charged on the part of a withdraw or transfer that goes into the overdraft
+    OverdraftUsage,
+}
+
+impl FeeEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FeeEvent::Withdraw => "withdraw",
+            FeeEvent::Transfer => "transfer",
+            FeeEvent::MonthlyMaintenance => "monthly_maintenance",
+            FeeEvent::OverdraftUsage => "overdraft_usage",
+        }
+    }
+}
+
+/// Percentage of the amount, rounded half up to a whole unit
+fn percentage_of(amount: i64, rate: &BigDecimal) -> i64 {
+    (BigDecimal::from(amount) * rate)
+        .with_scale_round(0, RoundingMode::HalfUp)
+        .to_i64()
+        .unwrap_or(i64::MAX)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeTier {
+    /// Highest amount charged by this tier, the last tier has none
+    pub up_to: Option<i64>,
+    #[serde(default)]
+    pub flat: i64,
+    /// `0.01` for 1% of the amount
+    #[serde(default)]
+    pub rate: BigDecimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FeeRule {
+    Flat {
+        amount: i64,
+    },
+    Percentage {
+        rate: BigDecimal,
+    },
+    /// The tier matching the amount decides the fee, tiers are ordered by `up_to`
+    Tiered {
+        tiers: Vec<FeeTier>,
+    },
+}
+
+impl FeeRule {
+    fn fee(&self, amount: i64) -> i64 {
+        match self {
+            FeeRule::Flat { amount } => *amount,
+            FeeRule::Percentage { rate } => percentage_of(amount, rate),
+            FeeRule::Tiered { tiers } => tiers
+                .iter()
+                .find(|tier| tier.up_to.is_none_or(|up_to| amount <= up_to))
+                // Saturates like the percentage, a total too large is refused by `add_fees`
+                .map_or(0, |tier| {
+                    tier.flat.saturating_add(percentage_of(amount, &tier.rate))
+                }),
+        }
+    }
+}
+
+/// How much an account type pays for an event. Every amount is in minor units of `currency`,
+/// only accounts in it are charged by the schedule.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeSchedule {
+    pub account_type: AccountType,
+    pub event: FeeEvent,
+    pub currency: Currency,
+    pub rule: FeeRule,
+    /// Caps applied after the rule
+    pub min_fee: Option<i64>,
+    pub max_fee: Option<i64>,
+}
+
+impl FeeSchedule {
+    pub fn validate(&self) -> Result<(), FeeError> {
+        let invalid =
+            |message: &str| Err(FeeError::new(message.to_string(), ErrorKind::InvalidInput));
+
+        if self.account_type == AccountType::System {
+            return invalid("System accounts don't pay fees");
+        }
+
+        let zero = BigDecimal::from(0);
+        match &self.rule {
+            FeeRule::Flat { amount } if *amount < 0 => return invalid("Fees can't be negative"),
+            FeeRule::Percentage { rate } if *rate < zero => {
+                return invalid("Fees can't be negative")
+            }
+            FeeRule::Tiered { tiers } => {
+                if tiers.last().is_none_or(|tier| tier.up_to.is_some()) {
+                    return invalid("The last tier must not have an upper bound");
+                }
+
+                if tiers.iter().any(|tier| tier.flat < 0 || tier.rate < zero) {
+                    return invalid("Fees can't be negative");
+                }
+
+                let bounds: Vec<i64> = tiers.iter().filter_map(|tier| tier.up_to).collect();
+                if bounds.len() != tiers.len() - 1 || bounds.windows(2).any(|b| b[0] >= b[1]) {
+                    return invalid("Tiers must be ordered by a strictly increasing upper bound");
+                }
+            }
+            _ => {}
+        }
+
+        if self.min_fee.is_some_and(|min| min < 0) || self.max_fee.is_some_and(|max| max < 0) {
+            return invalid("Fees can't be negative");
+        }
+
+        if let (Some(min), Some(max)) = (self.min_fee, self.max_fee) {
+            if min > max {
+                return invalid("Minimum fee can't be greater than the maximum");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fee charged for an `amount`, between the caps
+    pub fn fee(&self, amount: i64) -> i64 {
+        let mut fee = self.rule.fee(amount);
+
+        if let Some(min_fee) = self.min_fee {
+            fee = fee.max(min_fee);
+        }
+        if let Some(max_fee) = self.max_fee {
+            fee = fee.min(max_fee);
+        }
+
+        fee
+    }
+}
+
+/// A fee to be booked, or quoted, for a transaction
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AssessedFee {
+    pub event: FeeEvent,
+    pub amount: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeQuote {
+    pub fees: Vec<AssessedFee>,
+    pub total: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(rule: FeeRule, min_fee: Option<i64>, max_fee: Option<i64>) -> FeeSchedule {
+        FeeSchedule {
+            account_type: AccountType::Checking,
+            event: FeeEvent::Transfer,
+            currency: Currency::Brl,
+            rule,
+            min_fee,
+            max_fee,
+        }
+    }
+
+    #[test]
+    fn test_percentage_with_caps() {
+        let schedule = schedule(
+            FeeRule::Percentage {
+                rate: "0.015".parse().unwrap(),
+            },
+            Some(2),
+            Some(50),
+        );
+
+        assert!(schedule.validate().is_ok());
+        assert_eq!(schedule.fee(10), 2);
+        assert_eq!(schedule.fee(1000), 15);
+        // 1.5% of 1030 is 15.45, and of 1070 is 16.05
+        assert_eq!(schedule.fee(1030), 15);
+        assert_eq!(schedule.fee(1070), 16);
+        assert_eq!(schedule.fee(100_000), 50);
+    }
+
+    #[test]
+    fn test_tiered() {
+        let schedule = schedule(
+            FeeRule::Tiered {
+                tiers: vec![
+                    FeeTier {
+                        up_to: Some(100),
+                        flat: 1,
+                        rate: BigDecimal::from(0),
+                    },
+                    FeeTier {
+                        up_to: Some(1000),
+                        flat: 2,
+                        rate: "0.01".parse().unwrap(),
+                    },
+                    FeeTier {
+                        up_to: None,
+                        flat: 0,
+                        rate: "0.005".parse().unwrap(),
+                    },
+                ],
+            },
+            None,
+            None,
+        );
+
+        assert!(schedule.validate().is_ok());
+        assert_eq!(schedule.fee(100), 1);
+        assert_eq!(schedule.fee(101), 3);
+        assert_eq!(schedule.fee(1000), 12);
+        assert_eq!(schedule.fee(10_000), 50);
+    }
+
+    #[test]
+    fn test_tier_saturates() {
+        let schedule = schedule(
+            FeeRule::Tiered {
+                tiers: vec![FeeTier {
+                    up_to: None,
+                    flat: i64::MAX,
+                    rate: BigDecimal::from(1),
+                }],
+            },
+            None,
+            None,
+        );
+
+        assert_eq!(schedule.fee(1000), i64::MAX);
+    }
+
+    #[test]
+    fn test_invalid_schedules() {
+        let unbounded_middle_tier = schedule(
+            FeeRule::Tiered {
+                tiers: vec![
+                    FeeTier {
+                        up_to: None,
+                        flat: 1,
+                        rate: BigDecimal::from(0),
+                    },
+                    FeeTier {
+                        up_to: None,
+                        flat: 2,
+                        rate: BigDecimal::from(0),
+                    },
+                ],
+            },
+            None,
+            None,
+        );
+
+        assert!(unbounded_middle_tier.validate().is_err());
+        assert!(schedule(FeeRule::Flat { amount: -1 }, None, None)
+            .validate()
+            .is_err());
+        assert!(schedule(FeeRule::Flat { amount: 1 }, Some(10), Some(5))
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct FeeError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl FeeError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for FeeError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<FeeError> for Box<dyn BankError> {
+    fn from(error: FeeError) -> Self {
+        Box::new(error)
+    }
+}
@@ -0,0 +1,776 @@
+use std::sync::Arc;
+
+use chrono::{Datelike, Months, NaiveDate};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, AccountStatus, AccountType, SystemAccount},
+    },
+    clock::{Clock, SystemClock},
+    error::{BankError, ErrorKind},
+    money::domain::{Amount, Currency},
+    storage::repository::{AccountRepository, LedgerRepository},
+    transaction::{
+        domain::TransactionType,
+        ledger::{self, LedgerAccount, Posting},
+    },
+};
+
+use super::{
+    domain::{AssessedFee, FeeEvent, FeeQuote, FeeRule, FeeSchedule},
+    error::FeeError,
+};
+
+pub(crate) struct FeeScheduleRow {
+    pub account_type: AccountType,
+    pub event: FeeEvent,
+    pub currency: Currency,
+    pub rule: Json<FeeRule>,
+    pub min_fee: Option<i64>,
+    pub max_fee: Option<i64>,
+}
+
+impl From<FeeScheduleRow> for FeeSchedule {
+    fn from(row: FeeScheduleRow) -> Self {
+        Self {
+            account_type: row.account_type,
+            event: row.event,
+            currency: row.currency,
+            rule: row.rule.0,
+            min_fee: row.min_fee,
+            max_fee: row.max_fee,
+        }
+    }
+}
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(FeeError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+/// `amount` plus every fee, refused as invalid when too large to count instead of wrapping
+pub(crate) fn add_fees(amount: i64, fees: &[AssessedFee]) -> Result<i64, Box<dyn BankError>> {
+    match fees
+        .iter()
+        .try_fold(amount, |total, fee| total.checked_add(fee.amount))
+    {
+        Some(total) => Ok(total),
+        None => Err(Box::new(FeeError::new(
+            "Amount plus fees is too large".to_string(),
+            ErrorKind::InvalidInput,
+        ))),
+    }
+}
+
+/// Schedule of the type and currency of `account` for `event`, refusing one in another currency
+/// since its amounts would be charged as if they were in the account's
+async fn account_schedule<R: LedgerRepository + ?Sized>(
+    account: &Account,
+    event: FeeEvent,
+    conn: &mut R,
+) -> Result<Option<FeeSchedule>, Box<dyn BankError>> {
+    let schedule = conn
+        .get_fee_schedule(*account.account_type(), event, *account.currency())
+        .await?;
+
+    match schedule {
+        Some(schedule) if schedule.currency != *account.currency() => {
+            println!(
+                "Fee schedule in {} found for account {} in {}",
+                schedule.currency.code(),
+                account.id(),
+                account.currency().code()
+            );
+            Err(unexpected_error())
+        }
+        schedule => Ok(schedule),
+    }
+}
+
+/// Fees owed by `account` for moving `amount` out of it through `event`, including the
+/// overdraft usage fee when the movement and its fees go below the available balance
+pub(crate) async fn assess_fees<R: AccountRepository + LedgerRepository + ?Sized>(
+    event: FeeEvent,
+    amount: i64,
+    account: &Account,
+    conn: &mut R,
+) -> Result<Vec<AssessedFee>, Box<dyn BankError>> {
+    let mut fees = Vec::new();
+
+    if let Some(schedule) = account_schedule(account, event, conn).await? {
+        fees.push(AssessedFee {
+            event,
+            amount: schedule.fee(amount),
+        });
+    }
+
+    let debit = add_fees(amount, &fees)?;
+
+    if let Some(schedule) = account_schedule(account, FeeEvent::OverdraftUsage, conn).await? {
+        let available = AccountManager::get_available_balance(account, conn)
+            .await?
+            .amount_minor
+            .max(0);
+        let overdraft_used = debit.saturating_sub(available);
+
+        if overdraft_used > 0 {
+            fees.push(AssessedFee {
+                event: FeeEvent::OverdraftUsage,
+                amount: schedule.fee(overdraft_used),
+            });
+        }
+    }
+
+    fees.retain(|fee| fee.amount > 0);
+
+    Ok(fees)
+}
+
+/// Postings debiting the fees from `account` and crediting them to the fee income account, to
+/// be booked in the same journal entry as the movement they are charged for
+pub(crate) fn fee_postings(account: &Account, fees: &[AssessedFee]) -> Vec<Posting> {
+    fees.iter()
+        .flat_map(|fee| {
+            [
+                Posting::new(
+                    LedgerAccount::Customer(*account.id()),
+                    -fee.amount,
+                    TransactionType::Fee,
+                ),
+                Posting::new(
+                    LedgerAccount::System(SystemAccount::FeeIncome, *account.currency()),
+                    fee.amount,
+                    TransactionType::Fee,
+                ),
+            ]
+        })
+        .collect()
+}
+
+pub struct FeeManager<'a> {
+    db_pool: &'a sqlx::PgPool,
+    clock: Arc<dyn Clock>,
+}
+
+impl<'a> FeeManager<'a> {
+    pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
+        Self {
+            db_pool,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Replaces the clock deciding which months are already over
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub async fn list_schedules(&self) -> Result<Vec<FeeSchedule>, Box<dyn BankError>> {
+        let schedules = sqlx::query_as!(
+            FeeScheduleRow,
+            r#"SELECT account_type AS "account_type: AccountType", event AS "event: FeeEvent", currency AS "currency: Currency", rule AS "rule: Json<FeeRule>", min_fee, max_fee
+            FROM fee_schedule ORDER BY account_type, event, currency"#
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        match schedules {
+            Ok(schedules) => Ok(schedules.into_iter().map(FeeSchedule::from).collect()),
+            Err(e) => {
+                println!("Error listing fee schedules: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Creates or replaces the schedule of the account type for the event in the schedule's
+    /// currency
+    pub async fn set_schedule(
+        &self,
+        schedule: &FeeSchedule,
+    ) -> Result<FeeSchedule, Box<dyn BankError>> {
+        schedule.validate()?;
+
+        let result = sqlx::query!(
+            "INSERT INTO fee_schedule (account_type, event, currency, rule, min_fee, max_fee) VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (account_type, event, currency) DO UPDATE SET rule = $4, min_fee = $5, max_fee = $6, updated_at = NOW()",
+            schedule.account_type.as_str(),
+            schedule.event.as_str(),
+            schedule.currency.code(),
+            Json(&schedule.rule) as _,
+            schedule.min_fee,
+            schedule.max_fee
+        )
+        .execute(self.db_pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(schedule.clone()),
+            Err(e) => {
+                println!("Error saving fee schedule: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    pub async fn remove_schedule(
+        &self,
+        account_type: AccountType,
+        event: FeeEvent,
+        currency: Currency,
+    ) -> Result<(), Box<dyn BankError>> {
+        let result = sqlx::query!(
+            "DELETE FROM fee_schedule WHERE account_type = $1 AND event = $2 AND currency = $3",
+            account_type.as_str(),
+            event.as_str(),
+            currency.code()
+        )
+        .execute(self.db_pool)
+        .await;
+
+        match result {
+            Ok(result) if result.rows_affected() == 0 => Err(Box::new(FeeError::new(
+                format!(
+                    "No {} fee schedule for {} accounts in {}",
+                    event.as_str(),
+                    account_type.as_str(),
+                    currency.code()
+                ),
+                ErrorKind::NotFound,
+            ))),
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error removing fee schedule: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Fees a withdraw or transfer of `amount` would pay right now
+    pub async fn quote(
+        &self,
+        account: &Account,
+        event: FeeEvent,
+        amount: Amount,
+    ) -> Result<FeeQuote, Box<dyn BankError>> {
+        if !matches!(event, FeeEvent::Withdraw | FeeEvent::Transfer) {
+            return Err(Box::new(FeeError::new(
+                "Only withdraws and transfers can be quoted".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error getting database connection: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let fees = assess_fees(event, amount.into(), account, &mut *conn).await?;
+        let total = add_fees(0, &fees)?;
+
+        Ok(FeeQuote { fees, total })
+    }
+
+    /// Charges the maintenance fee of the month of `month` to every open account whose type has
+    /// one, skipping accounts already charged. It may take the balance below zero.
+    pub async fn charge_monthly_maintenance(
+        &self,
+        month: NaiveDate,
+    ) -> Result<u64, Box<dyn BankError>> {
+        let month = month.with_day(1).unwrap_or(month);
+        let next_month = match month.checked_add_months(Months::new(1)) {
+            Some(next_month) => next_month,
+            None => return Err(unexpected_error()),
+        };
+
+        if next_month > self.clock.now().date_naive() {
+            return Err(Box::new(FeeError::new(
+                format!("Month [{}] is not over yet", month.format("%Y-%m")),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let accounts = sqlx::query!(
+            r#"SELECT account.id FROM account
+            JOIN fee_schedule ON fee_schedule.account_type = account.type AND fee_schedule.event = $1
+            AND fee_schedule.currency = account.currency
+            WHERE account.status <> $2 AND account.created_at < $3
+            AND NOT EXISTS (SELECT 1 FROM maintenance_fee WHERE account_id = account.id AND month = $4)
+            ORDER BY account.number"#,
+            FeeEvent::MonthlyMaintenance.as_str(),
+            AccountStatus::Closed.as_str(),
+            next_month.and_hms_opt(0, 0, 0).map(|midnight| midnight.and_utc()),
+            month
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        let accounts = match accounts {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                println!("Error getting accounts to charge: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let mut charged = 0;
+        for row in accounts {
+            if self.charge_account_maintenance(&row.id, month).await? {
+                charged += 1;
+            }
+        }
+
+        Ok(charged)
+    }
+
+    async fn charge_account_maintenance(
+        &self,
+        account_id: &Uuid,
+        month: NaiveDate,
+    ) -> Result<bool, Box<dyn BankError>> {
+        let mut tx = match self.db_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let account = AccountManager::get_account_from_id(account_id, &mut *tx).await?;
+        AccountManager::lock_accounts(&[&account], &mut *tx).await?;
+
+        // Checked again under the lock, a concurrent run may have charged it already
+        let charged = sqlx::query!(
+            r#"SELECT EXISTS (SELECT 1 FROM maintenance_fee WHERE account_id = $1 AND month = $2) AS "charged!""#,
+            account_id,
+            month
+        )
+        .fetch_one(&mut *tx)
+        .await;
+
+        match charged {
+            Ok(row) if row.charged => return Ok(false),
+            Ok(_) => {}
+            Err(e) => {
+                println!("Error checking maintenance fee: {}", e);
+                return Err(unexpected_error());
+            }
+        }
+
+        let schedule =
+            match account_schedule(&account, FeeEvent::MonthlyMaintenance, &mut *tx).await? {
+                Some(schedule) => schedule,
+                None => return Ok(false),
+            };
+
+        let fees = vec![AssessedFee {
+            event: FeeEvent::MonthlyMaintenance,
+            amount: schedule.fee(0),
+        }];
+
+        if fees[0].amount <= 0 {
+            return Ok(false);
+        }
+
+        let journal_entry = ledger::post_journal_entry(
+            &format!("maintenance fee {}", month.format("%Y-%m")),
+            fee_postings(&account, &fees),
+            &mut *tx,
+        )
+        .await?;
+
+        let result = sqlx::query!(
+            "INSERT INTO maintenance_fee (account_id, month, journal_entry_id) VALUES ($1, $2, $3)",
+            account_id,
+            month,
+            journal_entry.id
+        )
+        .execute(&mut *tx)
+        .await;
+
+        if let Err(e) = result {
+            println!("Error recording maintenance fee: {}", e);
+            return Err(unexpected_error());
+        }
+
+        match tx.commit().await {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                println!("Error committing transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::internal::{
+        clock::FixedClock,
+        test_util::{create_funded_account, get_conn_with_new_db},
+        transaction::{domain::Transaction, transaction::TransactionManager},
+    };
+
+    async fn balance(db_pool: &sqlx::PgPool, account: &Account) -> i64 {
+        AccountManager::get_balance(account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor
+    }
+
+    fn flat(event: FeeEvent, amount: i64) -> FeeSchedule {
+        FeeSchedule {
+            account_type: AccountType::Checking,
+            event,
+            currency: Currency::Brl,
+            rule: FeeRule::Flat { amount },
+            min_fee: None,
+            max_fee: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fees_are_booked_with_the_transaction() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let fee_manager = FeeManager::new(db_pool);
+        fee_manager
+            .set_schedule(&flat(FeeEvent::Withdraw, 2))
+            .await
+            .unwrap();
+        fee_manager
+            .set_schedule(&FeeSchedule {
+                rule: FeeRule::Percentage {
+                    rate: "0.01".parse().unwrap(),
+                },
+                min_fee: Some(1),
+                ..flat(FeeEvent::Transfer, 0)
+            })
+            .await
+            .unwrap();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 1000).await;
+        let other = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        let quote = fee_manager
+            .quote(&account, FeeEvent::Transfer, Amount::new(500).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(quote.total, 5);
+
+        let transaction_manager = TransactionManager::new(db_pool);
+
+        let receipt = transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(500).unwrap(),
+                origin: account.clone(),
+                destination: other.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.fees, quote.fees);
+        // Both transfer legs and the fee
+        assert_eq!(receipt.transaction_ids.len(), 3);
+
+        let receipt = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(100).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            receipt.fees,
+            vec![AssessedFee {
+                event: FeeEvent::Withdraw,
+                amount: 2
+            }]
+        );
+
+        assert_eq!(balance(db_pool, &account).await, 393);
+        assert_eq!(balance(db_pool, &other).await, 500);
+
+        // The fee must be covered as well
+        let result = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(392).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "Insufficient funds, available amount is 3.93 BRL"
+        );
+
+        let trial_balance = transaction_manager.trial_balance().await.unwrap();
+        let fee_income = trial_balance
+            .lines
+            .iter()
+            .find(|line| {
+                line.system_code.as_deref() == Some("fee_income") && line.currency == Currency::Brl
+            })
+            .unwrap();
+
+        assert_eq!(fee_income.credits, 7.into());
+        assert!(trial_balance.is_balanced());
+
+        // Reversing the whole withdraw gives the fee back
+        transaction_manager
+            .reverse(&receipt.journal_entry_id, "Charged twice")
+            .await
+            .unwrap();
+
+        assert_eq!(balance(db_pool, &account).await, 495);
+    }
+
+    #[tokio::test]
+    async fn test_fees_on_the_largest_amount() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        FeeManager::new(db_pool)
+            .set_schedule(&flat(FeeEvent::Withdraw, 10))
+            .await
+            .unwrap();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+
+        let result = TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(i64::MAX).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+        assert_eq!(balance(db_pool, &account).await, 100);
+    }
+
+    #[tokio::test]
+    async fn test_overdraft_usage_fee() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let fee_manager = FeeManager::new(db_pool);
+        fee_manager
+            .set_schedule(&FeeSchedule {
+                rule: FeeRule::Percentage {
+                    rate: "0.1".parse().unwrap(),
+                },
+                ..flat(FeeEvent::OverdraftUsage, 0)
+            })
+            .await
+            .unwrap();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let account_manager = AccountManager::new(db_pool);
+        account_manager
+            .update_limits(
+                &account,
+                &crate::internal::account::domain::AccountLimits {
+                    overdraft_limit: 500,
+                },
+            )
+            .await
+            .unwrap();
+
+        let quote = fee_manager
+            .quote(&account, FeeEvent::Withdraw, Amount::new(100).unwrap())
+            .await
+            .unwrap();
+
+        assert!(quote.fees.is_empty());
+
+        let receipt = TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(300).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        // 200 of the withdraw went into the overdraft
+        assert_eq!(
+            receipt.fees,
+            vec![AssessedFee {
+                event: FeeEvent::OverdraftUsage,
+                amount: 20
+            }]
+        );
+        assert_eq!(balance(db_pool, &account).await, -220);
+    }
+
+    #[tokio::test]
+    async fn test_monthly_maintenance_is_charged_once() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let savings = create_funded_account(db_pool, AccountType::Savings, Currency::Brl, 0).await;
+
+        let month = Utc::now().date_naive().with_day(1).unwrap();
+        let next_month = month.checked_add_months(Months::new(1)).unwrap();
+        let clock = Arc::new(FixedClock(
+            next_month.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        ));
+
+        let fee_manager = FeeManager::new(db_pool).with_clock(clock);
+        fee_manager
+            .set_schedule(&flat(FeeEvent::MonthlyMaintenance, 10))
+            .await
+            .unwrap();
+
+        // Accounts opened after the month are not charged for it
+        let last_month = month.checked_sub_months(Months::new(1)).unwrap();
+        assert_eq!(
+            fee_manager
+                .charge_monthly_maintenance(last_month)
+                .await
+                .unwrap(),
+            0
+        );
+
+        assert_eq!(
+            fee_manager.charge_monthly_maintenance(month).await.unwrap(),
+            1
+        );
+        assert_eq!(
+            fee_manager.charge_monthly_maintenance(month).await.unwrap(),
+            0
+        );
+
+        let result = fee_manager.charge_monthly_maintenance(next_month).await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        assert_eq!(balance(db_pool, &account).await, 90);
+        assert_eq!(balance(db_pool, &savings).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reverse_monthly_maintenance() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+
+        let month = Utc::now().date_naive().with_day(1).unwrap();
+        let next_month = month.checked_add_months(Months::new(1)).unwrap();
+        let clock = Arc::new(FixedClock(
+            next_month.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        ));
+
+        let fee_manager = FeeManager::new(db_pool).with_clock(clock);
+        fee_manager
+            .set_schedule(&flat(FeeEvent::MonthlyMaintenance, 10))
+            .await
+            .unwrap();
+        fee_manager.charge_monthly_maintenance(month).await.unwrap();
+
+        let journal_entry_id = sqlx::query!(
+            "SELECT journal_entry_id FROM maintenance_fee WHERE account_id = $1",
+            account.id()
+        )
+        .fetch_one(db_pool)
+        .await
+        .unwrap()
+        .journal_entry_id;
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        transaction_manager
+            .reverse(&journal_entry_id, "waived")
+            .await
+            .unwrap();
+
+        assert_eq!(balance(db_pool, &account).await, 100);
+
+        let result = transaction_manager
+            .reverse(&journal_entry_id, "waived")
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::Conflict);
+        assert!(transaction_manager
+            .trial_balance()
+            .await
+            .unwrap()
+            .is_balanced());
+    }
+
+    #[tokio::test]
+    async fn test_schedules_only_charge_accounts_in_their_currency() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Usd, 100).await;
+        let transaction_manager = TransactionManager::new(db_pool);
+
+        let fee_manager = FeeManager::new(db_pool);
+        fee_manager
+            .set_schedule(&flat(FeeEvent::Withdraw, 2))
+            .await
+            .unwrap();
+
+        let withdraw = || Transaction::Withdraw {
+            amount: Amount::new(10).unwrap(),
+            origin: account.clone(),
+        };
+
+        let receipt = transaction_manager
+            .create_transaction(withdraw())
+            .await
+            .unwrap();
+
+        assert!(receipt.fees.is_empty());
+
+        fee_manager
+            .set_schedule(&FeeSchedule {
+                currency: Currency::Usd,
+                ..flat(FeeEvent::Withdraw, 3)
+            })
+            .await
+            .unwrap();
+
+        let receipt = transaction_manager
+            .create_transaction(withdraw())
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.fees.len(), 1);
+        assert_eq!(receipt.fees[0].amount, 3);
+        assert_eq!(balance(db_pool, &account).await, 77);
+        assert_eq!(fee_manager.list_schedules().await.unwrap().len(), 2);
+
+        fee_manager
+            .remove_schedule(AccountType::Checking, FeeEvent::Withdraw, Currency::Usd)
+            .await
+            .unwrap();
+        let result = fee_manager
+            .remove_schedule(AccountType::Checking, FeeEvent::Withdraw, Currency::Usd)
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::NotFound);
+    }
+}
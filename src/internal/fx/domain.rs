@@ -0,0 +1,330 @@
+use bigdecimal::{num_bigint::BigInt, BigDecimal, RoundingMode, ToPrimitive};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::internal::{error::ErrorKind, money::domain::Currency};
+
+use super::error::FxError;
+
+/// Decimals kept on the rates derived from the stored ones, inverse and spread rates
+pub const RATE_SCALE: i64 = 10;
+
+/// For how long a quoted rate can be executed
+pub const DEFAULT_QUOTE_TTL: chrono::Duration = chrono::Duration::seconds(30);
+
+/// Units of `quote_currency` bought by one unit of `base_currency`, from `effective_at` until a
+/// later rate of the same pair takes over
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FxRate {
+    pub base_currency: Currency,
+    pub quote_currency: Currency,
+    pub rate: BigDecimal,
+    pub effective_at: DateTime<Utc>,
+}
+
+impl FxRate {
+    pub fn validate(&self) -> Result<(), FxError> {
+        if self.base_currency == self.quote_currency {
+            return Err(FxError::new(
+                format!("A rate can't convert {} into itself", self.base_currency),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        if self.rate <= BigDecimal::from(0) {
+            return Err(FxError::new(
+                format!(
+                    "The {}/{} rate must be positive",
+                    self.base_currency, self.quote_currency
+                ),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses rates with a `base_currency,quote_currency,rate,effective_at` header, timestamps in
+/// RFC 3339. Blank lines are skipped.
+pub fn parse_rates_csv(content: &str) -> Result<Vec<FxRate>, FxError> {
+    let invalid = |line: usize, reason: &str| {
+        FxError::new(
+            format!("Invalid rate on line {}: {}", line, reason),
+            ErrorKind::InvalidInput,
+        )
+    };
+
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    match lines.next() {
+        Some((_, header)) if header.trim() == "base_currency,quote_currency,rate,effective_at" => {}
+        _ => {
+            return Err(FxError::new(
+                "Rates must start with a base_currency,quote_currency,rate,effective_at header"
+                    .to_string(),
+                ErrorKind::InvalidInput,
+            ))
+        }
+    }
+
+    let mut rates = Vec::new();
+    for (index, line) in lines {
+        let line_number = index + 1;
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [base_currency, quote_currency, rate, effective_at] = fields[..] else {
+            return Err(invalid(line_number, "expected 4 fields"));
+        };
+
+        let rate = FxRate {
+            base_currency: base_currency
+                .parse()
+                .map_err(|_| invalid(line_number, "unsupported base currency"))?,
+            quote_currency: quote_currency
+                .parse()
+                .map_err(|_| invalid(line_number, "unsupported quote currency"))?,
+            rate: rate
+                .parse()
+                .map_err(|_| invalid(line_number, "rate is not a number"))?,
+            effective_at: DateTime::parse_from_rfc3339(effective_at)
+                .map_err(|_| invalid(line_number, "effective_at is not an RFC 3339 timestamp"))?
+                .with_timezone(&Utc),
+        };
+        rate.validate()?;
+        rates.push(rate);
+    }
+
+    Ok(rates)
+}
+
+/// Parses a JSON array of `FxRate`
+pub fn parse_rates_json(content: &str) -> Result<Vec<FxRate>, FxError> {
+    let rates: Vec<FxRate> = serde_json::from_str(content)
+        .map_err(|e| FxError::new(format!("Invalid rates: {}", e), ErrorKind::InvalidInput))?;
+
+    for rate in &rates {
+        rate.validate()?;
+    }
+
+    Ok(rates)
+}
+
+/// Rate of the opposite pair, rounded half even to `RATE_SCALE` decimals
+pub fn inverse_rate(rate: &BigDecimal) -> BigDecimal {
+    (BigDecimal::from(1) / rate).with_scale_round(RATE_SCALE, RoundingMode::HalfEven)
+}
+
+/// Rate given to customers, the market rate minus a spread in basis points, rounded down to
+/// `RATE_SCALE` decimals so the spread is never smaller than configured
+pub fn customer_rate(market_rate: &BigDecimal, spread_bps: u32) -> BigDecimal {
+    let spread = BigDecimal::new(BigInt::from(spread_bps), 4);
+
+    (market_rate * (BigDecimal::from(1) - spread)).with_scale_round(RATE_SCALE, RoundingMode::Down)
+}
+
+/// Converts minor units of `from` into minor units of `to`, `rate` being units of `to` per unit
+/// of `from`. None when the result doesn't fit in an `i64`.
+///
+/// Amounts paid to customers are rounded `Down` so the bank never pays a fraction of a minor
+/// unit it doesn't have, market values are rounded `HalfEven` so rounding doesn't drift the FX
+/// position in either direction.
+pub fn convert(
+    amount: i64,
+    from: Currency,
+    to: Currency,
+    rate: &BigDecimal,
+    rounding: RoundingMode,
+) -> Option<i64> {
+    let exponent = i64::from(to.exponent()) - i64::from(from.exponent());
+    let minor_units = BigDecimal::new(BigInt::from(1), -exponent);
+
+    (BigDecimal::from(amount) * rate * minor_units)
+        .with_scale_round(0, rounding)
+        .to_i64()
+}
+
+/// A conversion of `amount` priced at `rate` until `expires_at`, executed by a single transfer
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FxQuote {
+    pub id: Uuid,
+    pub from_currency: Currency,
+    pub to_currency: Currency,
+    /// Minor units of `from_currency` leaving the origin account
+    pub amount: i64,
+    /// Minor units of `to_currency` credited to the destination account
+    pub converted_amount: i64,
+    /// Units of `to_currency` per unit of `from_currency` given to the customer
+    pub rate: BigDecimal,
+    /// Rate the customer one was derived from
+    pub market_rate: BigDecimal,
+    pub expires_at: DateTime<Utc>,
+    /// Set once a transfer executed the quote
+    pub journal_entry_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(value: &str) -> BigDecimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn test_convert_rounding_is_deterministic() {
+        // 10.00 USD at 5.4321 is 54.321 BRL
+        let usd_brl = rate("5.4321");
+        assert_eq!(
+            convert(
+                1000,
+                Currency::Usd,
+                Currency::Brl,
+                &usd_brl,
+                RoundingMode::Down
+            ),
+            Some(5432)
+        );
+        assert_eq!(
+            convert(
+                1000,
+                Currency::Usd,
+                Currency::Brl,
+                &usd_brl,
+                RoundingMode::HalfEven
+            ),
+            Some(5432)
+        );
+
+        // 0.25 EUR at 1.1 is 0.275 USD, a tie that goes to the even cent
+        let eur_usd = rate("1.1");
+        assert_eq!(
+            convert(
+                25,
+                Currency::Eur,
+                Currency::Usd,
+                &eur_usd,
+                RoundingMode::HalfEven
+            ),
+            Some(28)
+        );
+        assert_eq!(
+            convert(
+                25,
+                Currency::Eur,
+                Currency::Usd,
+                &eur_usd,
+                RoundingMode::Down
+            ),
+            Some(27)
+        );
+        // 7.5 and 10.5 cents, ties go to 8 and 10
+        assert_eq!(
+            convert(
+                5,
+                Currency::Eur,
+                Currency::Usd,
+                &rate("1.5"),
+                RoundingMode::HalfEven
+            ),
+            Some(8)
+        );
+        assert_eq!(
+            convert(
+                7,
+                Currency::Eur,
+                Currency::Usd,
+                &rate("1.5"),
+                RoundingMode::HalfEven
+            ),
+            Some(10)
+        );
+    }
+
+    #[test]
+    fn test_convert_follows_the_currency_exponents() {
+        // 12.34 USD at 151.237 JPY, JPY has no minor unit
+        assert_eq!(
+            convert(
+                1234,
+                Currency::Usd,
+                Currency::Jpy,
+                &rate("151.237"),
+                RoundingMode::Down
+            ),
+            Some(1866)
+        );
+        // 1866 JPY at 0.0066 USD is 12.3156 USD
+        assert_eq!(
+            convert(
+                1866,
+                Currency::Jpy,
+                Currency::Usd,
+                &rate("0.0066"),
+                RoundingMode::HalfEven
+            ),
+            Some(1232)
+        );
+        // 1.000 KWD at 3.2561 USD, KWD has three decimals
+        assert_eq!(
+            convert(
+                1000,
+                Currency::Kwd,
+                Currency::Usd,
+                &rate("3.2561"),
+                RoundingMode::Down
+            ),
+            Some(325)
+        );
+    }
+
+    #[test]
+    fn test_derived_rates() {
+        assert_eq!(inverse_rate(&rate("3")), rate("0.3333333333"));
+        assert_eq!(inverse_rate(&rate("1.5")), rate("0.6666666667"));
+        assert_eq!(customer_rate(&rate("5.4321"), 0), rate("5.4321"));
+        // 1% of 5.4321 is 0.054321, the customer gets 5.377779
+        assert_eq!(customer_rate(&rate("5.4321"), 100), rate("5.377779"));
+        assert_eq!(
+            customer_rate(&rate("0.3333333333"), 25),
+            rate("0.3324999999")
+        );
+    }
+
+    #[test]
+    fn test_parse_rates() {
+        let rates = parse_rates_csv(
+            "base_currency,quote_currency,rate,effective_at\n\
+            USD,BRL,5.4321,2024-12-30T12:00:00Z\n\
+            \n\
+            EUR, USD, 1.04, 2024-12-30T12:00:00-03:00\n",
+        )
+        .unwrap();
+
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].rate, rate("5.4321"));
+        assert_eq!(rates[1].base_currency, Currency::Eur);
+        assert_eq!(
+            rates[1].effective_at,
+            "2024-12-30T15:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+
+        let json = serde_json::to_string(&rates).unwrap();
+        assert_eq!(parse_rates_json(&json).unwrap(), rates);
+
+        for invalid in [
+            "USD,BRL,5.4321,2024-12-30T12:00:00Z",
+            "base_currency,quote_currency,rate,effective_at\nUSD,BRL,5.4321",
+            "base_currency,quote_currency,rate,effective_at\nUSD,XXX,5.4321,2024-12-30T12:00:00Z",
+            "base_currency,quote_currency,rate,effective_at\nUSD,BRL,-1,2024-12-30T12:00:00Z",
+            "base_currency,quote_currency,rate,effective_at\nUSD,USD,1,2024-12-30T12:00:00Z",
+            "base_currency,quote_currency,rate,effective_at\nUSD,BRL,5.4321,yesterday",
+        ] {
+            assert!(parse_rates_csv(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct FxError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl FxError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for FxError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<FxError> for Box<dyn BankError> {
+    fn from(error: FxError) -> Self {
+        Box::new(error)
+    }
+}
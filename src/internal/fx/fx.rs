@@ -0,0 +1,733 @@
+use std::{path::Path, sync::Arc};
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::internal::{
+    clock::{Clock, SystemClock},
+    error::{BankError, ErrorKind},
+    money::domain::{Amount, Currency},
+};
+
+use super::{
+    domain::{
+        convert, customer_rate, inverse_rate, parse_rates_csv, parse_rates_json, FxQuote, FxRate,
+        DEFAULT_QUOTE_TTL,
+    },
+    error::FxError,
+};
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(FxError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+fn amount_too_large() -> Box<dyn BankError> {
+    Box::new(FxError::new(
+        "Converted amount is too large".to_string(),
+        ErrorKind::InvalidInput,
+    ))
+}
+
+/// Latest rate of the pair effective at `at`
+async fn get_stored_rate(
+    base_currency: Currency,
+    quote_currency: Currency,
+    at: DateTime<Utc>,
+    conn: &mut sqlx::PgConnection,
+) -> Result<Option<BigDecimal>, Box<dyn BankError>> {
+    let rate = sqlx::query!(
+        "SELECT rate FROM fx_rate WHERE base_currency = $1 AND quote_currency = $2 AND effective_at <= $3
+        ORDER BY effective_at DESC LIMIT 1",
+        base_currency.code(),
+        quote_currency.code(),
+        at
+    )
+    .fetch_optional(conn)
+    .await;
+
+    match rate {
+        Ok(rate) => Ok(rate.map(|row| row.rate)),
+        Err(e) => {
+            println!("Error getting fx rate: {}", e);
+            Err(unexpected_error())
+        }
+    }
+}
+
+/// Units of `to` per unit of `from` at `at`, from the rate of the pair or else the inverse of
+/// the rate of the opposite pair
+pub(crate) async fn get_market_rate(
+    from: Currency,
+    to: Currency,
+    at: DateTime<Utc>,
+    conn: &mut sqlx::PgConnection,
+) -> Result<BigDecimal, Box<dyn BankError>> {
+    if let Some(rate) = get_stored_rate(from, to, at, conn).await? {
+        return Ok(rate);
+    }
+
+    match get_stored_rate(to, from, at, conn).await? {
+        Some(rate) => Ok(inverse_rate(&rate)),
+        None => Err(Box::new(FxError::new(
+            format!("No rate to convert {} into {}", from, to),
+            ErrorKind::NotFound,
+        ))),
+    }
+}
+
+/// Market value of the quoted amount at `at`, rounded half even
+pub(crate) async fn get_market_value(
+    quote: &FxQuote,
+    at: DateTime<Utc>,
+    conn: &mut sqlx::PgConnection,
+) -> Result<i64, Box<dyn BankError>> {
+    let market_rate = get_market_rate(quote.from_currency, quote.to_currency, at, conn).await?;
+
+    convert(
+        quote.amount,
+        quote.from_currency,
+        quote.to_currency,
+        &market_rate,
+        RoundingMode::HalfEven,
+    )
+    .ok_or_else(amount_too_large)
+}
+
+async fn get_quote(
+    id: &Uuid,
+    for_update: bool,
+    conn: &mut sqlx::PgConnection,
+) -> Result<FxQuote, Box<dyn BankError>> {
+    let query = if for_update {
+        sqlx::query_as!(
+            FxQuote,
+            r#"SELECT id, from_currency AS "from_currency: Currency", to_currency AS "to_currency: Currency", amount, converted_amount, rate, market_rate, expires_at, journal_entry_id, created_at AS "created_at!"
+            FROM fx_quote WHERE id = $1 FOR UPDATE"#,
+            id
+        )
+        .fetch_optional(conn)
+        .await
+    } else {
+        sqlx::query_as!(
+            FxQuote,
+            r#"SELECT id, from_currency AS "from_currency: Currency", to_currency AS "to_currency: Currency", amount, converted_amount, rate, market_rate, expires_at, journal_entry_id, created_at AS "created_at!"
+            FROM fx_quote WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(conn)
+        .await
+    };
+
+    match query {
+        Ok(Some(quote)) => Ok(quote),
+        Ok(None) => Err(Box::new(FxError::new(
+            format!("Quote [{}] not found", id),
+            ErrorKind::NotFound,
+        ))),
+        Err(e) => {
+            println!("Error getting fx quote: {}", e);
+            Err(unexpected_error())
+        }
+    }
+}
+
+/// Locks the quote for the transfer executing it, which must convert `from` into `to` before
+/// the quote expires. A quote is executed once, `mark_quote_used` records it.
+pub(crate) async fn claim_quote(
+    id: &Uuid,
+    from: Currency,
+    to: Currency,
+    now: DateTime<Utc>,
+    conn: &mut sqlx::PgConnection,
+) -> Result<FxQuote, Box<dyn BankError>> {
+    let quote = get_quote(id, true, conn).await?;
+
+    if quote.journal_entry_id.is_some() {
+        return Err(Box::new(FxError::new(
+            format!("Quote [{}] was already used", id),
+            ErrorKind::Conflict,
+        )));
+    }
+
+    if quote.expires_at <= now {
+        return Err(Box::new(FxError::new(
+            format!("Quote [{}] expired at {}", id, quote.expires_at),
+            ErrorKind::Conflict,
+        )));
+    }
+
+    if quote.from_currency != from || quote.to_currency != to {
+        return Err(Box::new(FxError::new(
+            format!(
+                "Quote [{}] converts {} into {}, not {} into {}",
+                id, quote.from_currency, quote.to_currency, from, to
+            ),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    Ok(quote)
+}
+
+pub(crate) async fn mark_quote_used(
+    id: &Uuid,
+    journal_entry_id: &Uuid,
+    conn: &mut sqlx::PgConnection,
+) -> Result<(), Box<dyn BankError>> {
+    let result = sqlx::query!(
+        "UPDATE fx_quote SET journal_entry_id = $2 WHERE id = $1",
+        id,
+        journal_entry_id
+    )
+    .execute(conn)
+    .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            println!("Error marking fx quote as used: {}", e);
+            Err(unexpected_error())
+        }
+    }
+}
+
+pub struct FxManager<'a> {
+    db_pool: &'a sqlx::PgPool,
+    clock: Arc<dyn Clock>,
+    quote_ttl: chrono::Duration,
+    spread_bps: u32,
+}
+
+impl<'a> FxManager<'a> {
+    pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
+        Self {
+            db_pool,
+            clock: Arc::new(SystemClock),
+            quote_ttl: DEFAULT_QUOTE_TTL,
+            spread_bps: 0,
+        }
+    }
+
+    /// Replaces the clock deciding which rates are effective and when quotes expire
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets for how long a quoted rate can be executed
+    pub fn with_quote_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.quote_ttl = ttl;
+        self
+    }
+
+    /// Sets the basis points taken off the market rate of the quotes, none by default
+    pub fn with_spread_bps(mut self, spread_bps: u32) -> Self {
+        self.spread_bps = spread_bps;
+        self
+    }
+
+    /// Stores the rates, replacing the ones of the same pair and effective time
+    pub async fn load_rates(&self, rates: &[FxRate]) -> Result<u64, Box<dyn BankError>> {
+        for rate in rates {
+            rate.validate()?;
+        }
+
+        let mut tx = match self.db_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        for rate in rates {
+            let result = sqlx::query!(
+                "INSERT INTO fx_rate (base_currency, quote_currency, rate, effective_at) VALUES ($1, $2, $3, $4)
+                ON CONFLICT (base_currency, quote_currency, effective_at) DO UPDATE SET rate = $3, created_at = NOW()",
+                rate.base_currency.code(),
+                rate.quote_currency.code(),
+                rate.rate,
+                rate.effective_at
+            )
+            .execute(&mut *tx)
+            .await;
+
+            if let Err(e) = result {
+                println!("Error saving fx rate: {}", e);
+                return Err(unexpected_error());
+            }
+        }
+
+        if let Err(e) = tx.commit().await {
+            println!("Error committing transaction: {}", e);
+            return Err(unexpected_error());
+        }
+
+        Ok(rates.len() as u64)
+    }
+
+    /// Loads the rates of a `.csv` or `.json` file, see `parse_rates_csv` and `parse_rates_json`
+    pub async fn load_rates_from_file(&self, path: &Path) -> Result<u64, Box<dyn BankError>> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                return Err(Box::new(FxError::new(
+                    format!("Can't read rates from [{}]: {}", path.display(), e),
+                    ErrorKind::InvalidInput,
+                )))
+            }
+        };
+
+        let rates = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => parse_rates_csv(&content)?,
+            Some("json") => parse_rates_json(&content)?,
+            _ => {
+                return Err(Box::new(FxError::new(
+                    format!(
+                        "Rates must be in a .csv or .json file, got [{}]",
+                        path.display()
+                    ),
+                    ErrorKind::InvalidInput,
+                )))
+            }
+        };
+
+        self.load_rates(&rates).await
+    }
+
+    /// Market rate converting `from` into `to` right now
+    pub async fn get_rate(
+        &self,
+        from: Currency,
+        to: Currency,
+    ) -> Result<BigDecimal, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error acquiring connection: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        get_market_rate(from, to, self.clock.now(), &mut conn).await
+    }
+
+    /// Locks the current rate, minus the spread, for converting `amount` minor units of `from`
+    /// into `to` until the quote expires
+    pub async fn create_quote(
+        &self,
+        from: Currency,
+        to: Currency,
+        amount: Amount,
+    ) -> Result<FxQuote, Box<dyn BankError>> {
+        if from == to {
+            return Err(Box::new(FxError::new(
+                format!("Can't convert {} into itself", from),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let now = self.clock.now();
+        let market_rate = self.get_rate(from, to).await?;
+        let rate = customer_rate(&market_rate, self.spread_bps);
+        let converted_amount = convert(amount.into(), from, to, &rate, RoundingMode::Down)
+            .ok_or_else(amount_too_large)?;
+
+        if converted_amount == 0 {
+            return Err(Box::new(FxError::new(
+                format!("Amount is too small to be converted into {}", to),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let quote = FxQuote {
+            id: Uuid::now_v7(),
+            from_currency: from,
+            to_currency: to,
+            amount: amount.into(),
+            converted_amount,
+            rate,
+            market_rate,
+            expires_at: now + self.quote_ttl,
+            journal_entry_id: None,
+            created_at: now,
+        };
+
+        let result = sqlx::query!(
+            "INSERT INTO fx_quote (id, from_currency, to_currency, amount, converted_amount, rate, market_rate, expires_at, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            quote.id,
+            quote.from_currency.code(),
+            quote.to_currency.code(),
+            quote.amount,
+            quote.converted_amount,
+            quote.rate,
+            quote.market_rate,
+            quote.expires_at,
+            quote.created_at
+        )
+        .execute(self.db_pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(quote),
+            Err(e) => {
+                println!("Error creating fx quote: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    pub async fn get_quote(&self, id: &Uuid) -> Result<FxQuote, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error acquiring connection: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        get_quote(id, false, &mut conn).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::internal::{
+        account::{
+            account::AccountManager,
+            domain::{Account, AccountType, SystemAccount},
+        },
+        clock::FixedClock,
+        test_util::{create_funded_account, get_conn_with_new_db},
+        transaction::{domain::Transaction, transaction::TransactionManager},
+    };
+
+    async fn balance(db_pool: &sqlx::PgPool, account: &Account) -> i64 {
+        AccountManager::get_balance(account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor
+    }
+
+    async fn system_balance(
+        db_pool: &sqlx::PgPool,
+        system_account: SystemAccount,
+        currency: Currency,
+    ) -> i64 {
+        let mut conn = db_pool.acquire().await.unwrap();
+        let account = AccountManager::get_system_account(system_account, currency, &mut *conn)
+            .await
+            .unwrap();
+
+        AccountManager::get_balance(&account, &mut *conn)
+            .await
+            .unwrap()
+            .amount_minor
+    }
+
+    fn usd_brl(rate: &str, effective_at: DateTime<Utc>) -> FxRate {
+        FxRate {
+            base_currency: Currency::Usd,
+            quote_currency: Currency::Brl,
+            rate: rate.parse().unwrap(),
+            effective_at,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fx_transfer_books_both_legs_and_the_spread() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let path = std::env::temp_dir().join(format!("fx_rates_{}.csv", Uuid::now_v7()));
+        std::fs::write(
+            &path,
+            format!(
+                "base_currency,quote_currency,rate,effective_at\nUSD,BRL,5.00,{}\n",
+                (Utc::now() - chrono::Duration::hours(1)).to_rfc3339()
+            ),
+        )
+        .unwrap();
+
+        let fx_manager = FxManager::new(db_pool).with_spread_bps(100);
+        assert_eq!(fx_manager.load_rates_from_file(&path).await.unwrap(), 1);
+        std::fs::remove_file(&path).unwrap();
+
+        let usd = create_funded_account(db_pool, AccountType::Checking, Currency::Usd, 10000).await;
+        let brl = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        // 10.00 USD at 5.00 minus 1% is 49.50 BRL
+        let quote = fx_manager
+            .create_quote(Currency::Usd, Currency::Brl, Amount::new(1000).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(quote.rate, "4.95".parse().unwrap());
+        assert_eq!(quote.converted_amount, 4950);
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let fx_transfer = || Transaction::FxTransfer {
+            quote_id: quote.id,
+            origin: usd.clone(),
+            destination: brl.clone(),
+        };
+
+        let receipt = transaction_manager
+            .create_transaction(fx_transfer())
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.transaction_ids.len(), 2);
+        assert_eq!(balance(db_pool, &usd).await, 9000);
+        assert_eq!(balance(db_pool, &brl).await, 4950);
+        assert_eq!(
+            system_balance(db_pool, SystemAccount::FxPosition, Currency::Usd).await,
+            1000
+        );
+        assert_eq!(
+            system_balance(db_pool, SystemAccount::FxPosition, Currency::Brl).await,
+            -5000
+        );
+        assert_eq!(
+            system_balance(db_pool, SystemAccount::FxGainLoss, Currency::Brl).await,
+            50
+        );
+        assert_eq!(
+            fx_manager
+                .get_quote(&quote.id)
+                .await
+                .unwrap()
+                .journal_entry_id,
+            Some(receipt.journal_entry_id)
+        );
+
+        let reused = transaction_manager.create_transaction(fx_transfer()).await;
+        assert_eq!(*reused.unwrap_err().kind(), ErrorKind::Conflict);
+
+        assert!(transaction_manager
+            .trial_balance()
+            .await
+            .unwrap()
+            .is_balanced());
+    }
+
+    #[tokio::test]
+    async fn test_rate_moving_after_the_quote_is_a_gain_or_loss() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let now = Utc::now();
+        let quoted_at = now - chrono::Duration::seconds(20);
+
+        let fx_manager = FxManager::new(db_pool)
+            .with_clock(Arc::new(FixedClock(quoted_at)))
+            .with_quote_ttl(chrono::Duration::minutes(5));
+        fx_manager
+            .load_rates(&[usd_brl("5.00", now - chrono::Duration::hours(1))])
+            .await
+            .unwrap();
+
+        let usd = create_funded_account(db_pool, AccountType::Checking, Currency::Usd, 10000).await;
+        let brl = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        let quote = fx_manager
+            .create_quote(Currency::Usd, Currency::Brl, Amount::new(1000).unwrap())
+            .await
+            .unwrap();
+
+        // The dollar falls while the quote is still valid, the customer keeps the locked rate
+        fx_manager
+            .load_rates(&[usd_brl("4.80", now - chrono::Duration::seconds(10))])
+            .await
+            .unwrap();
+
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::FxTransfer {
+                quote_id: quote.id,
+                origin: usd.clone(),
+                destination: brl.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(balance(db_pool, &brl).await, 5000);
+        assert_eq!(
+            system_balance(db_pool, SystemAccount::FxPosition, Currency::Brl).await,
+            -4800
+        );
+        assert_eq!(
+            system_balance(db_pool, SystemAccount::FxGainLoss, Currency::Brl).await,
+            -200
+        );
+    }
+
+    #[tokio::test]
+    async fn test_quotes_are_checked_before_executing() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let now = Utc::now();
+        FxManager::new(db_pool)
+            .load_rates(&[usd_brl("5.00", now - chrono::Duration::days(1))])
+            .await
+            .unwrap();
+
+        let usd = create_funded_account(db_pool, AccountType::Checking, Currency::Usd, 10000).await;
+        let brl = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 10000).await;
+        let eur = create_funded_account(db_pool, AccountType::Checking, Currency::Eur, 0).await;
+        let transaction_manager = TransactionManager::new(db_pool);
+
+        let expired = FxManager::new(db_pool)
+            .with_clock(Arc::new(FixedClock(now - chrono::Duration::hours(1))))
+            .create_quote(Currency::Usd, Currency::Brl, Amount::new(1000).unwrap())
+            .await
+            .unwrap();
+
+        let result = transaction_manager
+            .create_transaction(Transaction::FxTransfer {
+                quote_id: expired.id,
+                origin: usd.clone(),
+                destination: brl.clone(),
+            })
+            .await;
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::Conflict);
+
+        // The opposite pair uses the inverse rate, 10.00 BRL at 0.2 is 2.00 USD
+        let inverse = FxManager::new(db_pool)
+            .create_quote(Currency::Brl, Currency::Usd, Amount::new(1000).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(inverse.converted_amount, 200);
+
+        let result = transaction_manager
+            .create_transaction(Transaction::FxTransfer {
+                quote_id: inverse.id,
+                origin: brl.clone(),
+                destination: eur.clone(),
+            })
+            .await;
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        let no_rate = FxManager::new(db_pool)
+            .create_quote(Currency::Eur, Currency::Brl, Amount::new(1000).unwrap())
+            .await;
+        assert_eq!(*no_rate.unwrap_err().kind(), ErrorKind::NotFound);
+
+        assert_eq!(balance(db_pool, &usd).await, 10000);
+        assert_eq!(balance(db_pool, &brl).await, 10000);
+    }
+
+    #[tokio::test]
+    async fn test_reverse_fx_transfer() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let fx_manager = FxManager::new(db_pool).with_spread_bps(50);
+        fx_manager
+            .load_rates(&[usd_brl("5.4321", Utc::now() - chrono::Duration::hours(1))])
+            .await
+            .unwrap();
+
+        let usd = create_funded_account(db_pool, AccountType::Checking, Currency::Usd, 10000).await;
+        let brl = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        let quote = fx_manager
+            .create_quote(Currency::Usd, Currency::Brl, Amount::new(1234).unwrap())
+            .await
+            .unwrap();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let receipt = transaction_manager
+            .create_transaction(Transaction::FxTransfer {
+                quote_id: quote.id,
+                origin: usd.clone(),
+                destination: brl.clone(),
+            })
+            .await
+            .unwrap();
+
+        let partial = transaction_manager
+            .refund(
+                &receipt.journal_entry_id,
+                Amount::new(100).unwrap(),
+                "partial",
+            )
+            .await;
+        assert_eq!(*partial.unwrap_err().kind(), ErrorKind::InvalidInput);
+
+        transaction_manager
+            .reverse(&receipt.journal_entry_id, "customer request")
+            .await
+            .unwrap();
+
+        assert_eq!(balance(db_pool, &usd).await, 10000);
+        assert_eq!(balance(db_pool, &brl).await, 0);
+        assert_eq!(
+            system_balance(db_pool, SystemAccount::FxGainLoss, Currency::Brl).await,
+            0
+        );
+        assert!(transaction_manager
+            .trial_balance()
+            .await
+            .unwrap()
+            .is_balanced());
+    }
+
+    #[tokio::test]
+    async fn test_refund_fx_transfer_in_destination_currency() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let fx_manager = FxManager::new(db_pool).with_spread_bps(50);
+        fx_manager
+            .load_rates(&[usd_brl("5.4321", Utc::now() - chrono::Duration::hours(1))])
+            .await
+            .unwrap();
+
+        let usd = create_funded_account(db_pool, AccountType::Checking, Currency::Usd, 10000).await;
+        let brl = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        let quote = fx_manager
+            .create_quote(Currency::Usd, Currency::Brl, Amount::new(1234).unwrap())
+            .await
+            .unwrap();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let receipt = transaction_manager
+            .create_transaction(Transaction::FxTransfer {
+                quote_id: quote.id,
+                origin: usd.clone(),
+                destination: brl.clone(),
+            })
+            .await
+            .unwrap();
+
+        let currency = transaction_manager
+            .get_transaction_currency(&receipt.journal_entry_id)
+            .await
+            .unwrap();
+        assert_eq!(currency, Currency::Brl);
+
+        // The whole amount, given in the currency of the destination, refunds everything
+        let credited = balance(db_pool, &brl).await;
+        transaction_manager
+            .refund(
+                &receipt.journal_entry_id,
+                Amount::new(credited).unwrap(),
+                "customer request",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(balance(db_pool, &usd).await, 10000);
+        assert_eq!(balance(db_pool, &brl).await, 0);
+    }
+}
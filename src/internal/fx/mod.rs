@@ -0,0 +1,4 @@
+pub mod domain;
+pub mod error;
+#[allow(clippy::module_inception)]
+pub mod fx;
@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::internal::{error::ErrorKind, money::domain::Currency};
+
+use super::error::HoldError;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldStatus {
+    Active,
+    Captured,
+    Released,
+    /// Released by the sweeper after `expires_at`
+    Expired,
+}
+
+impl HoldStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HoldStatus::Active => "active",
+            HoldStatus::Captured => "captured",
+            HoldStatus::Released => "released",
+            HoldStatus::Expired => "expired",
+        }
+    }
+}
+
+impl FromStr for HoldStatus {
+    type Err = HoldError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "active" => Ok(HoldStatus::Active),
+            "captured" => Ok(HoldStatus::Captured),
+            "released" => Ok(HoldStatus::Released),
+            "expired" => Ok(HoldStatus::Expired),
+            _ => Err(HoldError::new(
+                format!("Unknown hold status [{}]", value),
+                ErrorKind::Internal,
+            )),
+        }
+    }
+}
+
+/// Funds reserved on an account, they can't be spent until the hold is captured or released
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Hold {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    /// Currency of the account, every amount of the hold is in it
+    pub currency: Currency,
+    pub amount: i64,
+    pub status: HoldStatus,
+    /// Set once captured, it may be lower than `amount`
+    pub captured_amount: Option<i64>,
+    /// Journal entry booked by the capture
+    pub journal_entry_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
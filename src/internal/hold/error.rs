@@ -0,0 +1,22 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct HoldError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl HoldError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for HoldError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
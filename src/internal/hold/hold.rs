@@ -0,0 +1,572 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, SystemAccount},
+    },
+    error::{BankError, ErrorKind},
+    money::domain::{Amount, Currency},
+    transaction::{
+        domain::TransactionType,
+        ledger::{self, LedgerAccount, Posting},
+        transaction::TransactionManager,
+    },
+};
+
+use super::{
+    domain::{Hold, HoldStatus},
+    error::HoldError,
+};
+
+struct HoldRow {
+    id: Uuid,
+    account_id: Uuid,
+    currency: Currency,
+    amount: i64,
+    status: String,
+    captured_amount: Option<i64>,
+    journal_entry_id: Option<Uuid>,
+    expires_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+}
+
+impl TryFrom<HoldRow> for Hold {
+    type Error = Box<dyn BankError>;
+
+    fn try_from(row: HoldRow) -> Result<Self, Self::Error> {
+        let status = match row.status.parse::<HoldStatus>() {
+            Ok(status) => status,
+            Err(e) => return Err(Box::new(e)),
+        };
+
+        Ok(Hold {
+            id: row.id,
+            account_id: row.account_id,
+            currency: row.currency,
+            amount: row.amount,
+            status,
+            captured_amount: row.captured_amount,
+            journal_entry_id: row.journal_entry_id,
+            expires_at: row.expires_at,
+            created_at: row.created_at,
+        })
+    }
+}
+
+pub struct HoldManager<'a> {
+    db_pool: &'a sqlx::PgPool,
+}
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(HoldError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+impl<'a> HoldManager<'a> {
+    pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
+        Self { db_pool }
+    }
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'a, sqlx::Postgres>, Box<dyn BankError>> {
+        match self.db_pool.begin().await {
+            Ok(tx) => Ok(tx),
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    async fn commit(tx: sqlx::Transaction<'a, sqlx::Postgres>) -> Result<(), Box<dyn BankError>> {
+        match tx.commit().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error committing transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    async fn get_hold(
+        hold_id: &Uuid,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<Hold, Box<dyn BankError>> {
+        let hold = sqlx::query_as!(
+            HoldRow,
+            r#"SELECT hold.id, hold.account_id, account.currency AS "currency: Currency", hold.amount, hold.status, hold.captured_amount, hold.journal_entry_id, hold.expires_at, hold.created_at AS "created_at!"
+            FROM hold JOIN account ON account.id = hold.account_id WHERE hold.id = $1"#,
+            hold_id
+        )
+        .fetch_optional(conn)
+        .await;
+
+        match hold {
+            Ok(Some(hold)) => hold.try_into(),
+            Ok(None) => Err(Box::new(HoldError::new(
+                format!("Hold [{}] not found", hold_id),
+                ErrorKind::NotFound,
+            ))),
+            Err(e) => {
+                println!("Error getting hold: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    pub async fn get(&self, hold_id: &Uuid) -> Result<Hold, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error getting database connection: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        HoldManager::get_hold(hold_id, &mut conn).await
+    }
+
+    /// Locks the hold account and then the hold itself, the same order used to place holds
+    async fn lock_active_hold(
+        hold_id: &Uuid,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<Hold, Box<dyn BankError>> {
+        let hold = HoldManager::get_hold(hold_id, conn).await?;
+
+        let account = AccountManager::get_account_from_id(&hold.account_id, conn).await?;
+        AccountManager::lock_accounts(&[&account], conn).await?;
+
+        let locked = sqlx::query!("SELECT id FROM hold WHERE id = $1 FOR UPDATE", hold_id)
+            .fetch_one(&mut *conn)
+            .await;
+
+        if let Err(e) = locked {
+            println!("Error locking hold: {}", e);
+            return Err(unexpected_error());
+        }
+
+        // Reads it again, it may have changed while waiting for the locks
+        let hold = HoldManager::get_hold(hold_id, conn).await?;
+
+        if hold.status != HoldStatus::Active || hold.expires_at <= Utc::now() {
+            return Err(Box::new(HoldError::new(
+                format!("Hold [{}] is no longer active", hold_id),
+                ErrorKind::Conflict,
+            )));
+        }
+
+        Ok(hold)
+    }
+
+    /// Reserves `amount` of the available balance until `expires_at`
+    pub async fn place_hold(
+        &self,
+        account: &Account,
+        amount: Amount,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Hold, Box<dyn BankError>> {
+        if expires_at <= Utc::now() {
+            return Err(Box::new(HoldError::new(
+                "Hold expiration must be in the future".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let mut tx = self.begin().await?;
+
+        AccountManager::lock_accounts(&[account], &mut *tx).await?;
+        TransactionManager::ensure_can_send(account, &mut *tx).await?;
+        TransactionManager::ensure_funds(amount.into(), account, &mut *tx).await?;
+
+        let hold_id = Uuid::now_v7();
+        let amount_parsed = amount.minor_units();
+        let result = sqlx::query!(
+            "INSERT INTO hold (id, account_id, amount, expires_at) VALUES ($1, $2, $3, $4)",
+            hold_id,
+            account.id(),
+            amount_parsed,
+            expires_at
+        )
+        .execute(&mut *tx)
+        .await;
+
+        if let Err(e) = result {
+            println!("Error creating hold: {}", e);
+            return Err(unexpected_error());
+        }
+
+        let hold = HoldManager::get_hold(&hold_id, &mut tx).await?;
+        HoldManager::commit(tx).await?;
+
+        Ok(hold)
+    }
+
+    /// Settles up to the held amount, the rest of the hold is released
+    pub async fn capture_hold(
+        &self,
+        hold_id: &Uuid,
+        amount: Amount,
+    ) -> Result<Hold, Box<dyn BankError>> {
+        let mut tx = self.begin().await?;
+
+        let hold = HoldManager::lock_active_hold(hold_id, &mut tx).await?;
+        let amount_parsed = amount.minor_units();
+
+        if amount_parsed > hold.amount {
+            return Err(Box::new(HoldError::new(
+                format!(
+                    "Captured amount must be between 1 and the {} held",
+                    hold.amount
+                ),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        // Closes the hold first, so its own reservation doesn't count against the capture
+        let closed = sqlx::query!(
+            "UPDATE hold SET status = $2, captured_amount = $3, closed_at = NOW() WHERE id = $1",
+            hold_id,
+            HoldStatus::Captured.as_str(),
+            amount_parsed
+        )
+        .execute(&mut *tx)
+        .await;
+
+        if let Err(e) = closed {
+            println!("Error capturing hold: {}", e);
+            return Err(unexpected_error());
+        }
+
+        let account = AccountManager::get_account_from_id(&hold.account_id, &mut *tx).await?;
+        TransactionManager::ensure_can_send(&account, &mut *tx).await?;
+        TransactionManager::ensure_withdraw_allowed(&account, &mut *tx).await?;
+        TransactionManager::ensure_funds(amount_parsed, &account, &mut *tx).await?;
+
+        let journal_entry = ledger::post_journal_entry(
+            "hold capture",
+            vec![
+                Posting::new(
+                    LedgerAccount::Customer(hold.account_id),
+                    -amount_parsed,
+                    TransactionType::HoldCapture,
+                ),
+                Posting::new(
+                    LedgerAccount::System(SystemAccount::Settlement, *account.currency()),
+                    amount_parsed,
+                    TransactionType::HoldCapture,
+                ),
+            ],
+            &mut *tx,
+        )
+        .await?;
+
+        let linked = sqlx::query!(
+            "UPDATE hold SET journal_entry_id = $2 WHERE id = $1",
+            hold_id,
+            journal_entry.id
+        )
+        .execute(&mut *tx)
+        .await;
+
+        if let Err(e) = linked {
+            println!("Error linking hold to its journal entry: {}", e);
+            return Err(unexpected_error());
+        }
+
+        let hold = HoldManager::get_hold(hold_id, &mut tx).await?;
+        HoldManager::commit(tx).await?;
+
+        Ok(hold)
+    }
+
+    pub async fn release_hold(&self, hold_id: &Uuid) -> Result<Hold, Box<dyn BankError>> {
+        let mut tx = self.begin().await?;
+
+        HoldManager::lock_active_hold(hold_id, &mut tx).await?;
+
+        let released = sqlx::query!(
+            "UPDATE hold SET status = $2, closed_at = NOW() WHERE id = $1",
+            hold_id,
+            HoldStatus::Released.as_str()
+        )
+        .execute(&mut *tx)
+        .await;
+
+        if let Err(e) = released {
+            println!("Error releasing hold: {}", e);
+            return Err(unexpected_error());
+        }
+
+        let hold = HoldManager::get_hold(hold_id, &mut tx).await?;
+        HoldManager::commit(tx).await?;
+
+        Ok(hold)
+    }
+
+    /// Marks every active hold past its expiration as expired, returning how many there were.
+    ///
+    /// Expired holds already stop counting against the available balance, this only closes them.
+    pub async fn release_expired_holds(&self) -> Result<u64, Box<dyn BankError>> {
+        let result = sqlx::query!(
+            "UPDATE hold SET status = $1, closed_at = NOW() WHERE status = 'active' AND expires_at <= NOW()",
+            HoldStatus::Expired.as_str()
+        )
+        .execute(self.db_pool)
+        .await;
+
+        match result {
+            Ok(result) => Ok(result.rows_affected()),
+            Err(e) => {
+                println!("Error releasing expired holds: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    pub async fn list_active_holds(
+        &self,
+        account: &Account,
+    ) -> Result<Vec<Hold>, Box<dyn BankError>> {
+        let holds = sqlx::query_as!(
+            HoldRow,
+            r#"SELECT hold.id, hold.account_id, account.currency AS "currency: Currency", hold.amount, hold.status, hold.captured_amount, hold.journal_entry_id, hold.expires_at, hold.created_at AS "created_at!"
+            FROM hold JOIN account ON account.id = hold.account_id
+            WHERE hold.account_id = $1 AND hold.status = 'active' AND hold.expires_at > NOW() ORDER BY hold.created_at"#,
+            account.id()
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        match holds {
+            Ok(holds) => holds.into_iter().map(Hold::try_from).collect(),
+            Err(e) => {
+                println!("Error listing holds: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::account::domain::{AccountType, SAVINGS_MONTHLY_WITHDRAW_LIMIT};
+    use crate::internal::test_util::{create_funded_account, get_conn_with_new_db};
+    use crate::internal::transaction::domain::Transaction;
+
+    async fn available_balance(db_pool: &sqlx::PgPool, account: &Account) -> i64 {
+        AccountManager::get_available_balance(account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor
+    }
+
+    #[tokio::test]
+    async fn test_hold_reserves_funds() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let hold_manager = HoldManager::new(db_pool);
+
+        hold_manager
+            .place_hold(
+                &account,
+                Amount::new(70).unwrap(),
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(available_balance(db_pool, &account).await, 30);
+
+        let withdraw = TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(31).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            withdraw.unwrap_err().message(),
+            "Insufficient funds, available amount is 0.30 BRL"
+        );
+
+        let second_hold = hold_manager
+            .place_hold(
+                &account,
+                Amount::new(31).unwrap(),
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await;
+
+        assert_eq!(
+            second_hold.unwrap_err().message(),
+            "Insufficient funds, available amount is 0.30 BRL"
+        );
+        assert_eq!(
+            hold_manager
+                .list_active_holds(&account)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_capture_hold() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let hold_manager = HoldManager::new(db_pool);
+
+        let hold = hold_manager
+            .place_hold(
+                &account,
+                Amount::new(70).unwrap(),
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+
+        let too_much = hold_manager
+            .capture_hold(&hold.id, Amount::new(71).unwrap())
+            .await;
+        assert_eq!(too_much.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        let captured = hold_manager
+            .capture_hold(&hold.id, Amount::new(60).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(captured.status, HoldStatus::Captured);
+        assert_eq!(captured.captured_amount, Some(60));
+        assert!(captured.journal_entry_id.is_some());
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 40);
+        // The 10 not captured are released with the hold
+        assert_eq!(available_balance(db_pool, &account).await, 40);
+
+        let again = hold_manager
+            .capture_hold(&hold.id, Amount::new(10).unwrap())
+            .await;
+        assert_eq!(again.unwrap_err().kind(), &ErrorKind::Conflict);
+
+        assert!(TransactionManager::new(db_pool)
+            .trial_balance()
+            .await
+            .unwrap()
+            .is_balanced());
+    }
+
+    #[tokio::test]
+    async fn test_captures_count_against_savings_withdraw_limit() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Savings, Currency::Brl, 1000).await;
+
+        let hold_manager = HoldManager::new(db_pool);
+        let expires_at = Utc::now() + chrono::Duration::hours(1);
+
+        for _ in 0..SAVINGS_MONTHLY_WITHDRAW_LIMIT {
+            let hold = hold_manager
+                .place_hold(&account, Amount::new(10).unwrap(), expires_at)
+                .await
+                .unwrap();
+            hold_manager
+                .capture_hold(&hold.id, Amount::new(10).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let hold = hold_manager
+            .place_hold(&account, Amount::new(10).unwrap(), expires_at)
+            .await
+            .unwrap();
+        let capture = hold_manager
+            .capture_hold(&hold.id, Amount::new(10).unwrap())
+            .await;
+
+        assert_eq!(capture.unwrap_err().kind(), &ErrorKind::Conflict);
+        // The hold is still active, so its amount stays reserved
+        assert_eq!(available_balance(db_pool, &account).await, 930);
+    }
+
+    #[tokio::test]
+    async fn test_release_hold() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let hold_manager = HoldManager::new(db_pool);
+
+        let hold = hold_manager
+            .place_hold(
+                &account,
+                Amount::new(70).unwrap(),
+                Utc::now() + chrono::Duration::hours(1),
+            )
+            .await
+            .unwrap();
+
+        let released = hold_manager.release_hold(&hold.id).await.unwrap();
+
+        assert_eq!(released.status, HoldStatus::Released);
+        assert_eq!(available_balance(db_pool, &account).await, 100);
+
+        let capture = hold_manager
+            .capture_hold(&hold.id, Amount::new(70).unwrap())
+            .await;
+        assert_eq!(capture.unwrap_err().kind(), &ErrorKind::Conflict);
+    }
+
+    #[tokio::test]
+    async fn test_expired_holds() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let hold_manager = HoldManager::new(db_pool);
+
+        let hold = hold_manager
+            .place_hold(
+                &account,
+                Amount::new(70).unwrap(),
+                Utc::now() + chrono::Duration::milliseconds(200),
+            )
+            .await
+            .unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+
+        assert_eq!(available_balance(db_pool, &account).await, 100);
+
+        let capture = hold_manager
+            .capture_hold(&hold.id, Amount::new(70).unwrap())
+            .await;
+        assert_eq!(capture.unwrap_err().kind(), &ErrorKind::Conflict);
+
+        assert_eq!(hold_manager.release_expired_holds().await.unwrap(), 1);
+        assert_eq!(hold_manager.release_expired_holds().await.unwrap(), 0);
+
+        let mut conn = db_pool.acquire().await.unwrap();
+        let expired = HoldManager::get_hold(&hold.id, &mut conn).await.unwrap();
+        assert_eq!(expired.status, HoldStatus::Expired);
+    }
+}
@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::internal::error::ErrorKind;
+
+use super::error::InterestError;
+
+/// Decimals kept for each daily accrual
+pub const ACCRUAL_SCALE: i64 = 10;
+
+/// How many days a day of interest is worth, and how many days a year has
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DayCountConvention {
+    /// Actual days over a 365 days year
+    Act365,
+    /// Every month has 30 days, over a 360 days year
+    Thirty360,
+}
+
+impl DayCountConvention {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DayCountConvention::Act365 => "act_365",
+            DayCountConvention::Thirty360 => "30_360",
+        }
+    }
+
+    /// Days of interest earned by holding a balance through the end of `date`
+    pub fn accrual_days(&self, date: NaiveDate) -> i64 {
+        match self {
+            DayCountConvention::Act365 => 1,
+            DayCountConvention::Thirty360 => match date.succ_opt() {
+                Some(next) => days_30_360(date, next),
+                None => 1,
+            },
+        }
+    }
+
+    pub fn year_days(&self) -> i64 {
+        match self {
+            DayCountConvention::Act365 => 365,
+            DayCountConvention::Thirty360 => 360,
+        }
+    }
+
+    /// Interest earned on `date` by an end of day `balance`, rounded to `ACCRUAL_SCALE` decimals
+    pub fn accrue(&self, balance: i64, annual_rate: &BigDecimal, date: NaiveDate) -> BigDecimal {
+        let interest =
+            BigDecimal::from(balance) * annual_rate * BigDecimal::from(self.accrual_days(date))
+                / BigDecimal::from(self.year_days());
+
+        interest.with_scale_round(ACCRUAL_SCALE, RoundingMode::HalfEven)
+    }
+}
+
+impl FromStr for DayCountConvention {
+    type Err = InterestError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "act_365" => Ok(DayCountConvention::Act365),
+            "30_360" => Ok(DayCountConvention::Thirty360),
+            _ => Err(InterestError::new(
+                format!("Unknown day count convention [{}]", value),
+                ErrorKind::InvalidInput,
+            )),
+        }
+    }
+}
+
+/// 30/360 day count between two dates, the 31st counts as the 30th
+fn days_30_360(start: NaiveDate, end: NaiveDate) -> i64 {
+    let start_day = start.day().min(30) as i64;
+    let end_day = if start_day == 30 {
+        end.day().min(30) as i64
+    } else {
+        end.day() as i64
+    };
+
+    360 * (end.year() - start.year()) as i64
+        + 30 * (end.month() as i64 - start.month() as i64)
+        + (end_day - start_day)
+}
+
+/// Rate applied to every savings account
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InterestSettings {
+    /// `0.05` for 5% a year
+    pub annual_rate: BigDecimal,
+    pub day_count: DayCountConvention,
+}
+
+/// Interest earned by an account on one day, not credited until the month is capitalized
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InterestAccrual {
+    pub account_id: Uuid,
+    pub accrual_date: NaiveDate,
+    pub balance: i64,
+    pub amount: BigDecimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct InterestCapitalization {
+    pub id: Uuid,
+    pub account_id: Uuid,
+    /// First day of the month
+    pub month: NaiveDate,
+    /// Accrued during the month plus what was carried from the previous one
+    pub accrued: BigDecimal,
+    /// Whole amount credited to the account
+    pub amount: i64,
+    pub carry_over: BigDecimal,
+    /// Not set when there was nothing to credit
+    pub journal_entry_id: Option<Uuid>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_30_360_months_have_30_days() {
+        for month in 1..=12 {
+            let first = NaiveDate::from_ymd_opt(2024, month, 1).unwrap();
+            let days: i64 = first
+                .iter_days()
+                .take_while(|date| date.month() == month)
+                .map(|date| DayCountConvention::Thirty360.accrual_days(date))
+                .sum();
+
+            assert_eq!(days, 30);
+        }
+    }
+
+    #[test]
+    fn test_accrue() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 15).unwrap();
+        let rate: BigDecimal = "0.05".parse().unwrap();
+
+        assert_eq!(
+            DayCountConvention::Act365.accrue(1000, &rate, date),
+            "0.1369863014".parse::<BigDecimal>().unwrap()
+        );
+        assert_eq!(
+            DayCountConvention::Thirty360.accrue(1000, &rate, date),
+            "0.1388888889".parse::<BigDecimal>().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_30_360_month_ends() {
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(days_30_360(day(2024, 1, 30), day(2024, 1, 31)), 0);
+        assert_eq!(days_30_360(day(2024, 1, 31), day(2024, 2, 1)), 1);
+        assert_eq!(days_30_360(day(2023, 2, 28), day(2023, 3, 1)), 3);
+        assert_eq!(days_30_360(day(2024, 2, 29), day(2024, 3, 1)), 2);
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct InterestError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl InterestError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for InterestError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<InterestError> for Box<dyn BankError> {
+    fn from(error: InterestError) -> Self {
+        Box::new(error)
+    }
+}
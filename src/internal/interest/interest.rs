@@ -0,0 +1,539 @@
+use std::sync::Arc;
+
+use bigdecimal::RoundingMode;
+use chrono::{Datelike, Months, NaiveDate};
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, AccountStatus, AccountType},
+    },
+    clock::{Clock, SystemClock},
+    error::{BankError, ErrorKind},
+    transaction::transaction::TransactionManager,
+};
+
+use super::{
+    domain::{InterestCapitalization, InterestSettings},
+    error::InterestError,
+};
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(InterestError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+fn first_day_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// Accrues interest on savings accounts every day, and credits it once a month
+pub struct InterestManager<'a> {
+    db_pool: &'a sqlx::PgPool,
+    settings: InterestSettings,
+    clock: Arc<dyn Clock>,
+}
+
+impl<'a> InterestManager<'a> {
+    pub fn new(db_pool: &'a sqlx::PgPool, settings: InterestSettings) -> Self {
+        Self {
+            db_pool,
+            settings,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Replaces the clock deciding which days and months are already over
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn today(&self) -> NaiveDate {
+        self.clock.now().date_naive()
+    }
+
+    /// Accrues every day since the last one accrued up to yesterday, then capitalizes the months
+    /// over since the first of them. Both are no-ops if already done, so the job can run as
+    /// often as needed, and days it missed while not running are caught up.
+    pub async fn run(&self) -> Result<(), Box<dyn BankError>> {
+        let today = self.today();
+        let Some(yesterday) = today.pred_opt() else {
+            return Ok(());
+        };
+
+        // The first run only accrues yesterday
+        let first_day = match self.last_accrued_date().await? {
+            Some(last) => last.succ_opt().unwrap_or(today),
+            None => yesterday,
+        };
+
+        let mut date = first_day;
+        while date <= yesterday {
+            self.accrue(date).await?;
+            self.record_accrued_date(date).await?;
+
+            date = match date.succ_opt() {
+                Some(next) => next,
+                None => break,
+            };
+        }
+
+        if let Some(last_month) = first_day_of_month(today).checked_sub_months(Months::new(1)) {
+            let mut month = first_day_of_month(first_day).min(last_month);
+            while month <= last_month {
+                self.capitalize(month).await?;
+
+                month = match month.checked_add_months(Months::new(1)) {
+                    Some(next_month) => next_month,
+                    None => break,
+                };
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Last day `run` accrued, None before its first run
+    async fn last_accrued_date(&self) -> Result<Option<NaiveDate>, Box<dyn BankError>> {
+        let last = sqlx::query!("SELECT accrued_through FROM interest_accrual_run WHERE id = 1")
+            .fetch_optional(self.db_pool)
+            .await;
+
+        match last {
+            Ok(row) => Ok(row.map(|row| row.accrued_through)),
+            Err(e) => {
+                println!("Error getting last accrued date: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Never moves back, a concurrent run may have gone further already
+    async fn record_accrued_date(&self, date: NaiveDate) -> Result<(), Box<dyn BankError>> {
+        let result = sqlx::query!(
+            "INSERT INTO interest_accrual_run (id, accrued_through) VALUES (1, $1)
+            ON CONFLICT (id) DO UPDATE SET accrued_through = GREATEST(interest_accrual_run.accrued_through, EXCLUDED.accrued_through)",
+            date
+        )
+        .execute(self.db_pool)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error recording last accrued date: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Records the interest earned on `date` by every savings account, based on its end of day
+    /// balance. Accounts already accrued for that date are skipped, returns how many were
+    /// accrued now.
+    pub async fn accrue(&self, date: NaiveDate) -> Result<u64, Box<dyn BankError>> {
+        if date >= self.today() {
+            return Err(Box::new(InterestError::new(
+                format!("Day [{}] is not over yet", date),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let end_of_day = match date.succ_opt().and_then(|next| next.and_hms_opt(0, 0, 0)) {
+            Some(end_of_day) => end_of_day.and_utc(),
+            None => return Err(unexpected_error()),
+        };
+
+        let balances = sqlx::query!(
+            r#"SELECT account.id, COALESCE(SUM(transaction.amount), 0)::BIGINT AS "balance!"
+            FROM account
+            JOIN transaction ON transaction.account_id = account.id AND transaction.created_at < $2
+            WHERE account.type = $1 AND account.status <> $3
+            AND NOT EXISTS (SELECT 1 FROM interest_accrual WHERE account_id = account.id AND accrual_date = $4)
+            GROUP BY account.id"#,
+            AccountType::Savings.as_str(),
+            end_of_day,
+            AccountStatus::Closed.as_str(),
+            date
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        let balances = match balances {
+            Ok(balances) => balances,
+            Err(e) => {
+                println!("Error getting end of day balances: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let mut accrued = 0;
+        for row in balances {
+            // Overdrawn savings don't earn anything, and don't pay interest here either
+            let balance = row.balance.max(0);
+            let amount = self
+                .settings
+                .day_count
+                .accrue(balance, &self.settings.annual_rate, date);
+
+            let result = sqlx::query!(
+                "INSERT INTO interest_accrual (account_id, accrual_date, balance, annual_rate, day_count, amount) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+                row.id,
+                date,
+                balance,
+                self.settings.annual_rate,
+                self.settings.day_count.as_str(),
+                amount
+            )
+            .execute(self.db_pool)
+            .await;
+
+            match result {
+                Ok(result) => accrued += result.rows_affected(),
+                Err(e) => {
+                    println!("Error recording interest accrual: {}", e);
+                    return Err(unexpected_error());
+                }
+            }
+        }
+
+        Ok(accrued)
+    }
+
+    /// Credits the whole part of the interest accrued during the month of `month`, the fraction
+    /// left is carried to the next month. Accounts already capitalized for that month are
+    /// skipped.
+    pub async fn capitalize(
+        &self,
+        month: NaiveDate,
+    ) -> Result<Vec<InterestCapitalization>, Box<dyn BankError>> {
+        let month = first_day_of_month(month);
+        let next_month = match month.checked_add_months(Months::new(1)) {
+            Some(next_month) => next_month,
+            None => return Err(unexpected_error()),
+        };
+
+        if next_month > self.today() {
+            return Err(Box::new(InterestError::new(
+                format!("Month [{}] is not over yet", month.format("%Y-%m")),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        let accounts = sqlx::query!(
+            r#"SELECT DISTINCT account_id FROM interest_accrual
+            WHERE accrual_date >= $1 AND accrual_date < $2
+            AND NOT EXISTS (SELECT 1 FROM interest_capitalization WHERE interest_capitalization.account_id = interest_accrual.account_id AND month = $1)"#,
+            month,
+            next_month
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        let accounts = match accounts {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                println!("Error getting accounts to capitalize: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let mut capitalizations = Vec::new();
+        for row in accounts {
+            if let Some(capitalization) = self
+                .capitalize_account(&row.account_id, month, next_month)
+                .await?
+            {
+                capitalizations.push(capitalization);
+            }
+        }
+
+        Ok(capitalizations)
+    }
+
+    async fn capitalize_account(
+        &self,
+        account_id: &Uuid,
+        month: NaiveDate,
+        next_month: NaiveDate,
+    ) -> Result<Option<InterestCapitalization>, Box<dyn BankError>> {
+        let mut tx = match self.db_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let account: Account = AccountManager::get_account_from_id(account_id, &mut *tx).await?;
+        AccountManager::lock_accounts(&[&account], &mut *tx).await?;
+
+        // Checked again under the lock, a concurrent run may have capitalized it already
+        let accrued = sqlx::query!(
+            r#"SELECT COALESCE(SUM(amount), 0) AS "accrued!",
+            (SELECT carry_over FROM interest_capitalization WHERE account_id = $1 AND month < $2 ORDER BY month DESC LIMIT 1) AS carry_over,
+            EXISTS (SELECT 1 FROM interest_capitalization WHERE account_id = $1 AND month = $2) AS "capitalized!"
+            FROM interest_accrual
+            WHERE account_id = $1 AND accrual_date >= $2 AND accrual_date < $3"#,
+            account_id,
+            month,
+            next_month
+        )
+        .fetch_one(&mut *tx)
+        .await;
+
+        let accrued = match accrued {
+            Ok(accrued) => accrued,
+            Err(e) => {
+                println!("Error getting accrued interest: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        if accrued.capitalized {
+            return Ok(None);
+        }
+
+        let total = accrued.accrued + accrued.carry_over.unwrap_or_default();
+        let amount = total.with_scale_round(0, RoundingMode::Down);
+        let carry_over = &total - &amount;
+
+        let amount: i64 = match amount.to_string().parse() {
+            Ok(amount) => amount,
+            Err(e) => {
+                println!("Error converting interest amount: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let journal_entry_id = if amount > 0 {
+            let receipt =
+                TransactionManager::create_interest_credit(amount, &account, &mut *tx).await?;
+            Some(receipt.journal_entry_id)
+        } else {
+            None
+        };
+
+        let capitalization = sqlx::query_as!(
+            InterestCapitalization,
+            "INSERT INTO interest_capitalization (id, account_id, month, accrued, amount, carry_over, journal_entry_id) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, account_id, month, accrued, amount, carry_over, journal_entry_id",
+            Uuid::now_v7(),
+            account_id,
+            month,
+            total,
+            amount,
+            carry_over,
+            journal_entry_id
+        )
+        .fetch_one(&mut *tx)
+        .await;
+
+        let capitalization = match capitalization {
+            Ok(capitalization) => capitalization,
+            Err(e) => {
+                println!("Error recording interest capitalization: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        match tx.commit().await {
+            Ok(_) => Ok(Some(capitalization)),
+            Err(e) => {
+                println!("Error committing transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+    use chrono::{Days, Utc};
+
+    use super::*;
+    use crate::internal::{
+        clock::FixedClock,
+        interest::domain::DayCountConvention,
+        money::domain::Currency,
+        test_util::{create_funded_account, get_conn_with_new_db},
+    };
+
+    fn settings(annual_rate: &str) -> InterestSettings {
+        InterestSettings {
+            annual_rate: annual_rate.parse().unwrap(),
+            day_count: DayCountConvention::Act365,
+        }
+    }
+
+    /// Clock set to midnight of `date`, the day before is over
+    fn clock_at(date: NaiveDate) -> Arc<dyn Clock> {
+        Arc::new(FixedClock(date.and_hms_opt(0, 0, 0).unwrap().and_utc()))
+    }
+
+    #[tokio::test]
+    async fn test_accrue_is_idempotent() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let savings =
+            create_funded_account(db_pool, AccountType::Savings, Currency::Brl, 1000).await;
+        create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 1000).await;
+
+        let today = Utc::now().date_naive();
+        let tomorrow = today.succ_opt().unwrap();
+
+        let result = InterestManager::new(db_pool, settings("0.0365"))
+            .with_clock(clock_at(today))
+            .accrue(today)
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        let interest_manager =
+            InterestManager::new(db_pool, settings("0.0365")).with_clock(clock_at(tomorrow));
+
+        // Only the savings account earns interest
+        assert_eq!(interest_manager.accrue(today).await.unwrap(), 1);
+        assert_eq!(interest_manager.accrue(today).await.unwrap(), 0);
+
+        let accrual = sqlx::query!(
+            "SELECT balance, amount FROM interest_accrual WHERE account_id = $1",
+            savings.id()
+        )
+        .fetch_one(db_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(accrual.balance, 1000);
+        assert_eq!(accrual.amount, "0.1".parse::<BigDecimal>().unwrap());
+
+        // The balance before the first deposit was zero
+        let yesterday = today.pred_opt().unwrap();
+        interest_manager.accrue(yesterday).await.unwrap();
+
+        let accruals = sqlx::query!(
+            "SELECT COUNT(*) AS \"count!\" FROM interest_accrual WHERE accrual_date = $1",
+            yesterday
+        )
+        .fetch_one(db_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(accruals.count, 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_catches_up_missed_days() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Savings, Currency::Brl, 1000).await;
+
+        // Days after the deposit, so every one of them has the same balance
+        let first_day = Utc::now()
+            .date_naive()
+            .checked_add_days(Days::new(2))
+            .unwrap();
+        let day = |offset: u64| first_day.checked_add_days(Days::new(offset)).unwrap();
+
+        InterestManager::new(db_pool, settings("0.0365"))
+            .with_clock(clock_at(day(1)))
+            .run()
+            .await
+            .unwrap();
+
+        // The job doesn't run on the next day, the one after accrues both
+        InterestManager::new(db_pool, settings("0.0365"))
+            .with_clock(clock_at(day(3)))
+            .run()
+            .await
+            .unwrap();
+
+        let accruals = sqlx::query!(
+            "SELECT accrual_date, amount FROM interest_accrual WHERE account_id = $1 AND accrual_date >= $2 ORDER BY accrual_date",
+            account.id(),
+            first_day
+        )
+        .fetch_all(db_pool)
+        .await
+        .unwrap();
+
+        assert_eq!(
+            accruals
+                .iter()
+                .map(|accrual| accrual.accrual_date)
+                .collect::<Vec<_>>(),
+            vec![day(0), day(1), day(2)]
+        );
+        assert!(accruals
+            .iter()
+            .all(|accrual| accrual.amount == "0.1".parse::<BigDecimal>().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_capitalize_month() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Savings, Currency::Brl, 1000).await;
+
+        // A month entirely after the deposit, so every day has the same balance
+        let month = first_day_of_month(Utc::now().date_naive())
+            .checked_add_months(Months::new(2))
+            .unwrap();
+        let next_month = month.checked_add_months(Months::new(1)).unwrap();
+        let days_in_month = (next_month - month).num_days() as u64;
+
+        let interest_manager =
+            InterestManager::new(db_pool, settings("0.0365")).with_clock(clock_at(next_month));
+
+        let result = InterestManager::new(db_pool, settings("0.0365"))
+            .with_clock(clock_at(next_month.pred_opt().unwrap()))
+            .capitalize(month)
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        for day in 0..days_in_month {
+            interest_manager
+                .accrue(month.checked_add_days(Days::new(day)).unwrap())
+                .await
+                .unwrap();
+        }
+
+        let capitalizations = interest_manager.capitalize(month).await.unwrap();
+
+        // 0.1 a day, the fraction is carried to the next month
+        let accrued = BigDecimal::from(days_in_month) / BigDecimal::from(10);
+        assert_eq!(capitalizations.len(), 1);
+        assert_eq!(capitalizations[0].accrued, accrued);
+        assert_eq!(capitalizations[0].amount, days_in_month as i64 / 10);
+        assert_eq!(
+            capitalizations[0].carry_over,
+            &accrued - BigDecimal::from(days_in_month / 10)
+        );
+
+        assert!(interest_manager.capitalize(month).await.unwrap().is_empty());
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 1000 + days_in_month as i64 / 10);
+
+        let trial_balance = TransactionManager::new(db_pool)
+            .trial_balance()
+            .await
+            .unwrap();
+
+        assert!(trial_balance.is_balanced());
+    }
+}
@@ -1,6 +1,15 @@
 pub mod account;
+pub mod clock;
 pub mod config;
+pub mod customer;
 pub mod error;
+pub mod fee;
+pub mod fx;
+pub mod hold;
+pub mod interest;
+pub mod money;
+pub mod schedule;
+pub mod storage;
 pub mod transaction;
 
 #[cfg(test)]
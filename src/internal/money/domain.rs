@@ -0,0 +1,373 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::internal::error::{BankError, ErrorKind};
+
+use super::error::MoneyError;
+
+/// ISO 4217 currencies an account can be opened in
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, sqlx::Type,
+)]
+#[serde(rename_all = "UPPERCASE")]
+#[sqlx(type_name = "varchar", rename_all = "UPPERCASE")]
+pub enum Currency {
+    Brl,
+    Usd,
+    Eur,
+    Gbp,
+    Chf,
+    /// No minor unit
+    Jpy,
+    /// Three decimals
+    Kwd,
+}
+
+/// Currency of the accounts opened without choosing one
+pub const DEFAULT_CURRENCY: Currency = Currency::Brl;
+
+impl Currency {
+    /// Every supported currency, in the order their system accounts are numbered
+    pub const ALL: [Currency; 7] = [
+        Currency::Brl,
+        Currency::Usd,
+        Currency::Eur,
+        Currency::Gbp,
+        Currency::Chf,
+        Currency::Jpy,
+        Currency::Kwd,
+    ];
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Brl => "BRL",
+            Currency::Usd => "USD",
+            Currency::Eur => "EUR",
+            Currency::Gbp => "GBP",
+            Currency::Chf => "CHF",
+            Currency::Jpy => "JPY",
+            Currency::Kwd => "KWD",
+        }
+    }
+
+    /// Decimals of the minor unit, `2` when 100 cents make a unit
+    pub fn exponent(&self) -> u32 {
+        match self {
+            Currency::Jpy => 0,
+            Currency::Kwd => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Currency {
+    type Err = MoneyError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "BRL" => Ok(Currency::Brl),
+            "USD" => Ok(Currency::Usd),
+            "EUR" => Ok(Currency::Eur),
+            "GBP" => Ok(Currency::Gbp),
+            "CHF" => Ok(Currency::Chf),
+            "JPY" => Ok(Currency::Jpy),
+            "KWD" => Ok(Currency::Kwd),
+            _ => Err(MoneyError::new(
+                format!("Unsupported currency [{}]", value),
+                ErrorKind::InvalidInput,
+            )),
+        }
+    }
+}
+
+/// An amount of a currency, counted in its minor unit so `12.34 BRL` is `1234`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(into = "MoneyRepr", try_from = "MoneyRepr")]
+pub struct Money {
+    pub amount_minor: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(amount_minor: i64, currency: Currency) -> Self {
+        Self {
+            amount_minor,
+            currency,
+        }
+    }
+
+    /// Parses a decimal string such as `"12.34"`, with at most as many decimals as the currency
+    /// has. Exponents, thousands separators and surrounding spaces are rejected.
+    pub fn parse(value: &str, currency: Currency) -> Result<Self, MoneyError> {
+        let invalid = || {
+            MoneyError::new(
+                format!("Invalid {} amount [{}]", currency, value),
+                ErrorKind::InvalidInput,
+            )
+        };
+
+        let (negative, digits) = match value.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, value),
+        };
+        let (units, decimals) = digits.split_once('.').unwrap_or((digits, ""));
+
+        if units.is_empty()
+            || !units.bytes().all(|b| b.is_ascii_digit())
+            || !decimals.bytes().all(|b| b.is_ascii_digit())
+            || (digits.contains('.') && decimals.is_empty())
+        {
+            return Err(invalid());
+        }
+
+        if decimals.len() > currency.exponent() as usize {
+            return Err(MoneyError::new(
+                format!(
+                    "{} amounts can't have more than {} decimals",
+                    currency,
+                    currency.exponent()
+                ),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        let padded = format!(
+            "{}{:0<width$}",
+            units,
+            decimals,
+            width = currency.exponent() as usize
+        );
+        let amount_minor: i64 = padded.parse().map_err(|_| invalid())?;
+
+        Ok(Self::new(
+            if negative {
+                -amount_minor
+            } else {
+                amount_minor
+            },
+            currency,
+        ))
+    }
+
+    /// The amount as a decimal string with exactly the decimals of the currency
+    pub fn to_decimal_string(&self) -> String {
+        let exponent = self.currency.exponent();
+        let sign = if self.amount_minor < 0 { "-" } else { "" };
+        let minor = self.amount_minor.unsigned_abs();
+
+        if exponent == 0 {
+            return format!("{}{}", sign, minor);
+        }
+
+        let scale = 10u64.pow(exponent);
+        format!(
+            "{}{}.{:0width$}",
+            sign,
+            minor / scale,
+            minor % scale,
+            width = exponent as usize
+        )
+    }
+
+    fn ensure_same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::new(
+                format!(
+                    "Can't combine {} and {} amounts without a conversion",
+                    self.currency, other.currency
+                ),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+
+        match self.amount_minor.checked_add(other.amount_minor) {
+            Some(amount_minor) => Ok(Money::new(amount_minor, self.currency)),
+            None => Err(MoneyError::new(
+                "Amount is too large".to_string(),
+                ErrorKind::InvalidInput,
+            )),
+        }
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.checked_add(&Money::new(-other.amount_minor, other.currency))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.to_decimal_string(), self.currency)
+    }
+}
+
+/// Minor units moved by a transaction, always greater than zero
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(try_from = "i64", into = "i64")]
+pub struct Amount(i64);
+
+impl Amount {
+    pub fn new(minor_units: i64) -> Result<Self, MoneyError> {
+        if minor_units <= 0 {
+            return Err(MoneyError::new(
+                "Amount must be greater than zero".to_string(),
+                ErrorKind::InvalidInput,
+            ));
+        }
+
+        Ok(Self(minor_units))
+    }
+
+    /// Parses a decimal string in `currency`, see `Money::parse`
+    pub fn parse(value: &str, currency: Currency) -> Result<Self, MoneyError> {
+        Self::new(Money::parse(value, currency)?.amount_minor)
+    }
+
+    pub fn minor_units(&self) -> i64 {
+        self.0
+    }
+
+    pub fn to_money(self, currency: Currency) -> Money {
+        Money::new(self.0, currency)
+    }
+}
+
+impl TryFrom<i64> for Amount {
+    type Error = String;
+
+    fn try_from(minor_units: i64) -> Result<Self, Self::Error> {
+        Amount::new(minor_units).map_err(|e| e.message().to_string())
+    }
+}
+
+impl From<Amount> for i64 {
+    fn from(amount: Amount) -> Self {
+        amount.0
+    }
+}
+
+/// JSON form of `Money`, the amount being a decimal string in the currency
+#[derive(Serialize, Deserialize)]
+struct MoneyRepr {
+    amount: String,
+    currency: Currency,
+}
+
+impl From<Money> for MoneyRepr {
+    fn from(money: Money) -> Self {
+        Self {
+            amount: money.to_decimal_string(),
+            currency: money.currency,
+        }
+    }
+}
+
+impl TryFrom<MoneyRepr> for Money {
+    type Error = String;
+
+    fn try_from(repr: MoneyRepr) -> Result<Self, Self::Error> {
+        Money::parse(&repr.amount, repr.currency).map_err(|e| e.message().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_follows_the_currency_exponent() {
+        assert_eq!(
+            Money::parse("12.34", Currency::Brl).unwrap(),
+            Money::new(1234, Currency::Brl)
+        );
+        assert_eq!(
+            Money::parse("12.3", Currency::Usd).unwrap(),
+            Money::new(1230, Currency::Usd)
+        );
+        assert_eq!(
+            Money::parse("12", Currency::Eur).unwrap(),
+            Money::new(1200, Currency::Eur)
+        );
+        assert_eq!(
+            Money::parse("1500", Currency::Jpy).unwrap(),
+            Money::new(1500, Currency::Jpy)
+        );
+        assert_eq!(
+            Money::parse("1.005", Currency::Kwd).unwrap(),
+            Money::new(1005, Currency::Kwd)
+        );
+        assert_eq!(
+            Money::parse("-0.05", Currency::Brl).unwrap(),
+            Money::new(-5, Currency::Brl)
+        );
+
+        for invalid in ["", "12.", ".5", "1e3", "1,000.00", " 12", "12.345", "--1"] {
+            assert!(Money::parse(invalid, Currency::Brl).is_err(), "{}", invalid);
+        }
+        assert!(Money::parse("1.5", Currency::Jpy).is_err());
+        assert!(Money::parse("99999999999999999999", Currency::Brl).is_err());
+    }
+
+    #[test]
+    fn test_decimal_string() {
+        assert_eq!(Money::new(1234, Currency::Brl).to_decimal_string(), "12.34");
+        assert_eq!(Money::new(5, Currency::Brl).to_decimal_string(), "0.05");
+        assert_eq!(Money::new(-5, Currency::Usd).to_decimal_string(), "-0.05");
+        assert_eq!(Money::new(1500, Currency::Jpy).to_decimal_string(), "1500");
+        assert_eq!(Money::new(1, Currency::Kwd).to_decimal_string(), "0.001");
+        assert_eq!(Money::new(1234, Currency::Brl).to_string(), "12.34 BRL");
+        assert_eq!(
+            Money::new(i64::MIN, Currency::Brl).to_decimal_string(),
+            "-92233720368547758.08"
+        );
+    }
+
+    #[test]
+    fn test_amount_is_positive() {
+        assert_eq!(Amount::new(1).unwrap().minor_units(), 1);
+        assert_eq!(
+            Amount::parse("92233720368547758.07", Currency::Brl)
+                .unwrap()
+                .minor_units(),
+            i64::MAX
+        );
+        assert!(Amount::new(0).is_err());
+        assert!(Amount::new(-5).is_err());
+        assert!(Amount::parse("0.00", Currency::Brl).is_err());
+        assert!(Amount::parse("-1.00", Currency::Brl).is_err());
+
+        assert_eq!(
+            serde_json::to_string(&Amount::new(1234).unwrap()).unwrap(),
+            "1234"
+        );
+        assert!(serde_json::from_str::<Amount>("0").is_err());
+        assert!(serde_json::from_str::<Amount>("-1").is_err());
+    }
+
+    #[test]
+    fn test_json_and_arithmetic() {
+        let money = Money::new(1234, Currency::Brl);
+        let json = serde_json::to_string(&money).unwrap();
+
+        assert_eq!(json, r#"{"amount":"12.34","currency":"BRL"}"#);
+        assert_eq!(serde_json::from_str::<Money>(&json).unwrap(), money);
+
+        assert_eq!(
+            money.checked_add(&Money::new(66, Currency::Brl)).unwrap(),
+            Money::new(1300, Currency::Brl)
+        );
+        assert!(money.checked_sub(&Money::new(1, Currency::Usd)).is_err());
+    }
+}
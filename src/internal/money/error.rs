@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct MoneyError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl MoneyError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for MoneyError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<MoneyError> for Box<dyn BankError> {
+    fn from(error: MoneyError) -> Self {
+        Box::new(error)
+    }
+}
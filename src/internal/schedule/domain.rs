@@ -0,0 +1,276 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::internal::{
+    error::ErrorKind,
+    money::domain::{Amount, Currency},
+};
+
+use super::error::ScheduleError;
+
+/// Movement booked at every occurrence of a schedule
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ScheduledTransactionType {
+    Deposit,
+    Withdraw,
+    Transfer,
+}
+
+impl ScheduledTransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduledTransactionType::Deposit => "deposit",
+            ScheduledTransactionType::Withdraw => "withdraw",
+            ScheduledTransactionType::Transfer => "transfer",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    Active,
+    /// Every occurrence was executed
+    Completed,
+    Cancelled,
+    /// A one-shot schedule whose only occurrence failed
+    Failed,
+}
+
+impl ScheduleStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScheduleStatus::Active => "active",
+            ScheduleStatus::Completed => "completed",
+            ScheduleStatus::Cancelled => "cancelled",
+            ScheduleStatus::Failed => "failed",
+        }
+    }
+}
+
+/// When a schedule runs again, counted from the previous occurrence so delays don't drift it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "frequency", rename_all = "snake_case")]
+pub enum Recurrence {
+    Once,
+    Daily {
+        every: u32,
+    },
+    Weekly {
+        every: u32,
+    },
+    /// On `day` of the month, or on its last day when the month is shorter
+    Monthly {
+        every: u32,
+        day: u32,
+    },
+}
+
+fn days_in_month(date: NaiveDate) -> u32 {
+    let first = date.with_day(1).unwrap_or(date);
+    match first.checked_add_months(Months::new(1)) {
+        Some(next) => (next - first).num_days() as u32,
+        None => 31,
+    }
+}
+
+impl Recurrence {
+    pub fn validate(&self, first_run_at: DateTime<Utc>) -> Result<(), ScheduleError> {
+        let invalid = |message: &str| {
+            Err(ScheduleError::new(
+                message.to_string(),
+                ErrorKind::InvalidInput,
+            ))
+        };
+
+        match self {
+            Recurrence::Once => {}
+            Recurrence::Daily { every }
+            | Recurrence::Weekly { every }
+            | Recurrence::Monthly { every, .. }
+                if *every == 0 =>
+            {
+                return invalid("A recurrence must repeat every 1 or more periods");
+            }
+            Recurrence::Monthly { day, .. } => {
+                if !(1..=31).contains(day) {
+                    return invalid("The day of the month must be between 1 and 31");
+                }
+
+                let date = first_run_at.date_naive();
+                if date.day() != (*day).min(days_in_month(date)) {
+                    return invalid(
+                        "The first run must be on the day of the month of the recurrence",
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Occurrence following the one at `run_at`, none for one-shot schedules
+    pub fn next_after(&self, run_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Recurrence::Once => None,
+            Recurrence::Daily { every } => run_at.checked_add_days(Days::new((*every).into())),
+            Recurrence::Weekly { every } => {
+                run_at.checked_add_days(Days::new(7 * u64::from(*every)))
+            }
+            Recurrence::Monthly { every, day } => {
+                let month = run_at
+                    .date_naive()
+                    .with_day(1)?
+                    .checked_add_months(Months::new(*every))?;
+                let date = month.with_day((*day).min(days_in_month(month)))?;
+
+                Some(date.and_time(run_at.time()).and_utc())
+            }
+        }
+    }
+}
+
+/// A transaction booked automatically, once or on a recurrence
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduledTransaction {
+    pub id: Uuid,
+    pub transaction_type: ScheduledTransactionType,
+    pub amount: i64,
+    pub origin_account_id: Option<Uuid>,
+    pub destination_account_id: Option<Uuid>,
+    pub recurrence: Recurrence,
+    /// Not set once the schedule is over
+    pub next_run_at: Option<DateTime<Utc>>,
+    /// Later than `next_run_at` while a failed attempt waits to be retried
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// Failed attempts of the next occurrence
+    pub attempts: i32,
+    /// No occurrence runs after it
+    pub ends_at: Option<DateTime<Utc>>,
+    pub status: ScheduleStatus,
+    pub created_at: DateTime<Utc>,
+    /// Currency of the accounts, `amount` is in it
+    pub currency: Currency,
+}
+
+/// Fields left empty are kept as they are
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ScheduleChanges {
+    pub amount: Option<Amount>,
+    pub recurrence: Option<Recurrence>,
+    /// Moves the next occurrence, the following ones are counted from it
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub ends_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "varchar", rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Succeeded,
+    Failed,
+}
+
+impl ExecutionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionStatus::Succeeded => "succeeded",
+            ExecutionStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One attempt at booking an occurrence
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ScheduledTransactionExecution {
+    pub id: Uuid,
+    pub scheduled_transaction_id: Uuid,
+    pub run_at: DateTime<Utc>,
+    /// Starts at 1, increases with every retry of the same occurrence
+    pub attempt: i32,
+    pub status: ExecutionStatus,
+    pub journal_entry_id: Option<Uuid>,
+    pub error: Option<String>,
+    pub executed_at: DateTime<Utc>,
+}
+
+/// How insufficient funds failures are retried, other failures are never retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts of an occurrence, including the first one
+    pub max_attempts: u32,
+    /// Wait between two attempts
+    pub backoff: chrono::Duration,
+}
+
+pub const DEFAULT_RETRY_POLICY: RetryPolicy = RetryPolicy {
+    max_attempts: 3,
+    backoff: chrono::Duration::hours(6),
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(9, 30, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn test_monthly_keeps_the_day_after_short_months() {
+        let recurrence = Recurrence::Monthly { every: 1, day: 31 };
+
+        assert!(recurrence.validate(at(2024, 1, 31)).is_ok());
+        assert_eq!(
+            recurrence.next_after(at(2024, 1, 31)),
+            Some(at(2024, 2, 29))
+        );
+        assert_eq!(
+            recurrence.next_after(at(2024, 2, 29)),
+            Some(at(2024, 3, 31))
+        );
+        assert_eq!(
+            recurrence.next_after(at(2024, 3, 31)),
+            Some(at(2024, 4, 30))
+        );
+
+        let quarterly = Recurrence::Monthly { every: 3, day: 5 };
+
+        assert_eq!(quarterly.next_after(at(2024, 11, 5)), Some(at(2025, 2, 5)));
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(Recurrence::Once.next_after(at(2024, 1, 1)), None);
+        assert_eq!(
+            Recurrence::Daily { every: 2 }.next_after(at(2024, 2, 28)),
+            Some(at(2024, 3, 1))
+        );
+        assert_eq!(
+            Recurrence::Weekly { every: 1 }.next_after(at(2024, 12, 30)),
+            Some(at(2025, 1, 6))
+        );
+    }
+
+    #[test]
+    fn test_invalid_recurrences() {
+        assert!(Recurrence::Daily { every: 0 }
+            .validate(at(2024, 1, 1))
+            .is_err());
+        assert!(Recurrence::Monthly { every: 1, day: 32 }
+            .validate(at(2024, 1, 1))
+            .is_err());
+        assert!(Recurrence::Monthly { every: 1, day: 5 }
+            .validate(at(2024, 1, 6))
+            .is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct ScheduleError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl ScheduleError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for ScheduleError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<ScheduleError> for Box<dyn BankError> {
+    fn from(error: ScheduleError) -> Self {
+        Box::new(error)
+    }
+}
@@ -0,0 +1,1000 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, Connection};
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, AccountType},
+    },
+    clock::{Clock, SystemClock},
+    error::{BankError, ErrorKind},
+    money::domain::{Amount, Currency},
+    transaction::{
+        domain::{Transaction, TransactionReceipt},
+        transaction::{self, TransactionManager},
+    },
+};
+
+use super::{
+    domain::{
+        ExecutionStatus, Recurrence, RetryPolicy, ScheduleChanges, ScheduleStatus,
+        ScheduledTransaction, ScheduledTransactionExecution, ScheduledTransactionType,
+        DEFAULT_RETRY_POLICY,
+    },
+    error::ScheduleError,
+};
+
+struct ScheduledTransactionRow {
+    id: Uuid,
+    transaction_type: ScheduledTransactionType,
+    amount: i64,
+    origin_account_id: Option<Uuid>,
+    destination_account_id: Option<Uuid>,
+    recurrence: Json<Recurrence>,
+    next_run_at: Option<DateTime<Utc>>,
+    next_attempt_at: Option<DateTime<Utc>>,
+    attempts: i32,
+    ends_at: Option<DateTime<Utc>>,
+    status: ScheduleStatus,
+    created_at: DateTime<Utc>,
+    currency: Currency,
+}
+
+impl From<ScheduledTransactionRow> for ScheduledTransaction {
+    fn from(row: ScheduledTransactionRow) -> Self {
+        Self {
+            id: row.id,
+            transaction_type: row.transaction_type,
+            amount: row.amount,
+            origin_account_id: row.origin_account_id,
+            destination_account_id: row.destination_account_id,
+            recurrence: row.recurrence.0,
+            next_run_at: row.next_run_at,
+            next_attempt_at: row.next_attempt_at,
+            attempts: row.attempts,
+            ends_at: row.ends_at,
+            status: row.status,
+            created_at: row.created_at,
+            currency: row.currency,
+        }
+    }
+}
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(ScheduleError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+fn bad_request(message: &str) -> Box<dyn BankError> {
+    Box::new(ScheduleError::new(
+        message.to_string(),
+        ErrorKind::InvalidInput,
+    ))
+}
+
+/// Account of a scheduled transaction, as it is now
+async fn scheduled_account(
+    id: Option<Uuid>,
+    conn: &mut sqlx::PgConnection,
+) -> Result<Account, Box<dyn BankError>> {
+    match id {
+        Some(id) => AccountManager::get_account_from_id(&id, conn).await,
+        None => Err(unexpected_error()),
+    }
+}
+
+/// Rebuilds the transaction booked at every occurrence
+async fn build_transaction(
+    scheduled: &ScheduledTransaction,
+    conn: &mut sqlx::PgConnection,
+) -> Result<Transaction, Box<dyn BankError>> {
+    let amount = match Amount::new(scheduled.amount) {
+        Ok(amount) => amount,
+        Err(_) => return Err(unexpected_error()),
+    };
+
+    let transaction = match scheduled.transaction_type {
+        ScheduledTransactionType::Deposit => Transaction::Deposit {
+            amount,
+            destination: scheduled_account(scheduled.destination_account_id, conn).await?,
+        },
+        ScheduledTransactionType::Withdraw => Transaction::Withdraw {
+            amount,
+            origin: scheduled_account(scheduled.origin_account_id, conn).await?,
+        },
+        ScheduledTransactionType::Transfer => Transaction::Transfer {
+            amount,
+            origin: scheduled_account(scheduled.origin_account_id, conn).await?,
+            destination: scheduled_account(scheduled.destination_account_id, conn).await?,
+        },
+    };
+
+    Ok(transaction)
+}
+
+/// Outcome of an attempt, failures are kept as messages so the scheduler future stays `Send`
+enum Attempt {
+    Booked(TransactionReceipt),
+    Failed {
+        error: String,
+        insufficient_funds: bool,
+    },
+}
+
+async fn book(
+    scheduled: &ScheduledTransaction,
+    conn: &mut sqlx::PgConnection,
+) -> Result<TransactionReceipt, Box<dyn BankError>> {
+    let transaction = build_transaction(scheduled, conn).await?;
+
+    TransactionManager::execute_transaction(transaction, conn).await
+}
+
+/// Books the transaction inside a savepoint, so a failure leaves nothing behind but the caller's
+/// database transaction stays usable to record it
+async fn execute_in_savepoint(
+    scheduled: &ScheduledTransaction,
+    conn: &mut sqlx::PgConnection,
+) -> Result<Attempt, Box<dyn BankError>> {
+    let mut savepoint = match conn.begin().await {
+        Ok(savepoint) => savepoint,
+        Err(e) => {
+            println!("Error creating savepoint: {}", e);
+            return Err(unexpected_error());
+        }
+    };
+
+    let attempt = match book(scheduled, &mut savepoint).await {
+        Ok(receipt) => Attempt::Booked(receipt),
+        Err(e) => Attempt::Failed {
+            error: e.message().to_string(),
+            insufficient_funds: transaction::is_insufficient_funds(e.as_ref()),
+        },
+    };
+
+    let released = match attempt {
+        Attempt::Booked(_) => savepoint.commit().await,
+        Attempt::Failed { .. } => savepoint.rollback().await,
+    };
+
+    if let Err(e) = released {
+        println!("Error releasing savepoint: {}", e);
+        return Err(unexpected_error());
+    }
+
+    Ok(attempt)
+}
+
+pub struct ScheduleManager<'a> {
+    db_pool: &'a sqlx::PgPool,
+    clock: Arc<dyn Clock>,
+    retry_policy: RetryPolicy,
+}
+
+impl<'a> ScheduleManager<'a> {
+    pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
+        Self {
+            db_pool,
+            clock: Arc::new(SystemClock),
+            retry_policy: DEFAULT_RETRY_POLICY,
+        }
+    }
+
+    /// Replaces the clock deciding which occurrences are due
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Sets how insufficient funds failures are retried
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    async fn begin(&self) -> Result<sqlx::Transaction<'a, sqlx::Postgres>, Box<dyn BankError>> {
+        match self.db_pool.begin().await {
+            Ok(tx) => Ok(tx),
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    async fn commit(tx: sqlx::Transaction<'a, sqlx::Postgres>) -> Result<(), Box<dyn BankError>> {
+        match tx.commit().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error committing transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Schedules `transaction` to be booked at `first_run_at`, and then on every occurrence of the
+    /// recurrence until `ends_at`
+    pub async fn create(
+        &self,
+        transaction: &Transaction,
+        first_run_at: DateTime<Utc>,
+        recurrence: Recurrence,
+        ends_at: Option<DateTime<Utc>>,
+    ) -> Result<ScheduledTransaction, Box<dyn BankError>> {
+        let (transaction_type, amount, origin, destination) = match transaction {
+            Transaction::Deposit {
+                amount,
+                destination,
+            } => (
+                ScheduledTransactionType::Deposit,
+                amount,
+                None,
+                Some(destination),
+            ),
+            Transaction::Withdraw { amount, origin } => (
+                ScheduledTransactionType::Withdraw,
+                amount,
+                Some(origin),
+                None,
+            ),
+            Transaction::Transfer {
+                amount,
+                origin,
+                destination,
+            } => (
+                ScheduledTransactionType::Transfer,
+                amount,
+                Some(origin),
+                Some(destination),
+            ),
+            Transaction::FxTransfer { .. } => {
+                return Err(bad_request(
+                    "Conversions can't be scheduled, their quotes expire before they run",
+                ))
+            }
+        };
+
+        if origin
+            .iter()
+            .chain(destination.iter())
+            .any(|account| *account.account_type() == AccountType::System)
+        {
+            return Err(bad_request("System accounts can't be used in transactions"));
+        }
+
+        if let (Some(origin), Some(destination)) = (origin, destination) {
+            TransactionManager::ensure_same_currency(origin, destination)?;
+        }
+
+        if first_run_at <= self.clock.now() {
+            return Err(bad_request("The first run must be in the future"));
+        }
+
+        if ends_at.is_some_and(|ends_at| ends_at < first_run_at) {
+            return Err(bad_request("The schedule can't end before its first run"));
+        }
+
+        recurrence.validate(first_run_at)?;
+
+        let scheduled = sqlx::query_as!(
+            ScheduledTransactionRow,
+            r#"INSERT INTO scheduled_transaction (id, transaction_type, amount, origin_account_id, destination_account_id, recurrence, next_run_at, next_attempt_at, ends_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $7, $8)
+            RETURNING id, transaction_type AS "transaction_type: ScheduledTransactionType", amount, origin_account_id, destination_account_id,
+            recurrence AS "recurrence: Json<Recurrence>", next_run_at, next_attempt_at, attempts, ends_at, status AS "status: ScheduleStatus", created_at AS "created_at!",
+            (SELECT account.currency FROM account WHERE account.id = COALESCE(scheduled_transaction.origin_account_id, scheduled_transaction.destination_account_id)) AS "currency!: Currency""#,
+            Uuid::now_v7(),
+            transaction_type.as_str(),
+            i64::from(*amount),
+            origin.map(|account| *account.id()),
+            destination.map(|account| *account.id()),
+            Json(&recurrence) as _,
+            first_run_at,
+            ends_at
+        )
+        .fetch_one(self.db_pool)
+        .await;
+
+        match scheduled {
+            Ok(scheduled) => Ok(scheduled.into()),
+            Err(e) => {
+                println!("Error creating scheduled transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    async fn get_scheduled(
+        id: &Uuid,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<ScheduledTransaction, Box<dyn BankError>> {
+        let scheduled = sqlx::query_as!(
+            ScheduledTransactionRow,
+            r#"SELECT id, transaction_type AS "transaction_type: ScheduledTransactionType", amount, origin_account_id, destination_account_id,
+            recurrence AS "recurrence: Json<Recurrence>", next_run_at, next_attempt_at, attempts, ends_at, status AS "status: ScheduleStatus", created_at AS "created_at!",
+            (SELECT account.currency FROM account WHERE account.id = COALESCE(scheduled_transaction.origin_account_id, scheduled_transaction.destination_account_id)) AS "currency!: Currency"
+            FROM scheduled_transaction WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(conn)
+        .await;
+
+        match scheduled {
+            Ok(Some(scheduled)) => Ok(scheduled.into()),
+            Ok(None) => Err(Box::new(ScheduleError::new(
+                format!("Scheduled transaction [{}] not found", id),
+                ErrorKind::NotFound,
+            ))),
+            Err(e) => {
+                println!("Error getting scheduled transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Locks an active schedule, waiting for the scheduler if it is executing it
+    async fn lock_active(
+        id: &Uuid,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<ScheduledTransaction, Box<dyn BankError>> {
+        let locked = sqlx::query!(
+            "SELECT id FROM scheduled_transaction WHERE id = $1 FOR UPDATE",
+            id
+        )
+        .fetch_optional(&mut *conn)
+        .await;
+
+        if let Err(e) = locked {
+            println!("Error locking scheduled transaction: {}", e);
+            return Err(unexpected_error());
+        }
+
+        let scheduled = ScheduleManager::get_scheduled(id, conn).await?;
+
+        if scheduled.status != ScheduleStatus::Active {
+            return Err(Box::new(ScheduleError::new(
+                format!(
+                    "Scheduled transaction [{}] is {}",
+                    id,
+                    scheduled.status.as_str()
+                ),
+                ErrorKind::Conflict,
+            )));
+        }
+
+        Ok(scheduled)
+    }
+
+    pub async fn get(&self, id: &Uuid) -> Result<ScheduledTransaction, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error getting database connection: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        ScheduleManager::get_scheduled(id, &mut conn).await
+    }
+
+    /// Schedules moving money out of or into the account, newest first
+    pub async fn list_for_account(
+        &self,
+        account: &Account,
+    ) -> Result<Vec<ScheduledTransaction>, Box<dyn BankError>> {
+        let scheduled = sqlx::query_as!(
+            ScheduledTransactionRow,
+            r#"SELECT id, transaction_type AS "transaction_type: ScheduledTransactionType", amount, origin_account_id, destination_account_id,
+            recurrence AS "recurrence: Json<Recurrence>", next_run_at, next_attempt_at, attempts, ends_at, status AS "status: ScheduleStatus", created_at AS "created_at!",
+            (SELECT account.currency FROM account WHERE account.id = COALESCE(scheduled_transaction.origin_account_id, scheduled_transaction.destination_account_id)) AS "currency!: Currency"
+            FROM scheduled_transaction WHERE origin_account_id = $1 OR destination_account_id = $1
+            ORDER BY id DESC"#,
+            account.id()
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        match scheduled {
+            Ok(scheduled) => Ok(scheduled
+                .into_iter()
+                .map(ScheduledTransaction::from)
+                .collect()),
+            Err(e) => {
+                println!("Error listing scheduled transactions: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Changes an active schedule, a pending retry is dropped when the next run moves
+    pub async fn update(
+        &self,
+        id: &Uuid,
+        changes: &ScheduleChanges,
+    ) -> Result<ScheduledTransaction, Box<dyn BankError>> {
+        let mut tx = self.begin().await?;
+
+        let scheduled = ScheduleManager::lock_active(id, &mut tx).await?;
+
+        if changes
+            .next_run_at
+            .is_some_and(|next_run_at| next_run_at <= self.clock.now())
+        {
+            return Err(bad_request("The next run must be in the future"));
+        }
+
+        let amount = changes.amount.map_or(scheduled.amount, i64::from);
+        let recurrence = changes.recurrence.as_ref().unwrap_or(&scheduled.recurrence);
+        let ends_at = changes.ends_at.or(scheduled.ends_at);
+        let (next_run_at, next_attempt_at, attempts) = match changes.next_run_at {
+            Some(next_run_at) => (next_run_at, next_run_at, 0),
+            None => match (scheduled.next_run_at, scheduled.next_attempt_at) {
+                (Some(next_run_at), Some(next_attempt_at)) => {
+                    (next_run_at, next_attempt_at, scheduled.attempts)
+                }
+                _ => return Err(unexpected_error()),
+            },
+        };
+
+        if ends_at.is_some_and(|ends_at| ends_at < next_run_at) {
+            return Err(bad_request("The schedule can't end before its next run"));
+        }
+
+        recurrence.validate(next_run_at)?;
+
+        let updated = sqlx::query_as!(
+            ScheduledTransactionRow,
+            r#"UPDATE scheduled_transaction
+            SET amount = $2, recurrence = $3, next_run_at = $4, next_attempt_at = $5, attempts = $6, ends_at = $7, updated_at = NOW()
+            WHERE id = $1
+            RETURNING id, transaction_type AS "transaction_type: ScheduledTransactionType", amount, origin_account_id, destination_account_id,
+            recurrence AS "recurrence: Json<Recurrence>", next_run_at, next_attempt_at, attempts, ends_at, status AS "status: ScheduleStatus", created_at AS "created_at!",
+            (SELECT account.currency FROM account WHERE account.id = COALESCE(scheduled_transaction.origin_account_id, scheduled_transaction.destination_account_id)) AS "currency!: Currency""#,
+            id,
+            amount,
+            Json(recurrence) as _,
+            next_run_at,
+            next_attempt_at,
+            attempts,
+            ends_at
+        )
+        .fetch_one(&mut *tx)
+        .await;
+
+        let updated = match updated {
+            Ok(updated) => updated.into(),
+            Err(e) => {
+                println!("Error updating scheduled transaction: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        ScheduleManager::commit(tx).await?;
+
+        Ok(updated)
+    }
+
+    /// Stops an active schedule, occurrences already booked are kept
+    pub async fn cancel(&self, id: &Uuid) -> Result<ScheduledTransaction, Box<dyn BankError>> {
+        let mut tx = self.begin().await?;
+
+        ScheduleManager::lock_active(id, &mut tx).await?;
+        ScheduleManager::finish(id, ScheduleStatus::Cancelled, &mut tx).await?;
+        let scheduled = ScheduleManager::get_scheduled(id, &mut tx).await?;
+
+        ScheduleManager::commit(tx).await?;
+
+        Ok(scheduled)
+    }
+
+    pub async fn list_executions(
+        &self,
+        id: &Uuid,
+    ) -> Result<Vec<ScheduledTransactionExecution>, Box<dyn BankError>> {
+        self.get(id).await?;
+
+        let executions = sqlx::query_as!(
+            ScheduledTransactionExecution,
+            r#"SELECT id, scheduled_transaction_id, run_at, attempt, status AS "status: ExecutionStatus", journal_entry_id, error, executed_at AS "executed_at!"
+            FROM scheduled_transaction_execution WHERE scheduled_transaction_id = $1
+            ORDER BY run_at, attempt"#,
+            id
+        )
+        .fetch_all(self.db_pool)
+        .await;
+
+        match executions {
+            Ok(executions) => Ok(executions),
+            Err(e) => {
+                println!("Error listing scheduled transaction executions: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    async fn finish(
+        id: &Uuid,
+        status: ScheduleStatus,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<(), Box<dyn BankError>> {
+        let result = sqlx::query!(
+            "UPDATE scheduled_transaction
+            SET status = $2, next_run_at = NULL, next_attempt_at = NULL, attempts = 0, updated_at = NOW()
+            WHERE id = $1",
+            id,
+            status.as_str()
+        )
+        .execute(conn)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error finishing scheduled transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    async fn record_execution(
+        scheduled: &ScheduledTransaction,
+        run_at: DateTime<Utc>,
+        attempt: i32,
+        outcome: &Attempt,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<(), Box<dyn BankError>> {
+        let (status, journal_entry_id, error) = match outcome {
+            Attempt::Booked(receipt) => (
+                ExecutionStatus::Succeeded,
+                Some(receipt.journal_entry_id),
+                None,
+            ),
+            Attempt::Failed { error, .. } => (ExecutionStatus::Failed, None, Some(error)),
+        };
+
+        let recorded = sqlx::query!(
+            "INSERT INTO scheduled_transaction_execution (id, scheduled_transaction_id, run_at, attempt, status, journal_entry_id, error)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            Uuid::now_v7(),
+            scheduled.id,
+            run_at,
+            attempt,
+            status.as_str(),
+            journal_entry_id,
+            error
+        )
+        .execute(conn)
+        .await;
+
+        match recorded {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error recording scheduled transaction execution: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Moves the schedule to the occurrence after `run_at`, or finishes it when there is none
+    async fn advance(
+        scheduled: &ScheduledTransaction,
+        run_at: DateTime<Utc>,
+        succeeded: bool,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<(), Box<dyn BankError>> {
+        let next_run_at = scheduled
+            .recurrence
+            .next_after(run_at)
+            .filter(|next_run_at| {
+                scheduled
+                    .ends_at
+                    .is_none_or(|ends_at| *next_run_at <= ends_at)
+            });
+
+        let Some(next_run_at) = next_run_at else {
+            let status = if succeeded || scheduled.recurrence != Recurrence::Once {
+                ScheduleStatus::Completed
+            } else {
+                ScheduleStatus::Failed
+            };
+            return ScheduleManager::finish(&scheduled.id, status, conn).await;
+        };
+
+        let result = sqlx::query!(
+            "UPDATE scheduled_transaction
+            SET next_run_at = $2, next_attempt_at = $2, attempts = 0, updated_at = NOW()
+            WHERE id = $1",
+            scheduled.id,
+            next_run_at
+        )
+        .execute(conn)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error advancing scheduled transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+
+    /// Executes the most overdue schedule not being executed by someone else, returns false when
+    /// nothing is due.
+    ///
+    /// The schedule stays locked until its execution, the record of it and the move to the next
+    /// occurrence are committed together, so an occurrence is booked once however many
+    /// schedulers run.
+    async fn run_next(&self) -> Result<bool, Box<dyn BankError>> {
+        let now = self.clock.now();
+        let mut tx = self.begin().await?;
+
+        let scheduled = sqlx::query_as!(
+            ScheduledTransactionRow,
+            r#"SELECT id, transaction_type AS "transaction_type: ScheduledTransactionType", amount, origin_account_id, destination_account_id,
+            recurrence AS "recurrence: Json<Recurrence>", next_run_at, next_attempt_at, attempts, ends_at, status AS "status: ScheduleStatus", created_at AS "created_at!",
+            (SELECT account.currency FROM account WHERE account.id = COALESCE(scheduled_transaction.origin_account_id, scheduled_transaction.destination_account_id)) AS "currency!: Currency"
+            FROM scheduled_transaction
+            WHERE status = $1 AND next_attempt_at <= $2
+            ORDER BY next_attempt_at
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED"#,
+            ScheduleStatus::Active.as_str(),
+            now
+        )
+        .fetch_optional(&mut *tx)
+        .await;
+
+        let scheduled: ScheduledTransaction = match scheduled {
+            Ok(Some(scheduled)) => scheduled.into(),
+            Ok(None) => {
+                ScheduleManager::commit(tx).await?;
+                return Ok(false);
+            }
+            Err(e) => {
+                println!("Error getting due scheduled transaction: {}", e);
+                return Err(unexpected_error());
+            }
+        };
+
+        let Some(run_at) = scheduled.next_run_at else {
+            return Err(unexpected_error());
+        };
+        let attempt = scheduled.attempts + 1;
+
+        let outcome = execute_in_savepoint(&scheduled, &mut tx).await?;
+
+        ScheduleManager::record_execution(&scheduled, run_at, attempt, &outcome, &mut tx).await?;
+
+        match outcome {
+            Attempt::Failed {
+                insufficient_funds: true,
+                ..
+            } if attempt < self.retry_policy.max_attempts as i32 => {
+                let retry = sqlx::query!(
+                    "UPDATE scheduled_transaction
+                    SET next_attempt_at = $2, attempts = $3, updated_at = NOW()
+                    WHERE id = $1",
+                    scheduled.id,
+                    now + self.retry_policy.backoff,
+                    attempt
+                )
+                .execute(&mut *tx)
+                .await;
+
+                if let Err(e) = retry {
+                    println!("Error retrying scheduled transaction: {}", e);
+                    return Err(unexpected_error());
+                }
+            }
+            Attempt::Booked(_) => {
+                ScheduleManager::advance(&scheduled, run_at, true, &mut tx).await?
+            }
+            Attempt::Failed { .. } => {
+                ScheduleManager::advance(&scheduled, run_at, false, &mut tx).await?
+            }
+        }
+
+        ScheduleManager::commit(tx).await?;
+
+        Ok(true)
+    }
+
+    /// Executes every due occurrence, returns how many executions were attempted
+    pub async fn run_due(&self) -> Result<u64, Box<dyn BankError>> {
+        let mut executed = 0;
+
+        while self.run_next().await? {
+            executed += 1;
+        }
+
+        Ok(executed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+    use crate::internal::{
+        clock::FixedClock,
+        test_util::{create_funded_account, get_conn_with_new_db},
+    };
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, 0, 0)
+            .unwrap()
+            .and_utc()
+    }
+
+    fn clock(now: DateTime<Utc>) -> Arc<dyn Clock> {
+        Arc::new(FixedClock(now))
+    }
+
+    async fn balance(db_pool: &sqlx::PgPool, account: &Account) -> i64 {
+        AccountManager::get_balance(account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor
+    }
+
+    #[tokio::test]
+    async fn test_standing_order_runs_every_occurrence_once() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let origin =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 1000).await;
+        let destination =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        let scheduled = ScheduleManager::new(db_pool)
+            .with_clock(clock(at(2100, 1, 1, 0)))
+            .create(
+                &Transaction::Transfer {
+                    amount: Amount::new(100).unwrap(),
+                    origin: origin.clone(),
+                    destination: destination.clone(),
+                },
+                at(2100, 1, 5, 9),
+                Recurrence::Monthly { every: 1, day: 5 },
+                Some(at(2100, 6, 1, 0)),
+            )
+            .await
+            .unwrap();
+
+        let nothing_due = ScheduleManager::new(db_pool)
+            .with_clock(clock(at(2100, 1, 5, 8)))
+            .run_due()
+            .await
+            .unwrap();
+
+        assert_eq!(nothing_due, 0);
+
+        // The scheduler was down for two months, every missed occurrence runs
+        let schedule_manager = ScheduleManager::new(db_pool).with_clock(clock(at(2100, 3, 5, 10)));
+
+        assert_eq!(schedule_manager.run_due().await.unwrap(), 3);
+        assert_eq!(schedule_manager.run_due().await.unwrap(), 0);
+
+        assert_eq!(balance(db_pool, &origin).await, 700);
+        assert_eq!(balance(db_pool, &destination).await, 300);
+
+        let executions = schedule_manager
+            .list_executions(&scheduled.id)
+            .await
+            .unwrap();
+        let run_dates: Vec<DateTime<Utc>> = executions.iter().map(|e| e.run_at).collect();
+
+        assert_eq!(
+            run_dates,
+            vec![at(2100, 1, 5, 9), at(2100, 2, 5, 9), at(2100, 3, 5, 9)]
+        );
+        assert!(executions
+            .iter()
+            .all(|e| e.status == ExecutionStatus::Succeeded && e.journal_entry_id.is_some()));
+
+        let scheduled = schedule_manager.get(&scheduled.id).await.unwrap();
+
+        assert_eq!(scheduled.next_run_at, Some(at(2100, 4, 5, 9)));
+
+        // The June occurrence is after the end of the schedule
+        let schedule_manager = ScheduleManager::new(db_pool).with_clock(clock(at(2100, 7, 1, 0)));
+
+        assert_eq!(schedule_manager.run_due().await.unwrap(), 2);
+
+        let scheduled = schedule_manager.get(&scheduled.id).await.unwrap();
+
+        assert_eq!(scheduled.status, ScheduleStatus::Completed);
+        assert_eq!(scheduled.next_run_at, None);
+        assert_eq!(balance(db_pool, &origin).await, 500);
+    }
+
+    #[tokio::test]
+    async fn test_insufficient_funds_are_retried() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 100).await;
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            backoff: chrono::Duration::hours(1),
+        };
+
+        let schedule_manager = ScheduleManager::new(db_pool).with_clock(clock(at(2100, 1, 1, 0)));
+        let mut created = Vec::new();
+        for amount in [150, 1000] {
+            let withdraw = Transaction::Withdraw {
+                amount: Amount::new(amount).unwrap(),
+                origin: account.clone(),
+            };
+            created.push(
+                schedule_manager
+                    .create(&withdraw, at(2100, 1, 2, 9), Recurrence::Once, None)
+                    .await
+                    .unwrap(),
+            );
+        }
+        let (retried, exhausted) = (&created[0], &created[1]);
+
+        let first_run = ScheduleManager::new(db_pool)
+            .with_clock(clock(at(2100, 1, 2, 9)))
+            .with_retry_policy(policy);
+
+        assert_eq!(first_run.run_due().await.unwrap(), 2);
+        assert_eq!(first_run.run_due().await.unwrap(), 0);
+
+        let scheduled = first_run.get(&retried.id).await.unwrap();
+
+        assert_eq!(scheduled.status, ScheduleStatus::Active);
+        assert_eq!(scheduled.attempts, 1);
+        assert_eq!(scheduled.next_attempt_at, Some(at(2100, 1, 2, 10)));
+
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let second_run = ScheduleManager::new(db_pool)
+            .with_clock(clock(at(2100, 1, 2, 10)))
+            .with_retry_policy(policy);
+
+        assert_eq!(second_run.run_due().await.unwrap(), 2);
+
+        let scheduled = second_run.get(&retried.id).await.unwrap();
+
+        assert_eq!(scheduled.status, ScheduleStatus::Completed);
+
+        let executions = second_run.list_executions(&retried.id).await.unwrap();
+
+        assert_eq!(executions.len(), 2);
+        assert_eq!(executions[0].status, ExecutionStatus::Failed);
+        assert!(executions[0]
+            .error
+            .as_ref()
+            .unwrap()
+            .starts_with("Insufficient funds"));
+        assert_eq!(executions[1].status, ExecutionStatus::Succeeded);
+        assert_eq!(executions[1].attempt, 2);
+
+        let scheduled = second_run.get(&exhausted.id).await.unwrap();
+
+        assert_eq!(scheduled.status, ScheduleStatus::Failed);
+        assert_eq!(balance(db_pool, &account).await, 50);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_schedulers_execute_once() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        for _ in 0..10 {
+            ScheduleManager::new(db_pool)
+                .with_clock(clock(at(2100, 1, 1, 0)))
+                .create(
+                    &Transaction::Deposit {
+                        amount: Amount::new(10).unwrap(),
+                        destination: account.clone(),
+                    },
+                    at(2100, 1, 1, 9),
+                    Recurrence::Daily { every: 1 },
+                    Some(at(2100, 1, 3, 9)),
+                )
+                .await
+                .unwrap();
+        }
+
+        let first = ScheduleManager::new(db_pool).with_clock(clock(at(2100, 1, 10, 0)));
+        let second = ScheduleManager::new(db_pool).with_clock(clock(at(2100, 1, 10, 0)));
+
+        let (first, second) = tokio::join!(first.run_due(), second.run_due());
+
+        assert_eq!(first.unwrap() + second.unwrap(), 30);
+        assert_eq!(balance(db_pool, &account).await, 300);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_cancel() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account = create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+        let schedule_manager = ScheduleManager::new(db_pool).with_clock(clock(at(2100, 1, 1, 0)));
+
+        let deposit = Transaction::Deposit {
+            amount: Amount::new(10).unwrap(),
+            destination: account.clone(),
+        };
+
+        let result = schedule_manager
+            .create(&deposit, at(2099, 12, 31, 0), Recurrence::Once, None)
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        let scheduled = schedule_manager
+            .create(
+                &deposit,
+                at(2100, 1, 2, 0),
+                Recurrence::Weekly { every: 1 },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let updated = schedule_manager
+            .update(
+                &scheduled.id,
+                &ScheduleChanges {
+                    amount: Some(Amount::new(25).unwrap()),
+                    next_run_at: Some(at(2100, 1, 3, 0)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(updated.amount, 25);
+        assert_eq!(updated.next_attempt_at, Some(at(2100, 1, 3, 0)));
+
+        let cancelled = schedule_manager.cancel(&scheduled.id).await.unwrap();
+
+        assert_eq!(cancelled.status, ScheduleStatus::Cancelled);
+
+        let result = schedule_manager
+            .update(&scheduled.id, &ScheduleChanges::default())
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::Conflict);
+
+        let due = ScheduleManager::new(db_pool)
+            .with_clock(clock(at(2100, 2, 1, 0)))
+            .run_due()
+            .await
+            .unwrap();
+
+        assert_eq!(due, 0);
+        assert_eq!(
+            schedule_manager
+                .list_for_account(&account)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,696 @@
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{
+            Account, AccountLimits, AccountStatus, AccountStatusChange, AccountType,
+            DEFAULT_BANK_CODE, DEFAULT_BRANCH_CODE,
+        },
+    },
+    customer::{
+        customer::CustomerManager,
+        domain::{Customer, NewCustomer},
+    },
+    error::BankError,
+    money::domain::{Currency, Money},
+    transaction::{
+        domain::{
+            Transaction, TransactionCursor, TransactionFilter, TransactionPage, TransactionReceipt,
+        },
+        transaction::TransactionManager,
+    },
+};
+
+use super::repository::Storage;
+
+/// Customers, accounts and their movements on any `Storage`, each call in its own unit of work.
+///
+/// Only covers what every storage keeps, holds, FX transfers, interest, schedules and
+/// idempotency keys go through the Postgres managers.
+pub struct Bank<'a> {
+    storage: &'a dyn Storage,
+    bank_code: u32,
+    branch_code: u16,
+}
+
+impl<'a> Bank<'a> {
+    pub fn new(storage: &'a dyn Storage) -> Self {
+        Self {
+            storage,
+            bank_code: DEFAULT_BANK_CODE,
+            branch_code: DEFAULT_BRANCH_CODE,
+        }
+    }
+
+    /// Sets the bank of the IBANs issued and accepted
+    pub fn with_bank_code(mut self, bank_code: u32) -> Self {
+        self.bank_code = bank_code;
+        self
+    }
+
+    /// Sets the branch of the accounts opened, up to `MAX_BRANCH_CODE`
+    pub fn with_branch_code(mut self, branch_code: u16) -> Self {
+        self.branch_code = branch_code;
+        self
+    }
+
+    pub fn iban(&self, account: &Account) -> String {
+        account.iban(self.bank_code)
+    }
+
+    pub async fn create_customer(
+        &self,
+        new_customer: &NewCustomer,
+    ) -> Result<Customer, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        let customer =
+            CustomerManager::register_customer(new_customer, unit.repositories()).await?;
+
+        unit.commit().await?;
+
+        Ok(customer)
+    }
+
+    /// Opens a new account held by the given customers, every amount booked on it is in `currency`
+    pub async fn create_account(
+        &self,
+        account_type: AccountType,
+        currency: Currency,
+        holders: &[Uuid],
+    ) -> Result<Account, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        let account = AccountManager::open_account(
+            self.branch_code,
+            account_type,
+            currency,
+            holders,
+            unit.repositories(),
+        )
+        .await?;
+
+        unit.commit().await?;
+
+        Ok(account)
+    }
+
+    pub async fn get_account_from_number(
+        &self,
+        number: i64,
+    ) -> Result<Account, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        AccountManager::find_account_by_number(number, unit.repositories()).await
+    }
+
+    pub async fn get_account_from_iban(&self, iban: &str) -> Result<Account, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        AccountManager::find_account_by_iban(iban, self.bank_code, unit.repositories()).await
+    }
+
+    pub async fn list_accounts(&self) -> Result<Vec<Account>, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        unit.repositories().list_accounts().await
+    }
+
+    pub async fn get_balance(&self, account: &Account) -> Result<Money, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        AccountManager::get_balance(account, unit.repositories()).await
+    }
+
+    pub async fn get_available_balance(
+        &self,
+        account: &Account,
+    ) -> Result<Money, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        AccountManager::get_available_balance(account, unit.repositories()).await
+    }
+
+    pub async fn update_limits(
+        &self,
+        account: &Account,
+        limits: &AccountLimits,
+    ) -> Result<AccountLimits, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        let limits = AccountManager::set_limits(account, limits, unit.repositories()).await?;
+
+        unit.commit().await?;
+
+        Ok(limits)
+    }
+
+    /// Blocks every outgoing movement, the account keeps receiving money
+    pub async fn freeze(
+        &self,
+        account: &Account,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        self.change_status(account, AccountStatus::Frozen, reason)
+            .await
+    }
+
+    pub async fn unfreeze(
+        &self,
+        account: &Account,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        self.change_status(account, AccountStatus::Active, reason)
+            .await
+    }
+
+    async fn change_status(
+        &self,
+        account: &Account,
+        status: AccountStatus,
+        reason: &str,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        let change =
+            AccountManager::change_account_status(account, status, reason, unit.repositories())
+                .await?;
+
+        unit.commit().await?;
+
+        Ok(change)
+    }
+
+    /// Books a deposit, withdraw or transfer, nothing is kept if any check fails
+    pub async fn create_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        let receipt =
+            TransactionManager::book_transaction(transaction, unit.repositories()).await?;
+
+        unit.commit().await?;
+
+        Ok(receipt)
+    }
+
+    /// See `TransactionManager::list_transactions`
+    pub async fn list_transactions(
+        &self,
+        account: &Account,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+    ) -> Result<TransactionPage, Box<dyn BankError>> {
+        let mut unit = self.storage.begin().await?;
+
+        TransactionManager::list_account_transactions(account, filter, cursor, unit.repositories())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::internal::{
+        account::domain::SAVINGS_MONTHLY_WITHDRAW_LIMIT,
+        error::ErrorKind,
+        fee::domain::{FeeEvent, FeeRule, FeeSchedule},
+        money::domain::Amount,
+        storage::{memory::MemoryStorage, postgres::PgStorage},
+        test_util::{get_conn_with_new_db, new_customer},
+        transaction::{
+            domain::TransactionType,
+            ledger::{LedgerAccount, Posting},
+        },
+    };
+
+    async fn open_account(bank: &Bank<'_>, account_type: AccountType, seed: u128) -> Account {
+        let customer = bank.create_customer(&new_customer(seed)).await.unwrap();
+
+        bank.create_account(account_type, Currency::Brl, &[customer.id])
+            .await
+            .unwrap()
+    }
+
+    async fn deposit(bank: &Bank<'_>, account: &Account, amount: i64) {
+        bank.create_transaction(Transaction::Deposit {
+            amount: Amount::new(amount).unwrap(),
+            destination: account.clone(),
+        })
+        .await
+        .unwrap();
+    }
+
+    async fn balance(bank: &Bank<'_>, account: &Account) -> i64 {
+        bank.get_balance(account).await.unwrap().amount_minor
+    }
+
+    #[tokio::test]
+    async fn test_memory_deposit_withdraw_and_transfer() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let origin = open_account(&bank, AccountType::Checking, 1).await;
+        let destination = open_account(&bank, AccountType::Checking, 2).await;
+
+        deposit(&bank, &origin, 100).await;
+
+        bank.create_transaction(Transaction::Withdraw {
+            amount: Amount::new(30).unwrap(),
+            origin: origin.clone(),
+        })
+        .await
+        .unwrap();
+
+        let receipt = bank
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(50).unwrap(),
+                origin: origin.clone(),
+                destination: destination.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert!(receipt.transfer_id.is_some());
+        assert_eq!(balance(&bank, &origin).await, 20);
+        assert_eq!(balance(&bank, &destination).await, 50);
+
+        let page = bank
+            .list_transactions(&origin, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+
+        let amounts: Vec<i64> = page.entries.iter().map(|entry| entry.amount).collect();
+        assert_eq!(amounts, vec![100, -30, -50]);
+        assert_eq!(page.entries[2].balance, 20.into());
+        assert_eq!(
+            page.entries[2].counterparty_account_number,
+            Some(destination.number)
+        );
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[tokio::test]
+    async fn test_memory_list_transactions_pages() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+        for amount in 1..=5 {
+            deposit(&bank, &account, amount).await;
+        }
+
+        let filter = TransactionFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut cursor = None;
+        let mut amounts = Vec::new();
+        loop {
+            let page = bank
+                .list_transactions(&account, &filter, cursor.as_ref())
+                .await
+                .unwrap();
+            amounts.extend(page.entries.iter().map(|entry| entry.amount));
+
+            match page.next_cursor {
+                Some(next) => cursor = Some(next.parse::<TransactionCursor>().unwrap()),
+                None => break,
+            }
+        }
+
+        assert_eq!(amounts, vec![1, 2, 3, 4, 5]);
+
+        let withdraws = bank
+            .list_transactions(
+                &account,
+                &TransactionFilter {
+                    types: Some(vec![TransactionType::Withdraw]),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(withdraws.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_rejected_transaction_keeps_nothing() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let origin = open_account(&bank, AccountType::Checking, 1).await;
+        let destination = open_account(&bank, AccountType::Checking, 2).await;
+        deposit(&bank, &origin, 100).await;
+
+        let result = bank
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(150).unwrap(),
+                origin: origin.clone(),
+                destination: destination.clone(),
+            })
+            .await;
+
+        let error = result.unwrap_err();
+        assert_eq!(
+            *error.kind(),
+            ErrorKind::InsufficientFunds {
+                available: Money::new(100, Currency::Brl),
+                requested: Money::new(150, Currency::Brl),
+            }
+        );
+        assert!(error.message().starts_with("Insufficient funds"));
+
+        assert_eq!(balance(&bank, &origin).await, 100);
+        assert_eq!(balance(&bank, &destination).await, 0);
+
+        let page = bank
+            .list_transactions(&destination, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+        assert!(page.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_memory_overdraft_limit() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        bank.update_limits(
+            &account,
+            &AccountLimits {
+                overdraft_limit: 50,
+            },
+        )
+        .await
+        .unwrap();
+
+        let withdraw = |amount| Transaction::Withdraw {
+            amount: Amount::new(amount).unwrap(),
+            origin: account.clone(),
+        };
+
+        assert!(bank.create_transaction(withdraw(151)).await.is_err());
+        assert!(bank.create_transaction(withdraw(150)).await.is_ok());
+        assert_eq!(balance(&bank, &account).await, -50);
+    }
+
+    #[tokio::test]
+    async fn test_memory_frozen_account_only_receives() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        bank.freeze(&account, "Suspicious activity").await.unwrap();
+
+        let result = bank
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(10).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::AccountFrozen);
+
+        deposit(&bank, &account, 10).await;
+
+        // Freezing twice is not a valid transition
+        assert!(bank.freeze(&account, "Again").await.is_err());
+
+        bank.unfreeze(&account, "Cleared").await.unwrap();
+
+        bank.create_transaction(Transaction::Withdraw {
+            amount: Amount::new(10).unwrap(),
+            origin: account.clone(),
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(balance(&bank, &account).await, 100);
+    }
+
+    #[tokio::test]
+    async fn test_memory_savings_withdraw_limit() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Savings, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        for _ in 0..SAVINGS_MONTHLY_WITHDRAW_LIMIT {
+            bank.create_transaction(Transaction::Withdraw {
+                amount: Amount::new(1).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+        }
+
+        let result = bank
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(1).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::Conflict);
+    }
+
+    #[tokio::test]
+    async fn test_memory_transfer_between_currencies_fails() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let origin = open_account(&bank, AccountType::Checking, 1).await;
+        let customer = bank.create_customer(&new_customer(2)).await.unwrap();
+        let destination = bank
+            .create_account(AccountType::Checking, Currency::Usd, &[customer.id])
+            .await
+            .unwrap();
+        deposit(&bank, &origin, 100).await;
+
+        let result = bank
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(10).unwrap(),
+                origin: origin.clone(),
+                destination: destination.clone(),
+            })
+            .await;
+
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_memory_concurrent_withdraws() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        let withdraw = || {
+            bank.create_transaction(Transaction::Withdraw {
+                amount: Amount::new(60).unwrap(),
+                origin: account.clone(),
+            })
+        };
+
+        let (first, second) = tokio::join!(withdraw(), withdraw());
+
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(balance(&bank, &account).await, 40);
+    }
+
+    #[tokio::test]
+    async fn test_memory_fees() {
+        let storage = MemoryStorage::new();
+        storage
+            .set_fee_schedule(FeeSchedule {
+                account_type: AccountType::Checking,
+                event: FeeEvent::Withdraw,
+                currency: Currency::Brl,
+                rule: FeeRule::Flat { amount: 5 },
+                min_fee: None,
+                max_fee: None,
+            })
+            .await;
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        let receipt = bank
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(50).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.fees.len(), 1);
+        assert_eq!(balance(&bank, &account).await, 45);
+    }
+
+    #[tokio::test]
+    async fn test_memory_duplicated_customer_and_unknown_holder() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        bank.create_customer(&new_customer(1)).await.unwrap();
+        let result = bank.create_customer(&new_customer(1)).await;
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::Conflict);
+
+        let result = bank
+            .create_account(AccountType::Checking, Currency::Brl, &[Uuid::now_v7()])
+            .await;
+        assert_eq!(*result.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_memory_account_lookups() {
+        let storage = MemoryStorage::new();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+
+        let by_number = bank.get_account_from_number(account.number).await.unwrap();
+        assert_eq!(by_number.id, account.id);
+
+        let by_iban = bank
+            .get_account_from_iban(&bank.iban(&account))
+            .await
+            .unwrap();
+        assert_eq!(by_iban.id, account.id);
+
+        let accounts = bank.list_accounts().await.unwrap();
+        assert_eq!(accounts.len(), 1);
+    }
+
+    /// Runs the same movements on the storage and returns the balances and history they left
+    async fn parity_scenario(storage: &dyn Storage) -> (i64, i64, Vec<(i64, TransactionType)>) {
+        let bank = Bank::new(storage);
+
+        let origin = open_account(&bank, AccountType::Savings, 1).await;
+        let destination = open_account(&bank, AccountType::Checking, 2).await;
+
+        deposit(&bank, &origin, 1000).await;
+        bank.create_transaction(Transaction::Transfer {
+            amount: Amount::new(300).unwrap(),
+            origin: origin.clone(),
+            destination: destination.clone(),
+        })
+        .await
+        .unwrap();
+        assert!(bank
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(5000).unwrap(),
+                origin: origin.clone(),
+            })
+            .await
+            .is_err());
+        bank.freeze(&destination, "Review").await.unwrap();
+        assert!(bank
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(10).unwrap(),
+                origin: destination.clone(),
+            })
+            .await
+            .is_err());
+
+        let history = bank
+            .list_transactions(&origin, &TransactionFilter::default(), None)
+            .await
+            .unwrap()
+            .entries
+            .iter()
+            .map(|entry| (entry.amount, entry.transaction_type))
+            .collect();
+
+        (
+            balance(&bank, &origin).await,
+            balance(&bank, &destination).await,
+            history,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_memory_matches_postgres() {
+        let database = get_conn_with_new_db().await;
+        let postgres = PgStorage::new(database.get_pool().clone());
+
+        let memory = parity_scenario(&MemoryStorage::new()).await;
+
+        assert_eq!(memory, parity_scenario(&postgres).await);
+        assert_eq!(memory.0, 700);
+    }
+
+    /// Books a lone posting, which no storage may commit, and returns the error of the commit
+    async fn commit_unbalanced_entry(storage: &dyn Storage) -> ErrorKind {
+        let bank = Bank::new(storage);
+        let account = open_account(&bank, AccountType::Checking, 1).await;
+
+        let mut unit = storage.begin().await.unwrap();
+        let journal_entry_id = Uuid::now_v7();
+        let repositories = unit.repositories();
+        repositories
+            .insert_journal_entry(&journal_entry_id, "unbalanced")
+            .await
+            .unwrap();
+        repositories
+            .insert_posting(
+                &journal_entry_id,
+                account.id(),
+                &Posting::new(
+                    LedgerAccount::Customer(*account.id()),
+                    50,
+                    TransactionType::Deposit,
+                ),
+            )
+            .await
+            .unwrap();
+        let error = unit.commit().await.unwrap_err();
+
+        let page = bank
+            .list_transactions(&account, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+        assert!(page.entries.is_empty());
+
+        *error.kind()
+    }
+
+    #[tokio::test]
+    async fn test_memory_rejects_unbalanced_entry_like_postgres() {
+        let database = get_conn_with_new_db().await;
+        let postgres = PgStorage::new(database.get_pool().clone());
+
+        assert_eq!(
+            commit_unbalanced_entry(&MemoryStorage::new()).await,
+            ErrorKind::Internal
+        );
+        assert_eq!(
+            commit_unbalanced_entry(&postgres).await,
+            ErrorKind::Internal
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[tokio::test]
+    async fn test_memory_matches_sqlite() {
+        let sqlite = crate::internal::test_util::new_sqlite_storage().await;
+
+        assert_eq!(
+            parity_scenario(&MemoryStorage::new()).await,
+            parity_scenario(&sqlite).await
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use crate::internal::error::{BankError, ErrorKind};
+
+#[derive(Debug)]
+pub struct StorageError {
+    message: String,
+    kind: ErrorKind,
+}
+
+impl StorageError {
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
+    }
+}
+
+impl BankError for StorageError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl From<StorageError> for Box<dyn BankError> {
+    fn from(error: StorageError) -> Self {
+        Box::new(error)
+    }
+}
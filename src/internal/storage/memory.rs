@@ -0,0 +1,624 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, SubsecRound, Utc};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use crate::internal::{
+    account::domain::{
+        Account, AccountLimits, AccountStatus, AccountStatusChange, AccountType, SystemAccount,
+    },
+    customer::domain::{Customer, DocumentNumber, NewCustomer},
+    error::{BankError, ErrorKind},
+    fee::domain::{FeeEvent, FeeSchedule},
+    money::domain::Currency,
+    transaction::{
+        domain::{TransactionCursor, TransactionEntry, TransactionFilter, TransactionType},
+        ledger::Posting,
+    },
+};
+
+use super::{
+    error::StorageError,
+    repository::{
+        AccountRepository, CustomerRepository, LedgerRepository, Repositories, Storage, UnitOfWork,
+    },
+};
+
+fn unexpected_error(message: &str) -> Box<dyn BankError> {
+    println!("{}", message);
+    Box::new(StorageError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+#[derive(Clone)]
+struct StoredAccount {
+    account: Account,
+    system_account: Option<SystemAccount>,
+    balance: i64,
+    limits: AccountLimits,
+}
+
+#[derive(Clone)]
+struct StoredPosting {
+    id: i32,
+    account_id: Uuid,
+    journal_entry_id: Uuid,
+    amount: i64,
+    transaction_type: TransactionType,
+    transfer_id: Option<Uuid>,
+    counterparty_account_id: Option<Uuid>,
+    created_at: DateTime<Utc>,
+}
+
+/// Everything the Postgres tables of accounts and the ledger keep
+#[derive(Clone)]
+struct MemoryState {
+    customers: BTreeMap<Uuid, Customer>,
+    accounts: BTreeMap<Uuid, StoredAccount>,
+    account_sequence: i64,
+    status_changes: Vec<AccountStatusChange>,
+    /// Description of every journal entry
+    journal_entries: BTreeMap<Uuid, String>,
+    postings: Vec<StoredPosting>,
+    /// Origin, destination and amount of every transfer
+    transfers: BTreeMap<Uuid, (Uuid, Uuid, i64)>,
+    fee_schedules: Vec<FeeSchedule>,
+    /// Start of the current unit of work, the `NOW()` of Postgres, so every row it books shares
+    /// the same timestamp
+    now: DateTime<Utc>,
+}
+
+impl MemoryState {
+    /// The system accounts seeded by the migrations, numbered the same way
+    fn new() -> Self {
+        let mut accounts = BTreeMap::new();
+        for (currency_position, currency) in (0_i64..).zip(Currency::ALL) {
+            for (position, system_account) in (1_i64..).zip(SystemAccount::ALL) {
+                let account = Account::new(
+                    -position - 100 * currency_position,
+                    AccountType::System,
+                    currency,
+                );
+                accounts.insert(
+                    *account.id(),
+                    StoredAccount {
+                        account,
+                        system_account: Some(system_account),
+                        balance: 0,
+                        limits: AccountLimits { overdraft_limit: 0 },
+                    },
+                );
+            }
+        }
+
+        Self {
+            customers: BTreeMap::new(),
+            accounts,
+            account_sequence: 0,
+            status_changes: Vec::new(),
+            journal_entries: BTreeMap::new(),
+            postings: Vec::new(),
+            transfers: BTreeMap::new(),
+            fee_schedules: Vec::new(),
+            now: Utc::now(),
+        }
+    }
+
+    fn account(&self, id: &Uuid) -> Result<&StoredAccount, Box<dyn BankError>> {
+        self.accounts
+            .get(id)
+            .ok_or_else(|| unexpected_error(&format!("Account [{}] not found in memory", id)))
+    }
+
+    /// Every journal entry that got postings after the first `booked` ones must sum to zero in
+    /// each currency, what Postgres checks with a deferred trigger when committing
+    fn ensure_entries_balanced(&self, booked: usize) -> Result<(), Box<dyn BankError>> {
+        let entries: BTreeSet<Uuid> = self
+            .postings
+            .iter()
+            .skip(booked)
+            .map(|posting| posting.journal_entry_id)
+            .collect();
+
+        let mut totals: BTreeMap<(Uuid, Currency), i64> = BTreeMap::new();
+        for posting in &self.postings {
+            if entries.contains(&posting.journal_entry_id) {
+                let currency = self.account(&posting.account_id)?.account.currency;
+                *totals
+                    .entry((posting.journal_entry_id, currency))
+                    .or_default() += posting.amount;
+            }
+        }
+
+        match totals.into_iter().find(|(_, total)| *total != 0) {
+            Some(((journal_entry_id, _), _)) => Err(unexpected_error(&format!(
+                "Journal entry [{}] does not sum to zero",
+                journal_entry_id
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn account_mut(&mut self, id: &Uuid) -> Result<&mut StoredAccount, Box<dyn BankError>> {
+        self.accounts
+            .get_mut(id)
+            .ok_or_else(|| unexpected_error(&format!("Account [{}] not found in memory", id)))
+    }
+}
+
+/// Accounts and ledger kept in memory, lost when the process exits. Meant for tests and demos,
+/// holds, FX quotes, interest, schedules and idempotency keys are only kept on Postgres.
+///
+/// A unit of work holds the whole storage until it ends, which serializes them the same way the
+/// Postgres row locks serialize the movements of an account, and works on a copy of the state
+/// that only replaces it on commit, once every journal entry it booked balances.
+#[derive(Clone)]
+pub struct MemoryStorage {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MemoryState::new())),
+        }
+    }
+
+    /// Replaces the fee the account type pays for the event in the schedule's currency
+    pub async fn set_fee_schedule(&self, schedule: FeeSchedule) {
+        let mut state = self.state.lock().await;
+
+        state.fee_schedules.retain(|existing| {
+            existing.account_type != schedule.account_type
+                || existing.event != schedule.event
+                || existing.currency != schedule.currency
+        });
+        state.fee_schedules.push(schedule);
+    }
+}
+
+impl Default for MemoryStorage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct MemoryUnitOfWork {
+    guard: OwnedMutexGuard<MemoryState>,
+    state: MemoryState,
+}
+
+#[async_trait]
+impl Storage for MemoryStorage {
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork + '_>, Box<dyn BankError>> {
+        let guard = self.state.clone().lock_owned().await;
+
+        let mut state = guard.clone();
+        // Postgres keeps microseconds, so cursors built from the timestamps round trip
+        state.now = Utc::now().trunc_subsecs(6);
+
+        Ok(Box::new(MemoryUnitOfWork { guard, state }))
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for MemoryUnitOfWork {
+    fn repositories(&mut self) -> &mut dyn Repositories {
+        &mut self.state
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Box<dyn BankError>> {
+        let MemoryUnitOfWork { mut guard, state } = *self;
+        state.ensure_entries_balanced(guard.postings.len())?;
+        *guard = state;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for MemoryState {
+    async fn insert_customer(
+        &mut self,
+        new_customer: &NewCustomer,
+        document_number: &DocumentNumber,
+    ) -> Result<Option<Customer>, Box<dyn BankError>> {
+        if self
+            .customers
+            .values()
+            .any(|customer| customer.document_number == document_number.as_str())
+        {
+            return Ok(None);
+        }
+
+        let customer = Customer {
+            id: Uuid::now_v7(),
+            name: new_customer.name.trim().to_string(),
+            document_number: document_number.as_str().to_string(),
+            email: new_customer.email.clone(),
+            date_of_birth: new_customer.date_of_birth,
+            created_at: self.now,
+        };
+        self.customers.insert(customer.id, customer.clone());
+
+        Ok(Some(customer))
+    }
+
+    async fn find_customer(&mut self, id: &Uuid) -> Result<Option<Customer>, Box<dyn BankError>> {
+        Ok(self.customers.get(id).cloned())
+    }
+
+    async fn find_missing_customers(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, Box<dyn BankError>> {
+        Ok(ids
+            .iter()
+            .filter(|id| !self.customers.contains_key(id))
+            .copied()
+            .collect())
+    }
+}
+
+#[async_trait]
+impl AccountRepository for MemoryState {
+    async fn next_account_sequence(&mut self) -> Result<i64, Box<dyn BankError>> {
+        self.account_sequence += 1;
+
+        Ok(self.account_sequence)
+    }
+
+    /// Nothing reads the holders of an account back, so they are not kept
+    async fn insert_account(
+        &mut self,
+        account: &Account,
+        _holders: &[Uuid],
+    ) -> Result<(), Box<dyn BankError>> {
+        if self
+            .accounts
+            .values()
+            .any(|stored| stored.account.number == account.number)
+        {
+            return Err(unexpected_error(&format!(
+                "Account number [{}] already in use",
+                account.number()
+            )));
+        }
+
+        self.accounts.insert(
+            *account.id(),
+            StoredAccount {
+                account: account.clone(),
+                system_account: None,
+                balance: 0,
+                limits: AccountLimits { overdraft_limit: 0 },
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn find_account_by_number(
+        &mut self,
+        number: i64,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        Ok(self
+            .accounts
+            .values()
+            .find(|stored| {
+                stored.account.number == number
+                    && stored.account.account_type != AccountType::System
+            })
+            .map(|stored| stored.account.clone()))
+    }
+
+    async fn find_account_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        Ok(self.accounts.get(id).map(|stored| stored.account.clone()))
+    }
+
+    async fn find_system_account(
+        &mut self,
+        system_account: SystemAccount,
+        currency: Currency,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        Ok(self
+            .accounts
+            .values()
+            .find(|stored| {
+                stored.system_account == Some(system_account) && stored.account.currency == currency
+            })
+            .map(|stored| stored.account.clone()))
+    }
+
+    async fn list_accounts(&mut self) -> Result<Vec<Account>, Box<dyn BankError>> {
+        let mut accounts: Vec<Account> = self
+            .accounts
+            .values()
+            .filter(|stored| stored.account.account_type != AccountType::System)
+            .map(|stored| stored.account.clone())
+            .collect();
+        accounts.sort_by_key(|account| account.number);
+
+        Ok(accounts)
+    }
+
+    /// Nothing to do, the unit of work already holds the whole storage
+    async fn lock_accounts(&mut self, ids: &[Uuid]) -> Result<(), Box<dyn BankError>> {
+        for id in ids {
+            self.account(id)?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_status(&mut self, id: &Uuid) -> Result<AccountStatus, Box<dyn BankError>> {
+        Ok(self.account(id)?.account.status)
+    }
+
+    async fn record_status_change(
+        &mut self,
+        account: &Account,
+        from_status: AccountStatus,
+        to_status: AccountStatus,
+        reason: &str,
+        sweep_journal_entry_id: Option<Uuid>,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        self.account_mut(account.id())?.account.status = to_status;
+
+        let change = AccountStatusChange {
+            id: Uuid::now_v7(),
+            account_id: *account.id(),
+            from_status,
+            to_status,
+            reason: reason.to_string(),
+            sweep_journal_entry_id,
+            created_at: self.now,
+        };
+        self.status_changes.push(change.clone());
+
+        Ok(change)
+    }
+
+    async fn get_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>> {
+        let stored = self.account(id)?;
+        if stored.system_account.is_none() {
+            return Ok(stored.balance);
+        }
+
+        Ok(self
+            .postings
+            .iter()
+            .filter(|posting| posting.account_id == *id)
+            .map(|posting| posting.amount)
+            .sum())
+    }
+
+    /// Holds are not kept in memory, so the whole balance is available
+    async fn get_available_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>> {
+        Ok(self.account(id)?.balance)
+    }
+
+    async fn apply_balance_change(
+        &mut self,
+        id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>> {
+        let stored = self.account_mut(id)?;
+
+        match stored.balance.checked_add(amount) {
+            Some(balance) => {
+                stored.balance = balance;
+                Ok(())
+            }
+            None => Err(unexpected_error(&format!(
+                "Balance of account [{}] out of range",
+                id
+            ))),
+        }
+    }
+
+    async fn get_limits(&mut self, id: &Uuid) -> Result<AccountLimits, Box<dyn BankError>> {
+        Ok(self.account(id)?.limits.clone())
+    }
+
+    async fn update_limits(
+        &mut self,
+        id: &Uuid,
+        limits: &AccountLimits,
+    ) -> Result<Option<AccountLimits>, Box<dyn BankError>> {
+        match self.accounts.get_mut(id) {
+            Some(stored) if stored.account.account_type != AccountType::System => {
+                stored.limits = limits.clone();
+                Ok(Some(limits.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for MemoryState {
+    async fn insert_journal_entry(
+        &mut self,
+        id: &Uuid,
+        description: &str,
+    ) -> Result<(), Box<dyn BankError>> {
+        if self.journal_entries.contains_key(id) {
+            return Err(unexpected_error(&format!(
+                "Journal entry [{}] already exists",
+                id
+            )));
+        }
+
+        self.journal_entries.insert(*id, description.to_string());
+
+        Ok(())
+    }
+
+    async fn insert_posting(
+        &mut self,
+        journal_entry_id: &Uuid,
+        account_id: &Uuid,
+        posting: &Posting,
+    ) -> Result<i32, Box<dyn BankError>> {
+        self.account(account_id)?;
+
+        if !self.journal_entries.contains_key(journal_entry_id) {
+            return Err(unexpected_error(&format!(
+                "Journal entry [{}] not found in memory",
+                journal_entry_id
+            )));
+        }
+
+        if let Some(transfer_id) = posting.transfer_id {
+            if !self.transfers.contains_key(&transfer_id) {
+                return Err(unexpected_error(&format!(
+                    "Transfer [{}] not found in memory",
+                    transfer_id
+                )));
+            }
+        }
+
+        let id = self.postings.last().map_or(1, |last| last.id + 1);
+        self.postings.push(StoredPosting {
+            id,
+            account_id: *account_id,
+            journal_entry_id: *journal_entry_id,
+            amount: posting.amount,
+            transaction_type: posting.transaction_type,
+            transfer_id: posting.transfer_id,
+            counterparty_account_id: posting.counterparty_account_id,
+            created_at: self.now,
+        });
+
+        Ok(id)
+    }
+
+    async fn insert_transfer(
+        &mut self,
+        id: &Uuid,
+        origin_account_id: &Uuid,
+        destination_account_id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>> {
+        self.account(origin_account_id)?;
+        self.account(destination_account_id)?;
+
+        self.transfers
+            .insert(*id, (*origin_account_id, *destination_account_id, amount));
+
+        Ok(())
+    }
+
+    async fn count_withdraws_since(
+        &mut self,
+        account_id: &Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn BankError>> {
+        Ok(self
+            .postings
+            .iter()
+            .filter(|posting| {
+                posting.account_id == *account_id
+                    && matches!(
+                        posting.transaction_type,
+                        TransactionType::Withdraw
+                            | TransactionType::TransferOut
+                            | TransactionType::HoldCapture
+                    )
+                    && posting.created_at >= since
+            })
+            .count() as i64)
+    }
+
+    async fn list_transactions(
+        &mut self,
+        account_id: &Uuid,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        limit: i64,
+    ) -> Result<Vec<TransactionEntry>, Box<dyn BankError>> {
+        let mut history: Vec<&StoredPosting> = self
+            .postings
+            .iter()
+            .filter(|posting| posting.account_id == *account_id)
+            .collect();
+        history.sort_by_key(|posting| (posting.created_at, posting.id));
+
+        let mut balance = BigDecimal::from(0);
+        let mut entries = Vec::new();
+        for posting in history {
+            balance += posting.amount;
+
+            let matches = filter.from.is_none_or(|from| posting.created_at >= from)
+                && filter.to.is_none_or(|to| posting.created_at < to)
+                && filter
+                    .types
+                    .as_ref()
+                    .is_none_or(|types| types.contains(&posting.transaction_type))
+                && filter
+                    .min_amount
+                    .is_none_or(|min_amount| posting.amount.abs() >= min_amount)
+                && filter
+                    .max_amount
+                    .is_none_or(|max_amount| posting.amount.abs() <= max_amount)
+                && cursor.is_none_or(|cursor| {
+                    (posting.created_at, posting.id) > (cursor.created_at, cursor.id)
+                });
+
+            if !matches {
+                continue;
+            }
+
+            if entries.len() as i64 >= limit {
+                break;
+            }
+
+            let counterparty_account_number = match posting.counterparty_account_id {
+                Some(id) => Some(self.account(&id)?.account.number),
+                None => None,
+            };
+
+            entries.push(TransactionEntry {
+                id: posting.id,
+                journal_entry_id: posting.journal_entry_id,
+                amount: posting.amount,
+                transaction_type: posting.transaction_type,
+                created_at: posting.created_at,
+                transfer_id: posting.transfer_id,
+                counterparty_account_number,
+                balance: balance.clone(),
+            });
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_fee_schedule(
+        &mut self,
+        account_type: AccountType,
+        event: FeeEvent,
+        currency: Currency,
+    ) -> Result<Option<FeeSchedule>, Box<dyn BankError>> {
+        Ok(self
+            .fee_schedules
+            .iter()
+            .find(|schedule| {
+                schedule.account_type == account_type
+                    && schedule.event == event
+                    && schedule.currency == currency
+            })
+            .cloned())
+    }
+}
@@ -0,0 +1,7 @@
+pub mod bank;
+pub mod error;
+pub mod memory;
+pub mod postgres;
+pub mod repository;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
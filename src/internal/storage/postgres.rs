@@ -0,0 +1,719 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        domain::{
+            Account, AccountLimits, AccountStatus, AccountStatusChange, AccountType, SystemAccount,
+        },
+        error::AccountError,
+    },
+    customer::{
+        domain::{Customer, DocumentNumber, NewCustomer},
+        error::CustomerError,
+    },
+    error::{BankError, ErrorKind},
+    fee::{
+        domain::{FeeEvent, FeeRule, FeeSchedule},
+        error::FeeError,
+        fee::FeeScheduleRow,
+    },
+    money::domain::Currency,
+    transaction::{
+        domain::{TransactionCursor, TransactionEntry, TransactionFilter, TransactionType},
+        error::TransactionError,
+        ledger::Posting,
+    },
+};
+
+use super::{
+    error::StorageError,
+    repository::{
+        AccountRepository, CustomerRepository, LedgerRepository, Repositories, Storage, UnitOfWork,
+    },
+};
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(StorageError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+/// Accounts and ledger on Postgres, the storage every feature is available on
+pub struct PgStorage {
+    db_pool: sqlx::PgPool,
+}
+
+impl PgStorage {
+    pub fn new(db_pool: sqlx::PgPool) -> Self {
+        Self { db_pool }
+    }
+}
+
+/// A database transaction, rolled back when dropped without being committed
+pub struct PgUnitOfWork {
+    tx: sqlx::Transaction<'static, sqlx::Postgres>,
+}
+
+#[async_trait]
+impl Storage for PgStorage {
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork + '_>, Box<dyn BankError>> {
+        match self.db_pool.begin().await {
+            Ok(tx) => Ok(Box::new(PgUnitOfWork { tx })),
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for PgUnitOfWork {
+    fn repositories(&mut self) -> &mut dyn Repositories {
+        &mut *self.tx
+    }
+
+    async fn commit(self: Box<Self>) -> Result<(), Box<dyn BankError>> {
+        match self.tx.commit().await {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error committing transaction: {}", e);
+                Err(unexpected_error())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for sqlx::PgConnection {
+    async fn insert_customer(
+        &mut self,
+        new_customer: &NewCustomer,
+        document_number: &DocumentNumber,
+    ) -> Result<Option<Customer>, Box<dyn BankError>> {
+        let customer = sqlx::query_as!(
+            Customer,
+            r#"INSERT INTO customer (id, name, document_number, email, date_of_birth) VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (document_number) DO NOTHING
+            RETURNING id, name, document_number, email, date_of_birth, created_at AS "created_at!""#,
+            Uuid::now_v7(),
+            new_customer.name.trim(),
+            document_number.as_str(),
+            new_customer.email,
+            new_customer.date_of_birth
+        )
+        .fetch_optional(self)
+        .await;
+
+        match customer {
+            Ok(customer) => Ok(customer),
+            Err(e) => {
+                println!("Error creating customer: {}", e);
+                Err(Box::new(CustomerError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )))
+            }
+        }
+    }
+
+    async fn find_customer(&mut self, id: &Uuid) -> Result<Option<Customer>, Box<dyn BankError>> {
+        let customer = sqlx::query_as!(
+            Customer,
+            r#"SELECT id, name, document_number, email, date_of_birth, created_at AS "created_at!"
+            FROM customer WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(self)
+        .await;
+
+        match customer {
+            Ok(customer) => Ok(customer),
+            Err(e) => {
+                println!("Error getting customer: {}", e);
+                Err(Box::new(CustomerError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )))
+            }
+        }
+    }
+
+    async fn find_missing_customers(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, Box<dyn BankError>> {
+        let existing = sqlx::query!("SELECT id FROM customer WHERE id = ANY($1)", ids)
+            .fetch_all(self)
+            .await;
+
+        match existing {
+            Ok(rows) => {
+                let existing: Vec<Uuid> = rows.into_iter().map(|row| row.id).collect();
+                Ok(ids
+                    .iter()
+                    .filter(|id| !existing.contains(id))
+                    .copied()
+                    .collect())
+            }
+            Err(e) => {
+                println!("Error getting account holders: {}", e);
+                Err(Box::new(AccountError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )))
+            }
+        }
+    }
+}
+
+fn account_error() -> Box<dyn BankError> {
+    Box::new(AccountError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+#[async_trait]
+impl AccountRepository for sqlx::PgConnection {
+    async fn next_account_sequence(&mut self) -> Result<i64, Box<dyn BankError>> {
+        // Sequences hand out each value once, whatever the other transactions do
+        let sequence =
+            sqlx::query_scalar!(r#"SELECT nextval('account_number_seq') AS "sequence!""#)
+                .fetch_one(self)
+                .await;
+
+        match sequence {
+            Ok(sequence) => Ok(sequence),
+            Err(e) => {
+                println!("Error allocating account number: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn insert_account(
+        &mut self,
+        account: &Account,
+        holders: &[Uuid],
+    ) -> Result<(), Box<dyn BankError>> {
+        let res = sqlx::query!(
+            "INSERT INTO account (id, number, type, currency) VALUES ($1, $2, $3, $4)",
+            account.id(),
+            account.number(),
+            account.account_type().as_str(),
+            account.currency().code()
+        )
+        .execute(&mut *self)
+        .await;
+
+        let res = match res {
+            Ok(_) => {
+                sqlx::query!(
+                    "INSERT INTO account_holder (account_id, customer_id) SELECT $1, customer_id FROM UNNEST($2::UUID[]) AS customer_id ON CONFLICT DO NOTHING",
+                    account.id(),
+                    holders
+                )
+                .execute(self)
+                .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error creating account: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn find_account_by_number(
+        &mut self,
+        number: i64,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let account = sqlx::query_as!(
+            Account,
+            r#"SELECT id, number, type AS "account_type: AccountType", status AS "status: AccountStatus", currency AS "currency: Currency" FROM account WHERE number = $1 AND type <> 'system'"#,
+            number
+        )
+        .fetch_optional(self)
+        .await;
+
+        match account {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                println!("Error getting account: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn find_account_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let account = sqlx::query_as!(
+            Account,
+            r#"SELECT id, number, type AS "account_type: AccountType", status AS "status: AccountStatus", currency AS "currency: Currency" FROM account WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(self)
+        .await;
+
+        match account {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                println!("Error getting account: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn find_system_account(
+        &mut self,
+        system_account: SystemAccount,
+        currency: Currency,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let account = sqlx::query_as!(
+            Account,
+            r#"SELECT id, number, type AS "account_type: AccountType", status AS "status: AccountStatus", currency AS "currency: Currency" FROM account WHERE system_code = $1 AND currency = $2"#,
+            system_account.code(),
+            currency.code()
+        )
+        .fetch_optional(self)
+        .await;
+
+        match account {
+            Ok(account) => Ok(account),
+            Err(e) => {
+                println!(
+                    "Error getting system account [{}] in {}: {}",
+                    system_account.code(),
+                    currency,
+                    e
+                );
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn list_accounts(&mut self) -> Result<Vec<Account>, Box<dyn BankError>> {
+        let accounts = sqlx::query_as!(
+            Account,
+            r#"SELECT id, number, type AS "account_type: AccountType", status AS "status: AccountStatus", currency AS "currency: Currency" FROM account WHERE type <> 'system' ORDER BY number"#
+        )
+        .fetch_all(self)
+        .await;
+
+        match accounts {
+            Ok(accounts) => Ok(accounts),
+            Err(e) => {
+                println!("Error listing accounts: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    /// Row locks held until the end of the database transaction, taken ordered by id
+    async fn lock_accounts(&mut self, ids: &[Uuid]) -> Result<(), Box<dyn BankError>> {
+        let locked = sqlx::query!(
+            "SELECT id FROM account WHERE id = ANY($1) ORDER BY id FOR UPDATE",
+            ids
+        )
+        .fetch_all(self)
+        .await;
+
+        match locked {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error locking accounts: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn get_status(&mut self, id: &Uuid) -> Result<AccountStatus, Box<dyn BankError>> {
+        let status = sqlx::query!(
+            r#"SELECT status AS "status: AccountStatus" FROM account WHERE id = $1"#,
+            id
+        )
+        .fetch_one(self)
+        .await;
+
+        match status {
+            Ok(row) => Ok(row.status),
+            Err(e) => {
+                println!("Error getting account status: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn record_status_change(
+        &mut self,
+        account: &Account,
+        from_status: AccountStatus,
+        to_status: AccountStatus,
+        reason: &str,
+        sweep_journal_entry_id: Option<Uuid>,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        let updated = sqlx::query!(
+            "UPDATE account SET status = $2 WHERE id = $1",
+            account.id(),
+            to_status.as_str()
+        )
+        .execute(&mut *self)
+        .await;
+
+        if let Err(e) = updated {
+            println!("Error updating account status: {}", e);
+            return Err(account_error());
+        }
+
+        let change = sqlx::query_as!(
+            AccountStatusChange,
+            r#"INSERT INTO account_status_change (id, account_id, from_status, to_status, reason, sweep_journal_entry_id)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, account_id, from_status AS "from_status: AccountStatus", to_status AS "to_status: AccountStatus",
+            reason, sweep_journal_entry_id, created_at AS "created_at!""#,
+            Uuid::now_v7(),
+            account.id(),
+            from_status.as_str(),
+            to_status.as_str(),
+            reason,
+            sweep_journal_entry_id
+        )
+        .fetch_one(self)
+        .await;
+
+        match change {
+            Ok(change) => Ok(change),
+            Err(e) => {
+                println!("Error recording account status change: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn get_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>> {
+        let balance = sqlx::query!(
+            r#"SELECT CASE WHEN account.type = 'system'
+                THEN COALESCE((SELECT SUM(amount) FROM transaction WHERE account_id = account.id), 0)::BIGINT
+                ELSE account.balance
+            END AS "balance!"
+            FROM account WHERE id = $1"#,
+            id
+        )
+        .fetch_one(self)
+        .await;
+
+        match balance {
+            Ok(balance) => Ok(balance.balance),
+            Err(_) => Err(Box::new(AccountError::new(
+                "Failed to get balance".to_string(),
+                ErrorKind::Internal,
+            ))),
+        }
+    }
+
+    async fn get_available_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>> {
+        let balance = sqlx::query!(
+            r#"SELECT (account.balance - COALESCE(
+                (SELECT SUM(amount) FROM hold WHERE account_id = account.id AND status = 'active' AND expires_at > NOW()),
+                0
+            ))::BIGINT AS "available!"
+            FROM account WHERE id = $1"#,
+            id
+        )
+        .fetch_one(self)
+        .await;
+
+        match balance {
+            Ok(balance) => Ok(balance.available),
+            Err(_) => Err(Box::new(AccountError::new(
+                "Failed to get balance".to_string(),
+                ErrorKind::Internal,
+            ))),
+        }
+    }
+
+    async fn apply_balance_change(
+        &mut self,
+        id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>> {
+        let result = sqlx::query!(
+            "UPDATE account SET balance = balance + $2 WHERE id = $1",
+            id,
+            amount
+        )
+        .execute(self)
+        .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error updating account balance: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn get_limits(&mut self, id: &Uuid) -> Result<AccountLimits, Box<dyn BankError>> {
+        let limits = sqlx::query_as!(
+            AccountLimits,
+            "SELECT overdraft_limit FROM account WHERE id = $1",
+            id
+        )
+        .fetch_one(self)
+        .await;
+
+        match limits {
+            Ok(limits) => Ok(limits),
+            Err(e) => {
+                println!("Error getting account limits: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+
+    async fn update_limits(
+        &mut self,
+        id: &Uuid,
+        limits: &AccountLimits,
+    ) -> Result<Option<AccountLimits>, Box<dyn BankError>> {
+        let limits = sqlx::query_as!(
+            AccountLimits,
+            "UPDATE account SET overdraft_limit = $2 WHERE id = $1 AND type <> 'system' RETURNING overdraft_limit",
+            id,
+            limits.overdraft_limit
+        )
+        .fetch_optional(self)
+        .await;
+
+        match limits {
+            Ok(limits) => Ok(limits),
+            Err(e) => {
+                println!("Error updating account limits: {}", e);
+                Err(account_error())
+            }
+        }
+    }
+}
+
+fn ledger_error() -> Box<dyn BankError> {
+    Box::new(TransactionError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+#[async_trait]
+impl LedgerRepository for sqlx::PgConnection {
+    async fn insert_journal_entry(
+        &mut self,
+        id: &Uuid,
+        description: &str,
+    ) -> Result<(), Box<dyn BankError>> {
+        let journal_entry = sqlx::query!(
+            "INSERT INTO journal_entry (id, description) VALUES ($1, $2)",
+            id,
+            description
+        )
+        .execute(self)
+        .await;
+
+        match journal_entry {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error creating journal entry: {}", e);
+                Err(ledger_error())
+            }
+        }
+    }
+
+    async fn insert_posting(
+        &mut self,
+        journal_entry_id: &Uuid,
+        account_id: &Uuid,
+        posting: &Posting,
+    ) -> Result<i32, Box<dyn BankError>> {
+        let row = sqlx::query!(
+            "INSERT INTO transaction (account_id, amount, type, transfer_id, counterparty_account_id, journal_entry_id)
+            VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            account_id,
+            posting.amount,
+            posting.transaction_type.as_str(),
+            posting.transfer_id,
+            posting.counterparty_account_id,
+            journal_entry_id
+        )
+        .fetch_one(self)
+        .await;
+
+        match row {
+            Ok(row) => Ok(row.id),
+            Err(e) => {
+                println!("Error creating posting: {}", e);
+                Err(ledger_error())
+            }
+        }
+    }
+
+    async fn insert_transfer(
+        &mut self,
+        id: &Uuid,
+        origin_account_id: &Uuid,
+        destination_account_id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>> {
+        let transfer = sqlx::query!(
+            "INSERT INTO transfer (id, origin_account_id, destination_account_id, amount) VALUES ($1, $2, $3, $4)",
+            id,
+            origin_account_id,
+            destination_account_id,
+            amount
+        )
+        .execute(self)
+        .await;
+
+        match transfer {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                println!("Error creating transfer: {}", e);
+                Err(Box::new(TransactionError::new(
+                    "Error on transaction".to_string(),
+                    ErrorKind::Internal,
+                )))
+            }
+        }
+    }
+
+    async fn count_withdraws_since(
+        &mut self,
+        account_id: &Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn BankError>> {
+        let withdraws = sqlx::query!(
+            r#"SELECT COUNT(*) AS "count!" FROM transaction
+            WHERE account_id = $1 AND type IN ('withdraw', 'transfer_out', 'hold_capture') AND created_at >= $2"#,
+            account_id,
+            since
+        )
+        .fetch_one(self)
+        .await;
+
+        match withdraws {
+            Ok(row) => Ok(row.count),
+            Err(e) => {
+                println!("Error counting withdraws: {}", e);
+                Err(ledger_error())
+            }
+        }
+    }
+
+    async fn list_transactions(
+        &mut self,
+        account_id: &Uuid,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        limit: i64,
+    ) -> Result<Vec<TransactionEntry>, Box<dyn BankError>> {
+        let types: Option<Vec<String>> = filter.types.as_ref().map(|types| {
+            types
+                .iter()
+                .map(|transaction_type| transaction_type.as_str().to_string())
+                .collect()
+        });
+
+        let rows = sqlx::query!(
+            r#"SELECT id AS "id!", journal_entry_id AS "journal_entry_id!", amount AS "amount!", type AS "type!", created_at AS "created_at!", transfer_id AS "transfer_id?", counterparty_account_number AS "counterparty_account_number?", balance AS "balance!"
+            FROM (
+                SELECT transaction.id, transaction.journal_entry_id, transaction.amount, transaction.type, transaction.created_at, transaction.transfer_id,
+                counterparty.number AS counterparty_account_number,
+                SUM(transaction.amount) OVER (ORDER BY transaction.created_at, transaction.id) AS balance
+                FROM transaction
+                LEFT JOIN account AS counterparty ON counterparty.id = transaction.counterparty_account_id
+                WHERE transaction.account_id = $1
+            ) AS history
+            WHERE ($2::TIMESTAMPTZ IS NULL OR created_at >= $2)
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at < $3)
+            AND ($4::TEXT[] IS NULL OR type = ANY($4))
+            AND ($5::BIGINT IS NULL OR ABS(amount) >= $5)
+            AND ($6::BIGINT IS NULL OR ABS(amount) <= $6)
+            AND ($7::TIMESTAMPTZ IS NULL OR (created_at, id) > ($7, $8))
+            ORDER BY created_at, id
+            LIMIT $9"#,
+            account_id,
+            filter.from,
+            filter.to,
+            types.as_deref(),
+            filter.min_amount,
+            filter.max_amount,
+            cursor.map(|cursor| cursor.created_at),
+            cursor.map(|cursor| cursor.id),
+            limit
+        )
+        .fetch_all(self)
+        .await;
+
+        let rows = match rows {
+            Ok(rows) => rows,
+            Err(e) => {
+                println!("Error listing transactions: {}", e);
+                return Err(ledger_error());
+            }
+        };
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            entries.push(TransactionEntry {
+                id: row.id,
+                journal_entry_id: row.journal_entry_id,
+                amount: row.amount,
+                transaction_type: row.r#type.parse::<TransactionType>()?,
+                created_at: row.created_at,
+                transfer_id: row.transfer_id,
+                counterparty_account_number: row.counterparty_account_number,
+                balance: row.balance,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_fee_schedule(
+        &mut self,
+        account_type: AccountType,
+        event: FeeEvent,
+        currency: Currency,
+    ) -> Result<Option<FeeSchedule>, Box<dyn BankError>> {
+        let schedule = sqlx::query_as!(
+            FeeScheduleRow,
+            r#"SELECT account_type AS "account_type: AccountType", event AS "event: FeeEvent", currency AS "currency: Currency", rule AS "rule: Json<FeeRule>", min_fee, max_fee
+            FROM fee_schedule WHERE account_type = $1 AND event = $2 AND currency = $3"#,
+            account_type.as_str(),
+            event.as_str(),
+            currency.code()
+        )
+        .fetch_optional(self)
+        .await;
+
+        match schedule {
+            Ok(schedule) => Ok(schedule.map(FeeSchedule::from)),
+            Err(e) => {
+                println!("Error getting fee schedule: {}", e);
+                Err(Box::new(FeeError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )))
+            }
+        }
+    }
+}
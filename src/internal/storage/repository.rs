@@ -0,0 +1,181 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::internal::{
+    account::domain::{
+        Account, AccountLimits, AccountStatus, AccountStatusChange, AccountType, SystemAccount,
+    },
+    customer::domain::{Customer, DocumentNumber, NewCustomer},
+    error::BankError,
+    fee::domain::{FeeEvent, FeeSchedule},
+    money::domain::Currency,
+    transaction::{
+        domain::{TransactionCursor, TransactionEntry, TransactionFilter},
+        ledger::Posting,
+    },
+};
+
+/// Customers that can hold accounts
+#[async_trait]
+pub trait CustomerRepository: Send {
+    /// None when a customer with the same document already exists
+    async fn insert_customer(
+        &mut self,
+        new_customer: &NewCustomer,
+        document_number: &DocumentNumber,
+    ) -> Result<Option<Customer>, Box<dyn BankError>>;
+
+    async fn find_customer(&mut self, id: &Uuid) -> Result<Option<Customer>, Box<dyn BankError>>;
+
+    /// Ids of `ids` that are not of any customer
+    async fn find_missing_customers(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, Box<dyn BankError>>;
+}
+
+/// Accounts, their stored balance, limits and status.
+///
+/// Methods taking the id of an account expect it to exist, a missing one is an unexpected error.
+#[async_trait]
+pub trait AccountRepository: Send {
+    /// Next value of the sequence account numbers are derived from
+    async fn next_account_sequence(&mut self) -> Result<i64, Box<dyn BankError>>;
+
+    /// Stores a new account, with a zero balance and no overdraft, held by `holders`
+    async fn insert_account(
+        &mut self,
+        account: &Account,
+        holders: &[Uuid],
+    ) -> Result<(), Box<dyn BankError>>;
+
+    /// Customer accounts only, system accounts are never found by number
+    async fn find_account_by_number(
+        &mut self,
+        number: i64,
+    ) -> Result<Option<Account>, Box<dyn BankError>>;
+
+    async fn find_account_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Account>, Box<dyn BankError>>;
+
+    async fn find_system_account(
+        &mut self,
+        system_account: SystemAccount,
+        currency: Currency,
+    ) -> Result<Option<Account>, Box<dyn BankError>>;
+
+    /// Customer accounts ordered by number
+    async fn list_accounts(&mut self) -> Result<Vec<Account>, Box<dyn BankError>>;
+
+    /// Keeps other units of work from changing the accounts until this one ends, always in the
+    /// same order so two of them locking the same accounts can't deadlock
+    async fn lock_accounts(&mut self, ids: &[Uuid]) -> Result<(), Box<dyn BankError>>;
+
+    async fn get_status(&mut self, id: &Uuid) -> Result<AccountStatus, Box<dyn BankError>>;
+
+    /// Sets the status and keeps the change in the account history
+    async fn record_status_change(
+        &mut self,
+        account: &Account,
+        from_status: AccountStatus,
+        to_status: AccountStatus,
+        reason: &str,
+        sweep_journal_entry_id: Option<Uuid>,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>>;
+
+    /// Balance in minor units, stored on customer accounts and summed from the postings of
+    /// system ones
+    async fn get_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>>;
+
+    /// Balance minus the funds reserved by active holds
+    async fn get_available_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>>;
+
+    async fn apply_balance_change(
+        &mut self,
+        id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>>;
+
+    async fn get_limits(&mut self, id: &Uuid) -> Result<AccountLimits, Box<dyn BankError>>;
+
+    /// None when there is no customer account with the id
+    async fn update_limits(
+        &mut self,
+        id: &Uuid,
+        limits: &AccountLimits,
+    ) -> Result<Option<AccountLimits>, Box<dyn BankError>>;
+}
+
+/// Journal entries, their postings and what decides the fees booked with them
+#[async_trait]
+pub trait LedgerRepository: Send {
+    async fn insert_journal_entry(
+        &mut self,
+        id: &Uuid,
+        description: &str,
+    ) -> Result<(), Box<dyn BankError>>;
+
+    /// Books the posting on `account_id`, returning the id of the posting
+    async fn insert_posting(
+        &mut self,
+        journal_entry_id: &Uuid,
+        account_id: &Uuid,
+        posting: &Posting,
+    ) -> Result<i32, Box<dyn BankError>>;
+
+    /// Links both legs of a transfer
+    async fn insert_transfer(
+        &mut self,
+        id: &Uuid,
+        origin_account_id: &Uuid,
+        destination_account_id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>>;
+
+    /// Withdraws, outgoing transfers and hold captures of the account booked from `since` on
+    async fn count_withdraws_since(
+        &mut self,
+        account_id: &Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn BankError>>;
+
+    /// Up to `limit` postings of the account ordered by `created_at` then `id`, after `cursor`,
+    /// with the running balance computed over the whole history
+    async fn list_transactions(
+        &mut self,
+        account_id: &Uuid,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        limit: i64,
+    ) -> Result<Vec<TransactionEntry>, Box<dyn BankError>>;
+
+    async fn get_fee_schedule(
+        &mut self,
+        account_type: AccountType,
+        event: FeeEvent,
+        currency: Currency,
+    ) -> Result<Option<FeeSchedule>, Box<dyn BankError>>;
+}
+
+/// Every repository, as seen from inside a unit of work
+pub trait Repositories: CustomerRepository + AccountRepository + LedgerRepository {}
+
+impl<T: CustomerRepository + AccountRepository + LedgerRepository + ?Sized> Repositories for T {}
+
+/// Changes made through the repositories are only kept if the unit of work is committed,
+/// dropping it discards them
+#[async_trait]
+pub trait UnitOfWork: Send {
+    fn repositories(&mut self) -> &mut dyn Repositories;
+
+    async fn commit(self: Box<Self>) -> Result<(), Box<dyn BankError>>;
+}
+
+/// Where accounts and the ledger are kept
+#[async_trait]
+pub trait Storage: Send + Sync {
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork + '_>, Box<dyn BankError>>;
+}
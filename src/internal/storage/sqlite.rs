@@ -0,0 +1,965 @@
+use std::{collections::BTreeSet, path::Path};
+
+use async_trait::async_trait;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, SubsecRound, Utc};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+    types::Json,
+    Row,
+};
+use uuid::Uuid;
+
+use crate::internal::{
+    account::domain::{
+        Account, AccountLimits, AccountStatus, AccountStatusChange, AccountType, SystemAccount,
+    },
+    config::database::ConfigurationError,
+    customer::domain::{Customer, DocumentNumber, NewCustomer},
+    error::{BankError, ErrorKind},
+    fee::domain::{FeeEvent, FeeRule, FeeSchedule},
+    money::domain::Currency,
+    transaction::{
+        domain::{TransactionCursor, TransactionEntry, TransactionFilter, TransactionType},
+        ledger::Posting,
+    },
+};
+
+use super::{
+    error::StorageError,
+    repository::{
+        AccountRepository, CustomerRepository, LedgerRepository, Repositories, Storage, UnitOfWork,
+    },
+};
+
+fn unexpected_error(context: &str, error: impl std::fmt::Display) -> Box<dyn BankError> {
+    println!("{}: {}", context, error);
+    Box::new(StorageError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+/// Timestamps are kept as microseconds since the epoch, the precision of Postgres
+fn from_micros(micros: i64) -> Result<DateTime<Utc>, sqlx::Error> {
+    DateTime::from_timestamp_micros(micros)
+        .ok_or_else(|| sqlx::Error::Decode(format!("Invalid timestamp [{}]", micros).into()))
+}
+
+fn account_from_row(row: &SqliteRow) -> Result<Account, sqlx::Error> {
+    Ok(Account {
+        id: row.try_get("id")?,
+        number: row.try_get("number")?,
+        account_type: row.try_get("type")?,
+        status: row.try_get("status")?,
+        currency: row.try_get("currency")?,
+    })
+}
+
+fn customer_from_row(row: &SqliteRow) -> Result<Customer, sqlx::Error> {
+    Ok(Customer {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        document_number: row.try_get("document_number")?,
+        email: row.try_get("email")?,
+        date_of_birth: row.try_get::<NaiveDate, _>("date_of_birth")?,
+        created_at: from_micros(row.try_get("created_at")?)?,
+    })
+}
+
+const ACCOUNT_COLUMNS: &str = "id, number, type, status, currency";
+
+fn account_query(condition: &str) -> String {
+    format!(
+        "SELECT {} FROM account WHERE {}",
+        ACCOUNT_COLUMNS, condition
+    )
+}
+
+/// Accounts and ledger on a single SQLite file, for running without a Postgres server. Like
+/// `MemoryStorage`, holds, FX quotes, interest, schedules and idempotency keys are only kept on
+/// Postgres.
+///
+/// SQLite has no row locks, so every unit of work takes the write lock of the database when it
+/// begins and keeps it until it ends. Units of work, on this process or any other using the
+/// file, run one after the other, waiting up to the busy timeout for their turn. Journal
+/// entries are checked to balance when the unit of work commits, as Postgres does with a
+/// deferred trigger.
+pub struct SqliteStorage {
+    db_pool: sqlx::SqlitePool,
+}
+
+impl SqliteStorage {
+    /// Opens the database at `path`, creating it and running the migrations of
+    /// `migrations/sqlite` when needed
+    pub async fn open(path: impl AsRef<Path>) -> Result<Self, ConfigurationError> {
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .foreign_keys(true)
+            // Readers don't wait for the writer, only writers wait for each other
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(std::time::Duration::from_secs(30));
+
+        let db_pool = SqlitePoolOptions::new()
+            .max_connections(5)
+            .connect_with(options)
+            .await
+            .map_err(|e| ConfigurationError::new(e.to_string()))?;
+
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&db_pool)
+            .await
+            .map_err(|e| ConfigurationError::new(e.to_string()))?;
+
+        Ok(Self { db_pool })
+    }
+
+    /// Replaces the fee the account type pays for the event in the schedule's currency
+    pub async fn set_fee_schedule(&self, schedule: &FeeSchedule) -> Result<(), Box<dyn BankError>> {
+        schedule.validate()?;
+
+        sqlx::query(
+            "INSERT INTO fee_schedule (account_type, event, currency, rule, min_fee, max_fee) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (account_type, event, currency) DO UPDATE SET rule = ?4, min_fee = ?5, max_fee = ?6",
+        )
+        .bind(schedule.account_type)
+        .bind(schedule.event)
+        .bind(schedule.currency.code())
+        .bind(Json(&schedule.rule))
+        .bind(schedule.min_fee)
+        .bind(schedule.max_fee)
+        .execute(&self.db_pool)
+        .await
+        .map_err(|e| unexpected_error("Error setting fee schedule", e))?;
+
+        Ok(())
+    }
+}
+
+/// A database transaction holding the write lock, rolled back when dropped without being
+/// committed
+pub struct SqliteUnitOfWork {
+    tx: sqlx::Transaction<'static, sqlx::Sqlite>,
+    /// Start of the unit of work, the `NOW()` of Postgres, so every row it books shares the same
+    /// timestamp
+    now: DateTime<Utc>,
+    /// Journal entries that got postings, checked to balance on commit
+    posted_entries: BTreeSet<Uuid>,
+}
+
+impl SqliteUnitOfWork {
+    /// SQLite has no deferred triggers, so the check Postgres runs at commit is done here, every
+    /// entry must sum to zero in each currency
+    async fn ensure_entries_balanced(&mut self) -> Result<(), Box<dyn BankError>> {
+        for journal_entry_id in &self.posted_entries {
+            let unbalanced = sqlx::query(
+                r#"SELECT account.currency FROM "transaction"
+                JOIN account ON account.id = "transaction".account_id
+                WHERE "transaction".journal_entry_id = ?1
+                GROUP BY account.currency
+                HAVING SUM("transaction".amount) <> 0"#,
+            )
+            .bind(journal_entry_id)
+            .fetch_optional(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error checking journal entry", e))?;
+
+            if unbalanced.is_some() {
+                return Err(unexpected_error(
+                    "Error committing transaction",
+                    format!("journal entry [{}] does not sum to zero", journal_entry_id),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn begin(&self) -> Result<Box<dyn UnitOfWork + '_>, Box<dyn BankError>> {
+        let mut tx = self
+            .db_pool
+            .begin()
+            .await
+            .map_err(|e| unexpected_error("Error starting database transaction", e))?;
+
+        let now = Utc::now().trunc_subsecs(6);
+
+        // The first statement writes, so the transaction waits for the write lock instead of
+        // reading a snapshot another writer could invalidate
+        sqlx::query("UPDATE unit_of_work_lock SET acquired_at = ?1 WHERE id = 1")
+            .bind(now.timestamp_micros())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| unexpected_error("Error locking database", e))?;
+
+        Ok(Box::new(SqliteUnitOfWork {
+            tx,
+            now,
+            posted_entries: BTreeSet::new(),
+        }))
+    }
+}
+
+#[async_trait]
+impl UnitOfWork for SqliteUnitOfWork {
+    fn repositories(&mut self) -> &mut dyn Repositories {
+        self
+    }
+
+    async fn commit(mut self: Box<Self>) -> Result<(), Box<dyn BankError>> {
+        self.ensure_entries_balanced().await?;
+
+        self.tx
+            .commit()
+            .await
+            .map_err(|e| unexpected_error("Error committing transaction", e))
+    }
+}
+
+#[async_trait]
+impl CustomerRepository for SqliteUnitOfWork {
+    async fn insert_customer(
+        &mut self,
+        new_customer: &NewCustomer,
+        document_number: &DocumentNumber,
+    ) -> Result<Option<Customer>, Box<dyn BankError>> {
+        let row = sqlx::query(
+            "INSERT INTO customer (id, name, document_number, email, date_of_birth, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT (document_number) DO NOTHING
+            RETURNING id, name, document_number, email, date_of_birth, created_at",
+        )
+        .bind(Uuid::now_v7())
+        .bind(new_customer.name.trim())
+        .bind(document_number.as_str())
+        .bind(&new_customer.email)
+        .bind(new_customer.date_of_birth)
+        .bind(self.now.timestamp_micros())
+        .fetch_optional(&mut *self.tx)
+        .await;
+
+        match row {
+            Ok(row) => row
+                .map(|row| customer_from_row(&row))
+                .transpose()
+                .map_err(|e| unexpected_error("Error reading customer", e)),
+            Err(e) => Err(unexpected_error("Error creating customer", e)),
+        }
+    }
+
+    async fn find_customer(&mut self, id: &Uuid) -> Result<Option<Customer>, Box<dyn BankError>> {
+        let row = sqlx::query(
+            "SELECT id, name, document_number, email, date_of_birth, created_at FROM customer WHERE id = ?1",
+        )
+        .bind(id)
+        .fetch_optional(&mut *self.tx)
+        .await;
+
+        match row {
+            Ok(row) => row
+                .map(|row| customer_from_row(&row))
+                .transpose()
+                .map_err(|e| unexpected_error("Error reading customer", e)),
+            Err(e) => Err(unexpected_error("Error getting customer", e)),
+        }
+    }
+
+    async fn find_missing_customers(
+        &mut self,
+        ids: &[Uuid],
+    ) -> Result<Vec<Uuid>, Box<dyn BankError>> {
+        let mut missing = Vec::new();
+        for id in ids {
+            let exists = sqlx::query("SELECT 1 FROM customer WHERE id = ?1")
+                .bind(id)
+                .fetch_optional(&mut *self.tx)
+                .await
+                .map_err(|e| unexpected_error("Error getting account holders", e))?;
+
+            if exists.is_none() {
+                missing.push(*id);
+            }
+        }
+
+        Ok(missing)
+    }
+}
+
+impl SqliteUnitOfWork {
+    async fn fetch_account<'q>(
+        &mut self,
+        query: sqlx::query::Query<'q, sqlx::Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let row = query.fetch_optional(&mut *self.tx).await;
+
+        match row {
+            Ok(row) => row
+                .map(|row| account_from_row(&row))
+                .transpose()
+                .map_err(|e| unexpected_error("Error reading account", e)),
+            Err(e) => Err(unexpected_error("Error getting account", e)),
+        }
+    }
+
+    /// A column of an account that must exist
+    async fn account_column<T>(&mut self, id: &Uuid, column: &str) -> Result<T, Box<dyn BankError>>
+    where
+        T: for<'r> sqlx::Decode<'r, sqlx::Sqlite> + sqlx::Type<sqlx::Sqlite> + Send + Unpin,
+    {
+        let sql = format!("SELECT {} FROM account WHERE id = ?1", column);
+        let row = sqlx::query(&sql)
+            .bind(id)
+            .fetch_one(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error(&format!("Error getting account {}", column), e))?;
+
+        row.try_get(0)
+            .map_err(|e| unexpected_error(&format!("Error reading account {}", column), e))
+    }
+}
+
+#[async_trait]
+impl AccountRepository for SqliteUnitOfWork {
+    async fn next_account_sequence(&mut self) -> Result<i64, Box<dyn BankError>> {
+        // The check on the column stops the sequence at its maximum, like `NO CYCLE`
+        let row = sqlx::query("UPDATE account_number_seq SET value = value + 1 RETURNING value")
+            .fetch_one(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error allocating account number", e))?;
+
+        row.try_get(0)
+            .map_err(|e| unexpected_error("Error reading account number", e))
+    }
+
+    async fn insert_account(
+        &mut self,
+        account: &Account,
+        holders: &[Uuid],
+    ) -> Result<(), Box<dyn BankError>> {
+        sqlx::query("INSERT INTO account (id, number, type, currency) VALUES (?1, ?2, ?3, ?4)")
+            .bind(account.id())
+            .bind(account.number())
+            .bind(account.account_type().as_str())
+            .bind(account.currency().code())
+            .execute(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error creating account", e))?;
+
+        for holder in holders {
+            sqlx::query(
+                "INSERT INTO account_holder (account_id, customer_id) VALUES (?1, ?2) ON CONFLICT DO NOTHING",
+            )
+            .bind(account.id())
+            .bind(holder)
+            .execute(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error creating account", e))?;
+        }
+
+        Ok(())
+    }
+
+    async fn find_account_by_number(
+        &mut self,
+        number: i64,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let sql = account_query("number = ?1 AND type <> 'system'");
+        self.fetch_account(sqlx::query(&sql).bind(number)).await
+    }
+
+    async fn find_account_by_id(
+        &mut self,
+        id: &Uuid,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let sql = account_query("id = ?1");
+        self.fetch_account(sqlx::query(&sql).bind(id)).await
+    }
+
+    async fn find_system_account(
+        &mut self,
+        system_account: SystemAccount,
+        currency: Currency,
+    ) -> Result<Option<Account>, Box<dyn BankError>> {
+        let sql = account_query("system_code = ?1 AND currency = ?2");
+        self.fetch_account(
+            sqlx::query(&sql)
+                .bind(system_account.code())
+                .bind(currency.code()),
+        )
+        .await
+    }
+
+    async fn list_accounts(&mut self) -> Result<Vec<Account>, Box<dyn BankError>> {
+        let sql = format!(
+            "SELECT {} FROM account WHERE type <> 'system' ORDER BY number",
+            ACCOUNT_COLUMNS
+        );
+        let rows = sqlx::query(&sql)
+            .fetch_all(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error listing accounts", e))?;
+
+        rows.iter()
+            .map(account_from_row)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| unexpected_error("Error reading account", e))
+    }
+
+    /// Nothing to do, the unit of work already holds the write lock of the whole database
+    async fn lock_accounts(&mut self, ids: &[Uuid]) -> Result<(), Box<dyn BankError>> {
+        for id in ids {
+            self.account_column::<i64>(id, "number").await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_status(&mut self, id: &Uuid) -> Result<AccountStatus, Box<dyn BankError>> {
+        self.account_column(id, "status").await
+    }
+
+    async fn record_status_change(
+        &mut self,
+        account: &Account,
+        from_status: AccountStatus,
+        to_status: AccountStatus,
+        reason: &str,
+        sweep_journal_entry_id: Option<Uuid>,
+    ) -> Result<AccountStatusChange, Box<dyn BankError>> {
+        sqlx::query("UPDATE account SET status = ?2 WHERE id = ?1")
+            .bind(account.id())
+            .bind(to_status.as_str())
+            .execute(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error updating account status", e))?;
+
+        let change = AccountStatusChange {
+            id: Uuid::now_v7(),
+            account_id: *account.id(),
+            from_status,
+            to_status,
+            reason: reason.to_string(),
+            sweep_journal_entry_id,
+            created_at: self.now,
+        };
+
+        sqlx::query(
+            "INSERT INTO account_status_change (id, account_id, from_status, to_status, reason, sweep_journal_entry_id, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(change.id)
+        .bind(change.account_id)
+        .bind(from_status.as_str())
+        .bind(to_status.as_str())
+        .bind(reason)
+        .bind(sweep_journal_entry_id)
+        .bind(self.now.timestamp_micros())
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error recording account status change", e))?;
+
+        Ok(change)
+    }
+
+    async fn get_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>> {
+        let row = sqlx::query(
+            r#"SELECT CASE WHEN account.type = 'system'
+                THEN (SELECT COALESCE(SUM(amount), 0) FROM "transaction" WHERE account_id = account.id)
+                ELSE account.balance
+            END
+            FROM account WHERE id = ?1"#,
+        )
+        .bind(id)
+        .fetch_one(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error getting account balance", e))?;
+
+        row.try_get(0)
+            .map_err(|e| unexpected_error("Error reading account balance", e))
+    }
+
+    /// Holds are not kept on SQLite, so the whole balance is available
+    async fn get_available_balance(&mut self, id: &Uuid) -> Result<i64, Box<dyn BankError>> {
+        self.account_column(id, "balance").await
+    }
+
+    async fn apply_balance_change(
+        &mut self,
+        id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>> {
+        let updated = sqlx::query("UPDATE account SET balance = balance + ?2 WHERE id = ?1")
+            .bind(id)
+            .bind(amount)
+            .execute(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error updating account balance", e))?;
+
+        if updated.rows_affected() != 1 {
+            return Err(unexpected_error(
+                "Error updating account balance",
+                format!("account [{}] not found", id),
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn get_limits(&mut self, id: &Uuid) -> Result<AccountLimits, Box<dyn BankError>> {
+        Ok(AccountLimits {
+            overdraft_limit: self.account_column(id, "overdraft_limit").await?,
+        })
+    }
+
+    async fn update_limits(
+        &mut self,
+        id: &Uuid,
+        limits: &AccountLimits,
+    ) -> Result<Option<AccountLimits>, Box<dyn BankError>> {
+        let row = sqlx::query(
+            "UPDATE account SET overdraft_limit = ?2 WHERE id = ?1 AND type <> 'system' RETURNING overdraft_limit",
+        )
+        .bind(id)
+        .bind(limits.overdraft_limit)
+        .fetch_optional(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error updating account limits", e))?;
+
+        match row {
+            Some(row) => Ok(Some(AccountLimits {
+                overdraft_limit: row
+                    .try_get(0)
+                    .map_err(|e| unexpected_error("Error reading account limits", e))?,
+            })),
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl LedgerRepository for SqliteUnitOfWork {
+    async fn insert_journal_entry(
+        &mut self,
+        id: &Uuid,
+        description: &str,
+    ) -> Result<(), Box<dyn BankError>> {
+        sqlx::query("INSERT INTO journal_entry (id, description, created_at) VALUES (?1, ?2, ?3)")
+            .bind(id)
+            .bind(description)
+            .bind(self.now.timestamp_micros())
+            .execute(&mut *self.tx)
+            .await
+            .map_err(|e| unexpected_error("Error creating journal entry", e))?;
+
+        Ok(())
+    }
+
+    async fn insert_posting(
+        &mut self,
+        journal_entry_id: &Uuid,
+        account_id: &Uuid,
+        posting: &Posting,
+    ) -> Result<i32, Box<dyn BankError>> {
+        let row = sqlx::query(
+            r#"INSERT INTO "transaction" (account_id, amount, type, transfer_id, counterparty_account_id, journal_entry_id, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) RETURNING id"#,
+        )
+        .bind(account_id)
+        .bind(posting.amount)
+        .bind(posting.transaction_type.as_str())
+        .bind(posting.transfer_id)
+        .bind(posting.counterparty_account_id)
+        .bind(journal_entry_id)
+        .bind(self.now.timestamp_micros())
+        .fetch_one(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error creating posting", e))?;
+        self.posted_entries.insert(*journal_entry_id);
+
+        row.try_get(0)
+            .map_err(|e| unexpected_error("Error reading posting", e))
+    }
+
+    async fn insert_transfer(
+        &mut self,
+        id: &Uuid,
+        origin_account_id: &Uuid,
+        destination_account_id: &Uuid,
+        amount: i64,
+    ) -> Result<(), Box<dyn BankError>> {
+        sqlx::query(
+            "INSERT INTO transfer (id, origin_account_id, destination_account_id, amount, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(id)
+        .bind(origin_account_id)
+        .bind(destination_account_id)
+        .bind(amount)
+        .bind(self.now.timestamp_micros())
+        .execute(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error creating transfer", e))?;
+
+        Ok(())
+    }
+
+    async fn count_withdraws_since(
+        &mut self,
+        account_id: &Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, Box<dyn BankError>> {
+        let row = sqlx::query(
+            r#"SELECT COUNT(*) FROM "transaction"
+            WHERE account_id = ?1 AND type IN ('withdraw', 'transfer_out', 'hold_capture') AND created_at >= ?2"#,
+        )
+        .bind(account_id)
+        .bind(since.timestamp_micros())
+        .fetch_one(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error counting withdraws", e))?;
+
+        row.try_get(0)
+            .map_err(|e| unexpected_error("Error counting withdraws", e))
+    }
+
+    async fn list_transactions(
+        &mut self,
+        account_id: &Uuid,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        limit: i64,
+    ) -> Result<Vec<TransactionEntry>, Box<dyn BankError>> {
+        // Bound as a JSON array, SQLite has no array parameters
+        let types = filter.types.as_ref().map(|types| {
+            serde_json::Value::from(
+                types
+                    .iter()
+                    .map(|transaction_type| transaction_type.as_str())
+                    .collect::<Vec<_>>(),
+            )
+            .to_string()
+        });
+
+        let rows = sqlx::query(
+            r#"SELECT id, journal_entry_id, amount, type, created_at, transfer_id, counterparty_account_number, balance
+            FROM (
+                SELECT "transaction".id, "transaction".journal_entry_id, "transaction".amount, "transaction".type, "transaction".created_at, "transaction".transfer_id,
+                counterparty.number AS counterparty_account_number,
+                SUM("transaction".amount) OVER (ORDER BY "transaction".created_at, "transaction".id) AS balance
+                FROM "transaction"
+                LEFT JOIN account AS counterparty ON counterparty.id = "transaction".counterparty_account_id
+                WHERE "transaction".account_id = ?1
+            ) AS history
+            WHERE (?2 IS NULL OR created_at >= ?2)
+            AND (?3 IS NULL OR created_at < ?3)
+            AND (?4 IS NULL OR type IN (SELECT value FROM json_each(?4)))
+            AND (?5 IS NULL OR ABS(amount) >= ?5)
+            AND (?6 IS NULL OR ABS(amount) <= ?6)
+            AND (?7 IS NULL OR (created_at, id) > (?7, ?8))
+            ORDER BY created_at, id
+            LIMIT ?9"#,
+        )
+        .bind(account_id)
+        .bind(filter.from.map(|from| from.timestamp_micros()))
+        .bind(filter.to.map(|to| to.timestamp_micros()))
+        .bind(types)
+        .bind(filter.min_amount)
+        .bind(filter.max_amount)
+        .bind(cursor.map(|cursor| cursor.created_at.timestamp_micros()))
+        .bind(cursor.map(|cursor| cursor.id))
+        .bind(limit)
+        .fetch_all(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error listing transactions", e))?;
+
+        let mut entries = Vec::with_capacity(rows.len());
+        for row in rows {
+            let entry = (|| -> Result<TransactionEntry, Box<dyn BankError>> {
+                let read = |e| unexpected_error("Error reading transaction", e);
+
+                Ok(TransactionEntry {
+                    id: row.try_get("id").map_err(read)?,
+                    journal_entry_id: row.try_get("journal_entry_id").map_err(read)?,
+                    amount: row.try_get("amount").map_err(read)?,
+                    transaction_type: row
+                        .try_get::<String, _>("type")
+                        .map_err(read)?
+                        .parse::<TransactionType>()?,
+                    created_at: from_micros(row.try_get("created_at").map_err(read)?)
+                        .map_err(read)?,
+                    transfer_id: row.try_get("transfer_id").map_err(read)?,
+                    counterparty_account_number: row
+                        .try_get("counterparty_account_number")
+                        .map_err(read)?,
+                    balance: BigDecimal::from(row.try_get::<i64, _>("balance").map_err(read)?),
+                })
+            })()?;
+
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    async fn get_fee_schedule(
+        &mut self,
+        account_type: AccountType,
+        event: FeeEvent,
+        currency: Currency,
+    ) -> Result<Option<FeeSchedule>, Box<dyn BankError>> {
+        let row = sqlx::query(
+            "SELECT account_type, event, currency, rule, min_fee, max_fee FROM fee_schedule
+            WHERE account_type = ?1 AND event = ?2 AND currency = ?3",
+        )
+        .bind(account_type.as_str())
+        .bind(event.as_str())
+        .bind(currency.code())
+        .fetch_optional(&mut *self.tx)
+        .await
+        .map_err(|e| unexpected_error("Error getting fee schedule", e))?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let read = |e| unexpected_error("Error reading fee schedule", e);
+        Ok(Some(FeeSchedule {
+            account_type: row.try_get("account_type").map_err(read)?,
+            event: row.try_get("event").map_err(read)?,
+            currency: row.try_get("currency").map_err(read)?,
+            rule: row.try_get::<Json<FeeRule>, _>("rule").map_err(read)?.0,
+            min_fee: row.try_get("min_fee").map_err(read)?,
+            max_fee: row.try_get("max_fee").map_err(read)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::internal::{
+        money::domain::Amount,
+        storage::bank::Bank,
+        test_util::{new_customer, new_sqlite_storage},
+        transaction::{domain::Transaction, ledger::LedgerAccount},
+    };
+
+    async fn open_account(bank: &Bank<'_>, seed: u128) -> Account {
+        let customer = bank.create_customer(&new_customer(seed)).await.unwrap();
+
+        bank.create_account(AccountType::Checking, Currency::Brl, &[customer.id])
+            .await
+            .unwrap()
+    }
+
+    async fn deposit(bank: &Bank<'_>, account: &Account, amount: i64) {
+        bank.create_transaction(Transaction::Deposit {
+            amount: Amount::new(amount).unwrap(),
+            destination: account.clone(),
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_withdraws() {
+        let storage = Arc::new(new_sqlite_storage().await);
+        let bank = Bank::new(&*storage);
+
+        let account = open_account(&bank, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        let mut handles = Vec::new();
+        for _ in 0..10 {
+            let storage = storage.clone();
+            let account = account.clone();
+            handles.push(tokio::spawn(async move {
+                Bank::new(&*storage)
+                    .create_transaction(Transaction::Withdraw {
+                        amount: Amount::new(30).unwrap(),
+                        origin: account,
+                    })
+                    .await
+                    .is_ok()
+            }));
+        }
+
+        let mut succeeded = 0;
+        for handle in handles {
+            if handle.await.unwrap() {
+                succeeded += 1;
+            }
+        }
+
+        assert_eq!(succeeded, 3);
+        assert_eq!(bank.get_balance(&account).await.unwrap().amount_minor, 10);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_unit_of_work_rolls_back() {
+        let storage = new_sqlite_storage().await;
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, 1).await;
+
+        let mut unit = storage.begin().await.unwrap();
+        unit.repositories()
+            .apply_balance_change(account.id(), 50)
+            .await
+            .unwrap();
+        drop(unit);
+
+        assert_eq!(bank.get_balance(&account).await.unwrap().amount_minor, 0);
+    }
+
+    #[tokio::test]
+    async fn test_unbalanced_journal_entry_is_not_committed() {
+        let storage = new_sqlite_storage().await;
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, 1).await;
+
+        let mut unit = storage.begin().await.unwrap();
+        let journal_entry_id = Uuid::now_v7();
+        let repositories = unit.repositories();
+        repositories
+            .insert_journal_entry(&journal_entry_id, "unbalanced")
+            .await
+            .unwrap();
+        repositories
+            .insert_posting(
+                &journal_entry_id,
+                account.id(),
+                &Posting::new(
+                    LedgerAccount::Customer(*account.id()),
+                    50,
+                    TransactionType::Deposit,
+                ),
+            )
+            .await
+            .unwrap();
+
+        let result = unit.commit().await;
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::Internal);
+
+        let history = bank
+            .list_transactions(&account, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+        assert!(history.entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_reopen_keeps_ledger() {
+        let path = std::env::temp_dir().join(format!("bank_{}.db", Uuid::now_v7()));
+
+        let account = {
+            let storage = SqliteStorage::open(&path).await.unwrap();
+            let bank = Bank::new(&storage);
+            let account = open_account(&bank, 1).await;
+            deposit(&bank, &account, 100).await;
+            account
+        };
+
+        let storage = SqliteStorage::open(&path).await.unwrap();
+        let bank = Bank::new(&storage);
+
+        let reopened = bank.get_account_from_number(account.number).await.unwrap();
+        assert_eq!(bank.get_balance(&reopened).await.unwrap().amount_minor, 100);
+
+        // The sequence carries on instead of handing out the same number again
+        let other = open_account(&bank, 2).await;
+        assert_ne!(other.number, account.number);
+    }
+
+    #[tokio::test]
+    async fn test_history_pages() {
+        let storage = new_sqlite_storage().await;
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, 1).await;
+        for amount in 1..=5 {
+            deposit(&bank, &account, amount).await;
+        }
+        bank.create_transaction(Transaction::Withdraw {
+            amount: Amount::new(3).unwrap(),
+            origin: account.clone(),
+        })
+        .await
+        .unwrap();
+
+        let filter = TransactionFilter {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let mut cursor = None;
+        let mut entries = Vec::new();
+        loop {
+            let page = bank
+                .list_transactions(&account, &filter, cursor.as_ref())
+                .await
+                .unwrap();
+            entries.extend(page.entries);
+
+            match page.next_cursor {
+                Some(next) => cursor = Some(next.parse::<TransactionCursor>().unwrap()),
+                None => break,
+            }
+        }
+
+        let amounts: Vec<i64> = entries.iter().map(|entry| entry.amount).collect();
+        assert_eq!(amounts, vec![1, 2, 3, 4, 5, -3]);
+        assert_eq!(entries[5].balance, BigDecimal::from(12));
+
+        let withdraws = bank
+            .list_transactions(
+                &account,
+                &TransactionFilter {
+                    types: Some(vec![TransactionType::Withdraw]),
+                    min_amount: Some(2),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(withdraws.entries.len(), 1);
+        assert_eq!(withdraws.entries[0].balance, BigDecimal::from(12));
+    }
+
+    #[tokio::test]
+    async fn test_fee_schedule() {
+        let storage = new_sqlite_storage().await;
+        storage
+            .set_fee_schedule(&FeeSchedule {
+                account_type: AccountType::Checking,
+                event: FeeEvent::Withdraw,
+                currency: Currency::Brl,
+                rule: FeeRule::Flat { amount: 5 },
+                min_fee: None,
+                max_fee: None,
+            })
+            .await
+            .unwrap();
+        let bank = Bank::new(&storage);
+
+        let account = open_account(&bank, 1).await;
+        deposit(&bank, &account, 100).await;
+
+        let receipt = bank
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(50).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(receipt.fees.len(), 1);
+        assert_eq!(bank.get_balance(&account).await.unwrap().amount_minor, 45);
+    }
+}
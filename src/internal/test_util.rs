@@ -1,18 +1,92 @@
-use crate::internal::config::database::{Database, DatabaseParams};
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, AccountType},
+    },
+    config::{database::Database, settings::Settings},
+    customer::{
+        customer::CustomerManager,
+        domain::{cpf_check_digits, NewCustomer},
+    },
+    money::domain::{Amount, Currency},
+    transaction::{domain::Transaction, transaction::TransactionManager},
+};
 
 pub async fn get_conn_with_new_db() -> Database {
     let random_db_name = uuid::Uuid::now_v7().to_string();
     let random_db_name = "random_".to_string() + &random_db_name.replace("-", "_");
     println!("Random Db Name {}", random_db_name);
 
-    let database = Database::build_with_new_database(DatabaseParams {
-        host: "localhost".to_string(),
-        password: "pass".to_string(),
-        port: 5432,
-        user: "user".to_string(),
-        db_name: random_db_name,
-    })
-    .await
-    .expect("Failed to connect to Database");
+    // Same server as the binaries, see `Settings::load`, on a database of its own
+    let mut params = Settings::load().expect("Invalid configuration").database;
+    params.db_name = random_db_name;
+
+    let database = Database::build_with_new_database(params)
+        .await
+        .expect("Failed to connect to Database");
     database
 }
+
+/// Customer whose CPF is built from the last nine digits of `seed`, so different seeds below a
+/// billion never share a document
+pub fn new_customer(seed: u128) -> NewCustomer {
+    let mut digits: Vec<u32> = format!("{:09}", seed % 1_000_000_000)
+        .chars()
+        .map(|c| c.to_digit(10).unwrap())
+        .collect();
+    let (first, second) = cpf_check_digits(&digits);
+    digits.extend([first, second]);
+
+    NewCustomer {
+        name: "Test Customer".to_string(),
+        document_number: digits.iter().map(|d| d.to_string()).collect(),
+        email: "customer@example.com".to_string(),
+        date_of_birth: chrono::NaiveDate::from_ymd_opt(1990, 1, 1).unwrap(),
+    }
+}
+
+/// Creates a customer with a random valid CPF, to hold the accounts created by the tests
+pub async fn create_customer(db_pool: &sqlx::PgPool) -> uuid::Uuid {
+    let customer = CustomerManager::new(db_pool)
+        .create_customer(&new_customer(uuid::Uuid::now_v7().as_u128()))
+        .await
+        .expect("Failed to create customer");
+
+    customer.id
+}
+
+/// Opens an account of a new customer, with a deposit of `amount` unless it is zero
+pub async fn create_funded_account(
+    db_pool: &sqlx::PgPool,
+    account_type: AccountType,
+    currency: Currency,
+    amount: i64,
+) -> Account {
+    let account = AccountManager::new(db_pool)
+        .create_account_with_currency(account_type, currency, &[create_customer(db_pool).await])
+        .await
+        .expect("Failed to create account");
+
+    if amount > 0 {
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(amount).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .expect("Failed to deposit");
+    }
+
+    account
+}
+
+/// Opens a new SQLite database, in a file of the temporary directory
+#[cfg(feature = "sqlite")]
+pub async fn new_sqlite_storage() -> crate::internal::storage::sqlite::SqliteStorage {
+    let path = std::env::temp_dir().join(format!("bank_{}.db", uuid::Uuid::now_v7()));
+    println!("SQLite database {}", path.display());
+
+    crate::internal::storage::sqlite::SqliteStorage::open(&path)
+        .await
+        .expect("Failed to open SQLite database")
+}
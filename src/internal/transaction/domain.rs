@@ -1,20 +1,205 @@
-use serde::Deserialize;
+use std::{fmt, str::FromStr};
 
-use crate::internal::account::domain::Account;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
-#[derive(Deserialize, Debug)]
+use crate::internal::{
+    account::domain::Account,
+    error::ErrorKind,
+    fee::domain::AssessedFee,
+    money::domain::{Amount, Currency},
+};
+
+use super::error::TransactionError;
+
+#[derive(Serialize, Deserialize, Debug)]
 pub enum Transaction {
     Deposit {
-        amount: u32,
+        amount: Amount,
         destination: Account,
     },
     Withdraw {
-        amount: u32,
+        amount: Amount,
         origin: Account,
     },
     Transfer {
-        amount: u32,
+        amount: Amount,
+        origin: Account,
+        destination: Account,
+    },
+    /// Transfer between accounts in different currencies, moving the amount of the quote at its
+    /// locked rate
+    FxTransfer {
+        quote_id: Uuid,
         origin: Account,
         destination: Account,
     },
 }
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionReceipt {
+    pub journal_entry_id: Uuid,
+    /// Ids of the postings booked on customer accounts, in the order they were created
+    pub transaction_ids: Vec<i32>,
+    /// Links both legs when the transaction is a transfer
+    pub transfer_id: Option<Uuid>,
+    /// Fees booked in the same journal entry
+    #[serde(default)]
+    pub fees: Vec<AssessedFee>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionType {
+    Deposit,
+    Withdraw,
+    TransferIn,
+    TransferOut,
+    Reversal,
+    HoldCapture,
+    Interest,
+    Fee,
+    /// System side of a conversion, on the FX position and gain/loss accounts
+    FxConversion,
+}
+
+impl TransactionType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdraw => "withdraw",
+            TransactionType::TransferIn => "transfer_in",
+            TransactionType::TransferOut => "transfer_out",
+            TransactionType::Reversal => "reversal",
+            TransactionType::HoldCapture => "hold_capture",
+            TransactionType::Interest => "interest",
+            TransactionType::Fee => "fee",
+            TransactionType::FxConversion => "fx_conversion",
+        }
+    }
+}
+
+impl FromStr for TransactionType {
+    type Err = TransactionError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "deposit" => Ok(TransactionType::Deposit),
+            "withdraw" => Ok(TransactionType::Withdraw),
+            "transfer_in" => Ok(TransactionType::TransferIn),
+            "transfer_out" => Ok(TransactionType::TransferOut),
+            "reversal" => Ok(TransactionType::Reversal),
+            "hold_capture" => Ok(TransactionType::HoldCapture),
+            "interest" => Ok(TransactionType::Interest),
+            "fee" => Ok(TransactionType::Fee),
+            "fx_conversion" => Ok(TransactionType::FxConversion),
+            _ => Err(TransactionError::new(
+                format!("Unknown transaction type [{}]", value),
+                ErrorKind::InvalidInput,
+            )),
+        }
+    }
+}
+
+/// A row of an account statement
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TransactionEntry {
+    pub id: i32,
+    /// Groups every posting of the same movement, used to reverse it
+    pub journal_entry_id: Uuid,
+    pub amount: i64,
+    #[serde(rename = "type")]
+    pub transaction_type: TransactionType,
+    pub created_at: DateTime<Utc>,
+    /// Shared by both legs of a transfer
+    pub transfer_id: Option<Uuid>,
+    /// Number of the account on the other side of a transfer
+    pub counterparty_account_number: Option<i64>,
+    /// Balance of the account right after this entry was booked
+    pub balance: BigDecimal,
+}
+
+pub const DEFAULT_HISTORY_PAGE_SIZE: i64 = 50;
+pub const MAX_HISTORY_PAGE_SIZE: i64 = 500;
+
+/// Every field is optional, an empty filter matches the whole account history
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    /// Inclusive
+    pub from: Option<DateTime<Utc>>,
+    /// Exclusive
+    pub to: Option<DateTime<Utc>>,
+    pub types: Option<Vec<TransactionType>>,
+    /// Compared against the absolute amount, so withdraws can be filtered the same way as deposits
+    pub min_amount: Option<i64>,
+    pub max_amount: Option<i64>,
+    /// Defaults to `DEFAULT_HISTORY_PAGE_SIZE`, capped at `MAX_HISTORY_PAGE_SIZE`
+    pub limit: Option<i64>,
+}
+
+/// Position after the last entry of a page, entries are ordered by `created_at` then `id`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionCursor {
+    pub created_at: DateTime<Utc>,
+    pub id: i32,
+}
+
+impl fmt::Display for TransactionCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.created_at.timestamp_micros(), self.id)
+    }
+}
+
+impl FromStr for TransactionCursor {
+    type Err = TransactionError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            TransactionError::new(
+                format!("Invalid cursor [{}]", value),
+                ErrorKind::InvalidInput,
+            )
+        };
+
+        let (micros, id) = value.split_once('_').ok_or_else(invalid)?;
+        let micros: i64 = micros.parse().map_err(|_| invalid())?;
+        let id: i32 = id.parse().map_err(|_| invalid())?;
+        let created_at = DateTime::from_timestamp_micros(micros).ok_or_else(invalid)?;
+
+        Ok(Self { created_at, id })
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct TransactionPage {
+    pub entries: Vec<TransactionEntry>,
+    /// Present when there may be more entries after this page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrialBalanceLine {
+    pub account_id: Uuid,
+    pub account_number: i64,
+    /// Only present on system accounts
+    pub system_code: Option<String>,
+    pub currency: Currency,
+    pub debits: BigDecimal,
+    pub credits: BigDecimal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrialBalance {
+    pub lines: Vec<TrialBalanceLine>,
+    pub total_debits: BigDecimal,
+    pub total_credits: BigDecimal,
+    pub unbalanced_journal_entries: Vec<Uuid>,
+}
+
+impl TrialBalance {
+    pub fn is_balanced(&self) -> bool {
+        self.total_debits == self.total_credits && self.unbalanced_journal_entries.is_empty()
+    }
+}
@@ -1,14 +1,14 @@
-use crate::internal::error::BankError;
+use crate::internal::error::{BankError, ErrorKind};
 
 #[derive(Debug)]
 pub struct TransactionError {
     message: String,
-    status: axum::http::StatusCode,
+    kind: ErrorKind,
 }
 
 impl TransactionError {
-    pub fn new(message: String, status: axum::http::StatusCode) -> Self {
-        Self { message, status }
+    pub fn new(message: String, kind: ErrorKind) -> Self {
+        Self { message, kind }
     }
 }
 
@@ -16,8 +16,8 @@ impl BankError for TransactionError {
     fn message(&self) -> &str {
         &self.message
     }
-    fn status(&self) -> &axum::http::StatusCode {
-        &self.status
+    fn kind(&self) -> &ErrorKind {
+        &self.kind
     }
 }
 
@@ -25,7 +25,13 @@ impl From<Box<dyn BankError>> for TransactionError {
     fn from(error: Box<dyn BankError>) -> Self {
         Self {
             message: error.message().to_string(),
-            status: error.status().clone(),
+            kind: *error.kind(),
         }
     }
 }
+
+impl From<TransactionError> for Box<dyn BankError> {
+    fn from(error: TransactionError) -> Self {
+        Box::new(error)
+    }
+}
@@ -0,0 +1,241 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::internal::error::{BankError, ErrorKind};
+
+use super::{
+    domain::{Transaction, TransactionReceipt},
+    error::TransactionError,
+};
+
+/// How long a key is remembered when the manager is not configured otherwise
+pub const DEFAULT_IDEMPOTENCY_KEY_TTL: chrono::Duration = chrono::Duration::hours(24);
+
+pub(crate) enum IdempotencyClaim {
+    /// The key is new, or expired, and now belongs to the current database transaction
+    Claimed,
+    /// The key was already used by an identical request
+    Replay(TransactionReceipt),
+}
+
+pub(crate) fn validate_key(key: &str) -> Result<(), Box<dyn BankError>> {
+    if key.is_empty() || key.len() > 255 {
+        return Err(Box::new(TransactionError::new(
+            "Idempotency key must have between 1 and 255 characters".to_string(),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    Ok(())
+}
+
+/// What the client sent, accounts are named by number so a change to their rows, like a
+/// freeze, doesn't turn a retry into a different request
+#[derive(Serialize)]
+#[serde(tag = "type")]
+enum RequestFingerprint {
+    Deposit {
+        amount: i64,
+        destination: i64,
+    },
+    Withdraw {
+        amount: i64,
+        origin: i64,
+    },
+    Transfer {
+        amount: i64,
+        origin: i64,
+        destination: i64,
+    },
+    FxTransfer {
+        quote_id: Uuid,
+        origin: i64,
+        destination: i64,
+    },
+}
+
+impl From<&Transaction> for RequestFingerprint {
+    fn from(transaction: &Transaction) -> Self {
+        match transaction {
+            Transaction::Deposit {
+                amount,
+                destination,
+            } => RequestFingerprint::Deposit {
+                amount: amount.minor_units(),
+                destination: *destination.number(),
+            },
+            Transaction::Withdraw { amount, origin } => RequestFingerprint::Withdraw {
+                amount: amount.minor_units(),
+                origin: *origin.number(),
+            },
+            Transaction::Transfer {
+                amount,
+                origin,
+                destination,
+            } => RequestFingerprint::Transfer {
+                amount: amount.minor_units(),
+                origin: *origin.number(),
+                destination: *destination.number(),
+            },
+            Transaction::FxTransfer {
+                quote_id,
+                origin,
+                destination,
+            } => RequestFingerprint::FxTransfer {
+                quote_id: *quote_id,
+                origin: *origin.number(),
+                destination: *destination.number(),
+            },
+        }
+    }
+}
+
+pub(crate) fn fingerprint(transaction: &Transaction) -> Result<String, Box<dyn BankError>> {
+    match serde_json::to_vec(&RequestFingerprint::from(transaction)) {
+        Ok(payload) => Ok(format!("{:x}", Sha256::digest(payload))),
+        Err(e) => {
+            println!("Error serializing transaction: {}", e);
+            Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )))
+        }
+    }
+}
+
+/// Claims the key for the current database transaction.
+///
+/// If another request is still running with the same key, the insert waits for it to finish,
+/// so a replay only ever sees a committed response.
+pub(crate) async fn claim_key(
+    key: &str,
+    fingerprint: &str,
+    ttl: chrono::Duration,
+    conn: &mut sqlx::PgConnection,
+) -> Result<IdempotencyClaim, Box<dyn BankError>> {
+    let now = chrono::Utc::now();
+
+    let claimed = sqlx::query!(
+        "INSERT INTO idempotency_key (key, fingerprint, created_at, expires_at) VALUES ($1, $2, $3, $4)
+        ON CONFLICT (key) DO UPDATE
+        SET fingerprint = EXCLUDED.fingerprint, response = NULL, created_at = EXCLUDED.created_at, expires_at = EXCLUDED.expires_at
+        WHERE idempotency_key.expires_at <= EXCLUDED.created_at
+        RETURNING key",
+        key,
+        fingerprint,
+        now,
+        now + ttl
+    )
+    .fetch_optional(&mut *conn)
+    .await;
+
+    match claimed {
+        Ok(Some(_)) => return Ok(IdempotencyClaim::Claimed),
+        Ok(None) => {}
+        Err(e) => {
+            println!("Error claiming idempotency key: {}", e);
+            return Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )));
+        }
+    }
+
+    let existing = sqlx::query!(
+        "SELECT fingerprint, response FROM idempotency_key WHERE key = $1",
+        key
+    )
+    .fetch_one(conn)
+    .await;
+
+    let existing = match existing {
+        Ok(existing) => existing,
+        Err(e) => {
+            println!("Error reading idempotency key: {}", e);
+            return Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )));
+        }
+    };
+
+    if existing.fingerprint != fingerprint {
+        return Err(Box::new(TransactionError::new(
+            format!(
+                "Idempotency key [{}] was already used with a different request",
+                key
+            ),
+            ErrorKind::Unprocessable,
+        )));
+    }
+
+    match existing
+        .response
+        .map(serde_json::from_value::<TransactionReceipt>)
+    {
+        Some(Ok(receipt)) => Ok(IdempotencyClaim::Replay(receipt)),
+        _ => Err(Box::new(TransactionError::new(
+            "An unexpected error happened, please try again".to_string(),
+            ErrorKind::Internal,
+        ))),
+    }
+}
+
+pub(crate) async fn store_response(
+    key: &str,
+    receipt: &TransactionReceipt,
+    conn: &mut sqlx::PgConnection,
+) -> Result<(), Box<dyn BankError>> {
+    let response = match serde_json::to_value(receipt) {
+        Ok(response) => response,
+        Err(e) => {
+            println!("Error serializing transaction receipt: {}", e);
+            return Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )));
+        }
+    };
+
+    let result = sqlx::query!(
+        "UPDATE idempotency_key SET response = $2 WHERE key = $1",
+        key,
+        response
+    )
+    .execute(conn)
+    .await;
+
+    match result {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            println!("Error storing idempotency key response: {}", e);
+            Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )))
+        }
+    }
+}
+
+pub(crate) async fn delete_expired_keys(
+    conn: &mut sqlx::PgConnection,
+) -> Result<u64, Box<dyn BankError>> {
+    let result = sqlx::query!(
+        "DELETE FROM idempotency_key WHERE expires_at <= $1",
+        chrono::Utc::now()
+    )
+    .execute(conn)
+    .await;
+
+    match result {
+        Ok(result) => Ok(result.rows_affected()),
+        Err(e) => {
+            println!("Error deleting expired idempotency keys: {}", e);
+            Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )))
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{account::AccountManager, domain::SystemAccount},
+    error::{BankError, ErrorKind},
+    money::domain::Currency,
+    storage::repository::{AccountRepository, LedgerRepository},
+};
+
+use super::{
+    domain::{TransactionType, TrialBalance, TrialBalanceLine},
+    error::TransactionError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LedgerAccount {
+    Customer(Uuid),
+    /// System account of the currency of the entry
+    System(SystemAccount, Currency),
+}
+
+/// One side of a journal entry, booked as a row on the `transaction` table.
+///
+/// Positive amounts credit the account and negative ones debit it, so a customer deposit is
+/// a credit on the customer account and a debit on the cash vault.
+#[derive(Debug, Clone)]
+pub struct Posting {
+    pub account: LedgerAccount,
+    pub amount: i64,
+    pub transaction_type: TransactionType,
+    pub transfer_id: Option<Uuid>,
+    pub counterparty_account_id: Option<Uuid>,
+}
+
+impl Posting {
+    pub fn new(account: LedgerAccount, amount: i64, transaction_type: TransactionType) -> Self {
+        Self {
+            account,
+            amount,
+            transaction_type,
+            transfer_id: None,
+            counterparty_account_id: None,
+        }
+    }
+}
+
+pub(crate) struct PostedJournalEntry {
+    pub id: Uuid,
+    /// Same order as the postings given to `post_journal_entry`
+    pub transaction_ids: Vec<i32>,
+}
+
+impl PostedJournalEntry {
+    /// Ids of the postings booked on customer accounts, `postings` being the ones given to
+    /// `post_journal_entry`
+    pub fn customer_transaction_ids(&self, postings: &[Posting]) -> Vec<i32> {
+        postings
+            .iter()
+            .zip(&self.transaction_ids)
+            .filter(|(posting, _)| matches!(posting.account, LedgerAccount::Customer(_)))
+            .map(|(_, id)| *id)
+            .collect()
+    }
+}
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(TransactionError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+/// Currency of the account of every posting, in the same order
+async fn posting_currencies<R: AccountRepository + ?Sized>(
+    postings: &[Posting],
+    conn: &mut R,
+) -> Result<Vec<Currency>, Box<dyn BankError>> {
+    let mut customer_currencies: BTreeMap<Uuid, Currency> = BTreeMap::new();
+    for posting in postings {
+        if let LedgerAccount::Customer(id) = posting.account {
+            if customer_currencies.contains_key(&id) {
+                continue;
+            }
+
+            match conn.find_account_by_id(&id).await? {
+                Some(account) => {
+                    customer_currencies.insert(id, account.currency);
+                }
+                None => {
+                    println!("Posting on unknown account [{}]", id);
+                    return Err(unexpected_error());
+                }
+            }
+        }
+    }
+
+    Ok(postings
+        .iter()
+        .map(|posting| match posting.account {
+            LedgerAccount::Customer(id) => customer_currencies[&id],
+            LedgerAccount::System(_, currency) => currency,
+        })
+        .collect())
+}
+
+/// Books the postings as a single journal entry and updates the balance of every customer account
+/// involved. The postings must sum to zero in each currency, so a conversion balances both of
+/// its legs on its own.
+///
+/// System accounts take part in nearly every entry, so their rows are never written: their
+/// balance is the sum of their postings. Only the customer accounts, locked before calling this,
+/// are held until the entry commits, and entries of different customers don't wait on each other.
+pub(crate) async fn post_journal_entry<R: AccountRepository + LedgerRepository + ?Sized>(
+    description: &str,
+    postings: Vec<Posting>,
+    conn: &mut R,
+) -> Result<PostedJournalEntry, Box<dyn BankError>> {
+    let mut totals: BTreeMap<Currency, i64> = BTreeMap::new();
+    for (posting, currency) in postings
+        .iter()
+        .zip(posting_currencies(&postings, conn).await?)
+    {
+        *totals.entry(currency).or_default() += posting.amount;
+    }
+
+    if totals.values().any(|total| *total != 0) {
+        println!("Refusing unbalanced journal entry: {:?}", postings);
+        return Err(unexpected_error());
+    }
+
+    let mut customer_changes: BTreeMap<Uuid, i64> = BTreeMap::new();
+    let mut system_accounts: BTreeSet<(SystemAccount, Currency)> = BTreeSet::new();
+    for posting in &postings {
+        match posting.account {
+            LedgerAccount::Customer(id) => {
+                *customer_changes.entry(id).or_default() += posting.amount;
+            }
+            LedgerAccount::System(system_account, currency) => {
+                system_accounts.insert((system_account, currency));
+            }
+        }
+    }
+
+    let mut system_ids: BTreeMap<(SystemAccount, Currency), Uuid> = BTreeMap::new();
+    for (system_account, currency) in &system_accounts {
+        let account = AccountManager::get_system_account(*system_account, *currency, conn).await?;
+        system_ids.insert((*system_account, *currency), *account.id());
+    }
+
+    let journal_entry_id = Uuid::now_v7();
+    conn.insert_journal_entry(&journal_entry_id, description)
+        .await?;
+
+    for (account_id, amount) in &customer_changes {
+        AccountManager::apply_balance_change(account_id, *amount, conn).await?;
+    }
+
+    let mut transaction_ids = Vec::with_capacity(postings.len());
+    for posting in &postings {
+        let account_id = match posting.account {
+            LedgerAccount::Customer(id) => id,
+            LedgerAccount::System(system_account, currency) => {
+                system_ids[&(system_account, currency)]
+            }
+        };
+
+        transaction_ids.push(
+            conn.insert_posting(&journal_entry_id, &account_id, posting)
+                .await?,
+        );
+    }
+
+    Ok(PostedJournalEntry {
+        id: journal_entry_id,
+        transaction_ids,
+    })
+}
+
+pub(crate) async fn trial_balance(
+    conn: &mut sqlx::PgConnection,
+) -> Result<TrialBalance, Box<dyn BankError>> {
+    let lines = sqlx::query_as!(
+        TrialBalanceLine,
+        r#"SELECT account.id AS account_id, account.number AS account_number, account.system_code, account.currency AS "currency: Currency",
+        COALESCE(-SUM(transaction.amount) FILTER (WHERE transaction.amount < 0), 0) AS "debits!",
+        COALESCE(SUM(transaction.amount) FILTER (WHERE transaction.amount > 0), 0) AS "credits!"
+        FROM account
+        LEFT JOIN transaction ON transaction.account_id = account.id
+        GROUP BY account.id
+        ORDER BY account.number"#
+    )
+    .fetch_all(&mut *conn)
+    .await;
+
+    let lines = match lines {
+        Ok(lines) => lines,
+        Err(e) => {
+            println!("Error computing trial balance: {}", e);
+            return Err(unexpected_error());
+        }
+    };
+
+    let unbalanced_journal_entries = sqlx::query!(
+        "SELECT DISTINCT transaction.journal_entry_id FROM transaction
+        JOIN account ON account.id = transaction.account_id
+        GROUP BY transaction.journal_entry_id, account.currency
+        HAVING SUM(transaction.amount) <> 0"
+    )
+    .fetch_all(conn)
+    .await;
+
+    let unbalanced_journal_entries = match unbalanced_journal_entries {
+        Ok(rows) => rows.into_iter().map(|row| row.journal_entry_id).collect(),
+        Err(e) => {
+            println!("Error checking journal entries: {}", e);
+            return Err(unexpected_error());
+        }
+    };
+
+    let total_debits = lines.iter().map(|line| &line.debits).sum();
+    let total_credits = lines.iter().map(|line| &line.credits).sum();
+
+    Ok(TrialBalance {
+        lines,
+        total_debits,
+        total_credits,
+        unbalanced_journal_entries,
+    })
+}
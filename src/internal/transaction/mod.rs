@@ -1,3 +1,7 @@
 pub mod domain;
 pub mod error;
+pub mod idempotency;
+pub mod ledger;
+pub mod reversal;
+#[allow(clippy::module_inception)]
 pub mod transaction;
@@ -0,0 +1,331 @@
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+use crate::internal::{
+    account::{
+        account::AccountManager,
+        domain::{Account, AccountStatus, AccountType, SystemAccount},
+    },
+    error::{BankError, ErrorKind},
+    money::domain::{Amount, Currency},
+};
+
+use super::{
+    domain::{TransactionReceipt, TransactionType},
+    error::TransactionError,
+    ledger::{self, LedgerAccount, Posting},
+    transaction::TransactionManager,
+};
+
+struct OriginalPosting {
+    account: Account,
+    system_code: Option<String>,
+    amount: i64,
+    transaction_type: TransactionType,
+    counterparty_account_id: Option<Uuid>,
+}
+
+impl OriginalPosting {
+    fn ledger_account(&self) -> Result<LedgerAccount, Box<dyn BankError>> {
+        match self.system_code.as_deref() {
+            None => Ok(LedgerAccount::Customer(*self.account.id())),
+            Some(code) => match SystemAccount::from_code(code) {
+                Some(system_account) => Ok(LedgerAccount::System(
+                    system_account,
+                    *self.account.currency(),
+                )),
+                None => {
+                    println!("Unknown system account [{}]", code);
+                    Err(unexpected_error())
+                }
+            },
+        }
+    }
+
+    /// Postings carrying the movement itself, as opposed to the ones booked alongside it
+    fn is_principal(&self) -> bool {
+        matches!(
+            self.transaction_type,
+            TransactionType::Deposit
+                | TransactionType::Withdraw
+                | TransactionType::TransferIn
+                | TransactionType::TransferOut
+                | TransactionType::HoldCapture
+                | TransactionType::Interest
+        )
+    }
+}
+
+fn unexpected_error() -> Box<dyn BankError> {
+    Box::new(TransactionError::new(
+        "An unexpected error happened, please try again".to_string(),
+        ErrorKind::Internal,
+    ))
+}
+
+async fn get_original_postings(
+    journal_entry_id: &Uuid,
+    conn: &mut sqlx::PgConnection,
+) -> Result<Vec<OriginalPosting>, Box<dyn BankError>> {
+    let rows = sqlx::query!(
+        r#"SELECT account.id AS account_id, account.number AS account_number, account.type AS "account_type: AccountType", account.status AS "account_status: AccountStatus", account.currency AS "currency: Currency", account.system_code,
+        transaction.amount AS "amount!", transaction.type AS "type!", transaction.counterparty_account_id
+        FROM transaction
+        JOIN account ON account.id = transaction.account_id
+        WHERE transaction.journal_entry_id = $1
+        ORDER BY transaction.id"#,
+        journal_entry_id
+    )
+    .fetch_all(conn)
+    .await;
+
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => {
+            println!("Error getting journal entry postings: {}", e);
+            return Err(unexpected_error());
+        }
+    };
+
+    let mut postings = Vec::with_capacity(rows.len());
+    for row in rows {
+        postings.push(OriginalPosting {
+            account: Account::from_existing(
+                row.account_id,
+                row.account_number,
+                row.account_type,
+                row.account_status,
+                row.currency,
+            ),
+            system_code: row.system_code,
+            amount: row.amount,
+            transaction_type: row.r#type.parse::<TransactionType>()?,
+            counterparty_account_id: row.counterparty_account_id,
+        });
+    }
+
+    Ok(postings)
+}
+
+/// Books a compensating journal entry for `amount` of the original one, or for whatever was not
+/// reversed yet when `amount` is `None`.
+///
+/// Reversing the whole amount at once also reverses every posting booked alongside the
+/// movement, partial refunds only touch the transfer legs.
+pub(crate) async fn book_reversal(
+    original_journal_entry_id: &Uuid,
+    amount: Option<Amount>,
+    reason: &str,
+    conn: &mut sqlx::PgConnection,
+) -> Result<TransactionReceipt, Box<dyn BankError>> {
+    if reason.trim().is_empty() || reason.len() > 255 {
+        return Err(Box::new(TransactionError::new(
+            "Reversal reason must have between 1 and 255 characters".to_string(),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    // Serializes concurrent reversals of the same entry
+    let original = sqlx::query!(
+        "SELECT id FROM journal_entry WHERE id = $1 FOR UPDATE",
+        original_journal_entry_id
+    )
+    .fetch_optional(&mut *conn)
+    .await;
+
+    match original {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return Err(Box::new(TransactionError::new(
+                format!("Transaction [{}] not found", original_journal_entry_id),
+                ErrorKind::NotFound,
+            )))
+        }
+        Err(e) => {
+            println!("Error locking journal entry: {}", e);
+            return Err(unexpected_error());
+        }
+    }
+
+    let postings = get_original_postings(original_journal_entry_id, conn).await?;
+
+    if postings
+        .iter()
+        .any(|posting| posting.transaction_type == TransactionType::Reversal)
+    {
+        return Err(Box::new(TransactionError::new(
+            "A reversal can't be reversed".to_string(),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    let is_transfer = postings
+        .iter()
+        .any(|posting| posting.transaction_type == TransactionType::TransferOut);
+
+    // A fee charged on its own, like the monthly maintenance, is the movement of its entry
+    let is_standalone_fee = !postings.iter().any(OriginalPosting::is_principal)
+        && postings
+            .iter()
+            .any(|posting| posting.transaction_type == TransactionType::Fee);
+    let is_principal = |posting: &OriginalPosting| {
+        posting.is_principal()
+            || (is_standalone_fee && posting.transaction_type == TransactionType::Fee)
+    };
+
+    // Deposits and withdraws have a customer and a vault posting, transfers two customer
+    // legs, either way the moved amount is the positive principal posting
+    let principal: i64 = postings
+        .iter()
+        .filter(|posting| is_principal(posting) && posting.amount > 0)
+        .map(|posting| posting.amount)
+        .sum();
+
+    if principal <= 0 {
+        return Err(Box::new(TransactionError::new(
+            format!(
+                "Transaction [{}] has no movement that can be reversed",
+                original_journal_entry_id
+            ),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    let already_reversed = sqlx::query!(
+        r#"SELECT COALESCE(SUM(amount), 0)::BIGINT AS "amount!" FROM reversal WHERE original_journal_entry_id = $1"#,
+        original_journal_entry_id
+    )
+    .fetch_one(&mut *conn)
+    .await;
+
+    let already_reversed = match already_reversed {
+        Ok(row) => row.amount,
+        Err(e) => {
+            println!("Error getting previous reversals: {}", e);
+            return Err(unexpected_error());
+        }
+    };
+
+    let remaining = principal - already_reversed;
+    if remaining <= 0 {
+        return Err(Box::new(TransactionError::new(
+            format!(
+                "Transaction [{}] was already reversed",
+                original_journal_entry_id
+            ),
+            ErrorKind::Conflict,
+        )));
+    }
+
+    let amount: i64 = match amount {
+        None => remaining,
+        Some(amount) => amount.into(),
+    };
+
+    if amount <= 0 || amount > remaining {
+        return Err(Box::new(TransactionError::new(
+            format!(
+                "Reversal amount must be between 1 and the {} not reversed yet",
+                remaining
+            ),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    let is_conversion = postings
+        .iter()
+        .any(|posting| posting.transaction_type == TransactionType::FxConversion);
+
+    // The legs of a conversion are in different currencies, refunding part of one of them
+    // would need a new rate
+    if amount < principal && is_conversion {
+        return Err(Box::new(TransactionError::new(
+            "Partial refunds are not supported for currency conversions".to_string(),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    if amount < principal && !is_transfer {
+        return Err(Box::new(TransactionError::new(
+            "Partial refunds are only supported for transfers".to_string(),
+            ErrorKind::InvalidInput,
+        )));
+    }
+
+    let reverses_everything = amount == principal;
+
+    let mut reversal_postings = Vec::new();
+    for posting in &postings {
+        let reversed_amount = if reverses_everything {
+            -posting.amount
+        } else if is_principal(posting) {
+            -posting.amount.signum() * amount
+        } else {
+            continue;
+        };
+
+        reversal_postings.push(Posting {
+            counterparty_account_id: posting.counterparty_account_id,
+            ..Posting::new(
+                posting.ledger_account()?,
+                reversed_amount,
+                TransactionType::Reversal,
+            )
+        });
+    }
+
+    let mut customer_changes: BTreeMap<Uuid, i64> = BTreeMap::new();
+    for posting in &reversal_postings {
+        if let LedgerAccount::Customer(id) = posting.account {
+            *customer_changes.entry(id).or_default() += posting.amount;
+        }
+    }
+
+    let customers: Vec<&Account> = postings
+        .iter()
+        .filter(|posting| customer_changes.contains_key(posting.account.id()))
+        .map(|posting| &posting.account)
+        .collect();
+    AccountManager::lock_accounts(&customers, conn).await?;
+
+    for account in &customers {
+        // Reversals are corrections, they go through frozen accounts but not closed ones
+        TransactionManager::ensure_can_receive(account, conn).await?;
+
+        let change = customer_changes[account.id()];
+        if change < 0 {
+            TransactionManager::ensure_funds(-change, account, conn).await?;
+        }
+    }
+
+    let journal_entry = ledger::post_journal_entry(
+        &format!("reversal of {}", original_journal_entry_id),
+        reversal_postings.clone(),
+        conn,
+    )
+    .await?;
+
+    let reversal = sqlx::query!(
+        "INSERT INTO reversal (id, original_journal_entry_id, journal_entry_id, amount, reason) VALUES ($1, $2, $3, $4, $5)",
+        Uuid::now_v7(),
+        original_journal_entry_id,
+        journal_entry.id,
+        amount,
+        reason
+    )
+    .execute(&mut *conn)
+    .await;
+
+    if let Err(e) = reversal {
+        println!("Error creating reversal: {}", e);
+        return Err(unexpected_error());
+    }
+
+    Ok(TransactionReceipt {
+        journal_entry_id: journal_entry.id,
+        transaction_ids: journal_entry.customer_transaction_ids(&reversal_postings),
+        transfer_id: None,
+        fees: Vec::new(),
+    })
+}
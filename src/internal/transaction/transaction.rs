@@ -1,80 +1,485 @@
 use crate::internal::{
-    account::{account::AccountManager, domain::Account},
-    error::BankError,
+    account::{
+        account::AccountManager,
+        domain::{
+            Account, AccountStatus, AccountType, SystemAccount, SAVINGS_MONTHLY_WITHDRAW_LIMIT,
+        },
+    },
+    error::{BankError, ErrorKind},
+    fee::{
+        domain::{AssessedFee, FeeEvent},
+        fee,
+    },
+    fx::{domain::FxQuote, fx},
+    money::domain::{Amount, Currency, Money},
+    storage::repository::{AccountRepository, LedgerRepository, Repositories},
     transaction::error::TransactionError,
 };
 
-use super::domain::Transaction;
+use chrono::{DateTime, Datelike, Utc};
+use uuid::Uuid;
+
+use super::{
+    domain::{
+        Transaction, TransactionCursor, TransactionFilter, TransactionPage, TransactionReceipt,
+        TransactionType, TrialBalance, DEFAULT_HISTORY_PAGE_SIZE, MAX_HISTORY_PAGE_SIZE,
+    },
+    idempotency::{self, IdempotencyClaim, DEFAULT_IDEMPOTENCY_KEY_TTL},
+    ledger::{self, LedgerAccount, Posting},
+    reversal,
+};
+
+pub(crate) fn is_insufficient_funds(error: &dyn BankError) -> bool {
+    matches!(error.kind(), ErrorKind::InsufficientFunds { .. })
+}
+
+/// Midnight UTC of the first day of the month of `now`
+fn start_of_month(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date_naive()
+        .with_day(1)
+        .and_then(|day| day.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc())
+        .unwrap_or(now)
+}
 
 pub struct TransactionManager<'a> {
     db_pool: &'a sqlx::PgPool,
+    idempotency_key_ttl: chrono::Duration,
 }
 
 impl<'a> TransactionManager<'a> {
     pub fn new(db_pool: &'a sqlx::PgPool) -> Self {
-        Self { db_pool }
+        Self {
+            db_pool,
+            idempotency_key_ttl: DEFAULT_IDEMPOTENCY_KEY_TTL,
+        }
+    }
+
+    /// Sets for how long an idempotency key is remembered after its first use
+    pub fn with_idempotency_key_ttl(mut self, ttl: chrono::Duration) -> Self {
+        self.idempotency_key_ttl = ttl;
+        self
+    }
+
+    async fn create_deposit<R: AccountRepository + LedgerRepository + ?Sized>(
+        amount: Amount,
+        destination: &Account,
+        conn: &mut R,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let amount_parsed = amount.minor_units();
+
+        let journal_entry = ledger::post_journal_entry(
+            "deposit",
+            vec![
+                Posting::new(
+                    LedgerAccount::Customer(destination.id),
+                    amount_parsed,
+                    TransactionType::Deposit,
+                ),
+                Posting::new(
+                    LedgerAccount::System(SystemAccount::CashVault, destination.currency),
+                    -amount_parsed,
+                    TransactionType::Deposit,
+                ),
+            ],
+            conn,
+        )
+        .await?;
+
+        Ok(TransactionReceipt {
+            journal_entry_id: journal_entry.id,
+            transaction_ids: vec![journal_entry.transaction_ids[0]],
+            transfer_id: None,
+            fees: Vec::new(),
+        })
+    }
+
+    /// Books the withdraw and its fees as one journal entry
+    async fn create_withdraw<R: AccountRepository + LedgerRepository + ?Sized>(
+        amount: Amount,
+        origin: &Account,
+        fees: Vec<AssessedFee>,
+        conn: &mut R,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let amount_parsed = amount.minor_units();
+
+        let mut postings = vec![
+            Posting::new(
+                LedgerAccount::Customer(origin.id),
+                -amount_parsed,
+                TransactionType::Withdraw,
+            ),
+            Posting::new(
+                LedgerAccount::System(SystemAccount::CashVault, origin.currency),
+                amount_parsed,
+                TransactionType::Withdraw,
+            ),
+        ];
+        postings.extend(fee::fee_postings(origin, &fees));
+
+        let journal_entry = ledger::post_journal_entry("withdraw", postings.clone(), conn).await?;
+
+        Ok(TransactionReceipt {
+            journal_entry_id: journal_entry.id,
+            transaction_ids: journal_entry.customer_transaction_ids(&postings),
+            transfer_id: None,
+            fees,
+        })
+    }
+
+    /// Credits interest paid by the bank, booked against the interest expense account
+    pub(crate) async fn create_interest_credit<R: AccountRepository + LedgerRepository + ?Sized>(
+        amount: i64,
+        destination: &Account,
+        conn: &mut R,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let journal_entry = ledger::post_journal_entry(
+            "interest",
+            vec![
+                Posting::new(
+                    LedgerAccount::Customer(destination.id),
+                    amount,
+                    TransactionType::Interest,
+                ),
+                Posting::new(
+                    LedgerAccount::System(SystemAccount::InterestExpense, destination.currency),
+                    -amount,
+                    TransactionType::Interest,
+                ),
+            ],
+            conn,
+        )
+        .await?;
+
+        Ok(TransactionReceipt {
+            journal_entry_id: journal_entry.id,
+            transaction_ids: vec![journal_entry.transaction_ids[0]],
+            transfer_id: None,
+            fees: Vec::new(),
+        })
+    }
+
+    /// Books both legs of a transfer and its fees as one journal entry, linked by a `transfer`
+    /// row
+    pub(crate) async fn create_transfer<R: AccountRepository + LedgerRepository + ?Sized>(
+        amount: Amount,
+        origin: &Account,
+        destination: &Account,
+        fees: Vec<AssessedFee>,
+        conn: &mut R,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let amount_parsed = amount.minor_units();
+        let transfer_id = Uuid::now_v7();
+
+        conn.insert_transfer(&transfer_id, &origin.id, &destination.id, amount_parsed)
+            .await?;
+
+        let mut postings = vec![
+            Posting {
+                transfer_id: Some(transfer_id),
+                counterparty_account_id: Some(destination.id),
+                ..Posting::new(
+                    LedgerAccount::Customer(origin.id),
+                    -amount_parsed,
+                    TransactionType::TransferOut,
+                )
+            },
+            Posting {
+                transfer_id: Some(transfer_id),
+                counterparty_account_id: Some(origin.id),
+                ..Posting::new(
+                    LedgerAccount::Customer(destination.id),
+                    amount_parsed,
+                    TransactionType::TransferIn,
+                )
+            },
+        ];
+        postings.extend(fee::fee_postings(origin, &fees));
+
+        let journal_entry = ledger::post_journal_entry("transfer", postings.clone(), conn).await?;
+
+        Ok(TransactionReceipt {
+            journal_entry_id: journal_entry.id,
+            transaction_ids: journal_entry.customer_transaction_ids(&postings),
+            transfer_id: Some(transfer_id),
+            fees,
+        })
     }
 
-    async fn create_deposit(
-        amount: u32,
+    /// Books both legs of a conversion in their own currencies, each one against the FX position
+    /// of its currency. The destination leg is valued at `market_value`, and what the quoted rate
+    /// gives the customer above or below it is booked on the FX gain/loss account.
+    async fn create_fx_transfer(
+        quote: &FxQuote,
+        market_value: i64,
+        origin: &Account,
         destination: &Account,
+        fees: Vec<AssessedFee>,
         conn: &mut sqlx::PgConnection,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let transfer_id = Uuid::now_v7();
+
+        conn.insert_transfer(&transfer_id, &origin.id, &destination.id, quote.amount)
+            .await?;
+
+        let mut postings = vec![
+            Posting {
+                transfer_id: Some(transfer_id),
+                counterparty_account_id: Some(destination.id),
+                ..Posting::new(
+                    LedgerAccount::Customer(origin.id),
+                    -quote.amount,
+                    TransactionType::TransferOut,
+                )
+            },
+            Posting::new(
+                LedgerAccount::System(SystemAccount::FxPosition, origin.currency),
+                quote.amount,
+                TransactionType::FxConversion,
+            ),
+            Posting {
+                transfer_id: Some(transfer_id),
+                counterparty_account_id: Some(origin.id),
+                ..Posting::new(
+                    LedgerAccount::Customer(destination.id),
+                    quote.converted_amount,
+                    TransactionType::TransferIn,
+                )
+            },
+            Posting::new(
+                LedgerAccount::System(SystemAccount::FxPosition, destination.currency),
+                -market_value,
+                TransactionType::FxConversion,
+            ),
+        ];
+
+        let gain = market_value - quote.converted_amount;
+        if gain != 0 {
+            postings.push(Posting::new(
+                LedgerAccount::System(SystemAccount::FxGainLoss, destination.currency),
+                gain,
+                TransactionType::FxConversion,
+            ));
+        }
+        postings.extend(fee::fee_postings(origin, &fees));
+
+        let journal_entry =
+            ledger::post_journal_entry("fx transfer", postings.clone(), conn).await?;
+        fx::mark_quote_used(&quote.id, &journal_entry.id, conn).await?;
+
+        Ok(TransactionReceipt {
+            journal_entry_id: journal_entry.id,
+            transaction_ids: journal_entry.customer_transaction_ids(&postings),
+            transfer_id: Some(transfer_id),
+            fees,
+        })
+    }
+
+    /// Fails unless the account is active. The account must already be locked by the current
+    /// unit of work.
+    pub(crate) async fn ensure_can_send<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
     ) -> Result<(), Box<dyn BankError>> {
-        let amount_parsed: i64 = amount.into();
-        let result = sqlx::query!(
-            "INSERT INTO transaction (account_id, amount, type) VALUES ($1, $2, $3)",
-            destination.id,
-            amount_parsed,
-            "deposit"
-        )
-        .execute(conn)
-        .await;
+        match AccountManager::get_status(account, conn).await? {
+            AccountStatus::Active => Ok(()),
+            AccountStatus::Frozen => Err(Box::new(TransactionError::new(
+                format!("Account [{}] is frozen", account.number()),
+                ErrorKind::AccountFrozen,
+            ))),
+            status => Err(Box::new(TransactionError::new(
+                format!("Account [{}] is {}", account.number(), status.as_str()),
+                ErrorKind::Conflict,
+            ))),
+        }
+    }
 
-        match result {
-            Ok(_) => {
-                return Ok(());
-            }
-            Err(e) => {
-                return Err(Box::new(TransactionError::new(
-                    e.to_string(),
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                )));
-            }
+    /// Frozen accounts still receive money, closed ones don't. The account must already be
+    /// locked by the current unit of work.
+    pub(crate) async fn ensure_can_receive<R: AccountRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<(), Box<dyn BankError>> {
+        match AccountManager::get_status(account, conn).await? {
+            AccountStatus::Closed => Err(Box::new(TransactionError::new(
+                format!("Account [{}] is closed", account.number()),
+                ErrorKind::Conflict,
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Applies the rules of the account type to money leaving it. The account must already be
+    /// locked by the current unit of work, so concurrent withdraws are counted.
+    pub(crate) async fn ensure_withdraw_allowed<R: LedgerRepository + ?Sized>(
+        account: &Account,
+        conn: &mut R,
+    ) -> Result<(), Box<dyn BankError>> {
+        if account.account_type != AccountType::Savings {
+            return Ok(());
+        }
+
+        let withdraws = conn
+            .count_withdraws_since(account.id(), start_of_month(Utc::now()))
+            .await?;
+
+        if withdraws >= SAVINGS_MONTHLY_WITHDRAW_LIMIT {
+            return Err(Box::new(TransactionError::new(
+                format!(
+                    "Savings account [{}] reached the limit of {} withdraws this month",
+                    account.number(),
+                    SAVINGS_MONTHLY_WITHDRAW_LIMIT
+                ),
+                ErrorKind::Conflict,
+            )));
         }
+
+        Ok(())
     }
 
-    async fn create_withdraw(
-        amount: u32,
+    /// Transfers move the same amount on both sides, so both accounts must be in the same
+    /// currency unless the amount is converted
+    pub(crate) fn ensure_same_currency(
         origin: &Account,
-        conn: &mut sqlx::PgConnection,
+        destination: &Account,
     ) -> Result<(), Box<dyn BankError>> {
-        let amount_parsed: i64 = amount.into();
-        let result = sqlx::query!(
-            "INSERT INTO transaction (account_id, amount, type) VALUES ($1, $2, $3)",
-            origin.id,
-            -amount_parsed,
-            "withdraw"
-        )
-        .execute(conn)
-        .await;
+        if origin.currency() != destination.currency() {
+            return Err(Box::new(TransactionError::new(
+                format!(
+                    "Can't transfer from a {} account to a {} account without a conversion",
+                    origin.currency(),
+                    destination.currency()
+                ),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Fails with [`ErrorKind::InsufficientFunds`] if the available balance plus the overdraft
+    /// limit can't cover the amount.
+    ///
+    /// The account must already be locked by the current unit of work, otherwise a concurrent
+    /// withdraw could spend the same funds between the check and the insert.
+    pub(crate) async fn ensure_funds<R: AccountRepository + ?Sized>(
+        amount: i64,
+        origin: &Account,
+        conn: &mut R,
+    ) -> Result<(), Box<dyn BankError>> {
+        let spendable = AccountManager::get_spendable_amount(origin, conn).await?;
+
+        if spendable.amount_minor < amount {
+            let available = Money::new(spendable.amount_minor.max(0), spendable.currency);
+            return Err(Box::new(TransactionError::new(
+                format!("Insufficient funds, available amount is {}", available),
+                ErrorKind::InsufficientFunds {
+                    available,
+                    requested: Money::new(amount, spendable.currency),
+                },
+            )));
+        }
+
+        Ok(())
+    }
 
-        match result {
-            Ok(_) => {
-                return Ok(());
+    /// System accounts only move through the postings booked alongside customer movements
+    fn ensure_no_system_account(transaction: &Transaction) -> Result<(), Box<dyn BankError>> {
+        let involves_system_account = match transaction {
+            Transaction::Deposit { destination, .. } => vec![destination],
+            Transaction::Withdraw { origin, .. } => vec![origin],
+            Transaction::Transfer {
+                origin,
+                destination,
+                ..
             }
-            Err(_) => {
-                return Err(Box::new(TransactionError::new(
-                    "Error on transaction".to_string(),
-                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                )));
+            | Transaction::FxTransfer {
+                origin,
+                destination,
+                ..
+            } => vec![origin, destination],
+        }
+        .iter()
+        .any(|account| account.account_type == AccountType::System);
+
+        if involves_system_account {
+            return Err(Box::new(TransactionError::new(
+                "System accounts can't be used in transactions".to_string(),
+                ErrorKind::InvalidInput,
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Books the transaction in the caller's database transaction, `create_transaction` wraps
+    /// it in its own
+    pub(crate) async fn execute_transaction(
+        transaction: Transaction,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        TransactionManager::ensure_no_system_account(&transaction)?;
+
+        match transaction {
+            Transaction::FxTransfer {
+                quote_id,
+                origin,
+                destination,
+            } => {
+                println!(
+                    "FX transfer: quote_id={:?}, origin={:?}, destination={:?}",
+                    quote_id, origin, destination
+                );
+
+                if origin.currency() == destination.currency() {
+                    return Err(Box::new(TransactionError::new(
+                        format!(
+                            "Both accounts are in {}, there is nothing to convert",
+                            origin.currency()
+                        ),
+                        ErrorKind::InvalidInput,
+                    )));
+                }
+
+                AccountManager::lock_accounts(&[&origin, &destination], conn).await?;
+                TransactionManager::ensure_can_send(&origin, conn).await?;
+                TransactionManager::ensure_can_receive(&destination, conn).await?;
+                TransactionManager::ensure_withdraw_allowed(&origin, conn).await?;
+
+                let now = chrono::Utc::now();
+                let quote =
+                    fx::claim_quote(&quote_id, origin.currency, destination.currency, now, conn)
+                        .await?;
+
+                let fees =
+                    fee::assess_fees(FeeEvent::Transfer, quote.amount, &origin, conn).await?;
+                let debit = fee::add_fees(quote.amount, &fees)?;
+                TransactionManager::ensure_funds(debit, &origin, conn).await?;
+
+                let market_value = fx::get_market_value(&quote, now, conn).await?;
+
+                TransactionManager::create_fx_transfer(
+                    &quote,
+                    market_value,
+                    &origin,
+                    &destination,
+                    fees,
+                    conn,
+                )
+                .await
             }
+            transaction => TransactionManager::book_transaction(transaction, conn).await,
         }
     }
 
-    pub async fn create_transaction(
-        &self,
+    /// Books deposits, withdraws and transfers on the caller's unit of work, whatever the
+    /// storage. Conversions need the FX quotes only kept on Postgres, see `execute_transaction`.
+    pub(crate) async fn book_transaction<R: Repositories + ?Sized>(
         transaction: Transaction,
-    ) -> Result<(), Box<dyn BankError>> {
+        conn: &mut R,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        TransactionManager::ensure_no_system_account(&transaction)?;
+
         match transaction {
             Transaction::Deposit {
                 amount,
@@ -85,66 +490,24 @@ impl<'a> TransactionManager<'a> {
                     amount, destination
                 );
 
-                let mut conn = match self.db_pool.acquire().await {
-                    Ok(conn) => conn,
-                    Err(e) => {
-                        println!("Error getting connection: {}", e);
-                        return Err(Box::new(TransactionError::new(
-                            "An unexpected error happened, please try again".to_string(),
-                            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        )));
-                    }
-                };
-                return TransactionManager::create_deposit(amount, &destination, &mut conn).await;
+                AccountManager::lock_accounts(&[&destination], conn).await?;
+                TransactionManager::ensure_can_receive(&destination, conn).await?;
+
+                TransactionManager::create_deposit(amount, &destination, conn).await
             }
             Transaction::Withdraw { amount, origin } => {
                 println!("Withdraw: amount={:?}, origin={:?}", amount, origin);
 
-                // On a transaction, get the balance, if its enough, proceed with the transaction
-                // if not, return an error
-
-                let mut tx = match self.db_pool.begin().await {
-                    Ok(tx) => tx,
-                    Err(e) => {
-                        println!("Error starting database transaction: {}", e);
-                        return Err(Box::new(TransactionError::new(
-                            "An unexpected error happened, please try again".to_string(),
-                            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        )));
-                    }
-                };
-
-                let balance = AccountManager::get_balance(&origin, &mut tx).await?;
+                AccountManager::lock_accounts(&[&origin], conn).await?;
+                TransactionManager::ensure_can_send(&origin, conn).await?;
+                TransactionManager::ensure_withdraw_allowed(&origin, conn).await?;
 
-                if balance < amount.into() {
-                    if let Err(e) = tx.rollback().await {
-                        println!("Error rolling back transaction: {}", e);
-                    }
-                    return Err(Box::new(TransactionError::new(
-                        "Insufficient funds".to_string(),
-                        axum::http::StatusCode::BAD_REQUEST,
-                    )));
-                }
+                let fees =
+                    fee::assess_fees(FeeEvent::Withdraw, amount.into(), &origin, conn).await?;
+                let debit = fee::add_fees(amount.into(), &fees)?;
+                TransactionManager::ensure_funds(debit, &origin, conn).await?;
 
-                match TransactionManager::create_withdraw(amount, &origin, &mut tx).await {
-                    Ok(_) => {
-                        if let Err(err) = tx.commit().await {
-                            println!("Error committing transaction: {}", err);
-                            return Err(Box::new(TransactionError::new(
-                                "An unexpected error happened, please try again".to_string(),
-                                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                            )));
-                        }
-
-                        return Ok(());
-                    }
-                    Err(e) => {
-                        if let Err(e) = tx.rollback().await {
-                            println!("Error rolling back transaction: {}", e);
-                        }
-                        return Err(e);
-                    }
-                };
+                TransactionManager::create_withdraw(amount, &origin, fees, conn).await
             }
             Transaction::Transfer {
                 amount,
@@ -156,67 +519,335 @@ impl<'a> TransactionManager<'a> {
                     amount, origin, destination
                 );
 
-                let mut tx = match self.db_pool.begin().await {
-                    Ok(tx) => tx,
-                    Err(e) => {
-                        println!("Error starting database transaction: {}", e);
-                        return Err(Box::new(TransactionError::new(
-                            "An unexpected error happened, please try again".to_string(),
-                            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        )));
-                    }
-                };
+                TransactionManager::ensure_same_currency(&origin, &destination)?;
 
-                let balance = AccountManager::get_balance(&origin, &mut tx).await?;
+                AccountManager::lock_accounts(&[&origin, &destination], conn).await?;
+                TransactionManager::ensure_can_send(&origin, conn).await?;
+                TransactionManager::ensure_can_receive(&destination, conn).await?;
+                TransactionManager::ensure_withdraw_allowed(&origin, conn).await?;
 
-                if balance < amount.into() {
-                    if let Err(e) = tx.rollback().await {
-                        println!("Error rolling back transaction: {}", e);
-                    }
-                    return Err(Box::new(TransactionError::new(
-                        "Insufficient funds".to_string(),
-                        axum::http::StatusCode::BAD_REQUEST,
-                    )));
+                let fees =
+                    fee::assess_fees(FeeEvent::Transfer, amount.into(), &origin, conn).await?;
+                let debit = fee::add_fees(amount.into(), &fees)?;
+                TransactionManager::ensure_funds(debit, &origin, conn).await?;
+
+                TransactionManager::create_transfer(amount, &origin, &destination, fees, conn).await
+            }
+            Transaction::FxTransfer { .. } => Err(Box::new(TransactionError::new(
+                "FX transfers are not available on this storage".to_string(),
+                ErrorKind::Unsupported,
+            ))),
+        }
+    }
+
+    pub async fn create_transaction(
+        &self,
+        transaction: Transaction,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        let mut tx = match self.db_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                return Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )));
+            }
+        };
+
+        let receipt = match TransactionManager::execute_transaction(transaction, &mut tx).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                if let Err(e) = tx.rollback().await {
+                    println!("Error rolling back transaction: {}", e);
                 }
+                return Err(e);
+            }
+        };
 
-                let withdraw_result =
-                    TransactionManager::create_withdraw(amount, &origin, &mut tx).await;
-                let deposit_result =
-                    TransactionManager::create_deposit(amount, &destination, &mut tx).await;
-
-                match (withdraw_result, deposit_result) {
-                    (Ok(_), Ok(_)) => {
-                        match tx.commit().await {
-                            Ok(_) => {}
-                            Err(e) => {
-                                println!("Error committing transaction: {}", e);
-                                return Err(Box::new(TransactionError::new(
-                                    "An unexpected error happened, please try again".to_string(),
-                                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                                )));
-                            }
-                        }
-                        return Ok(());
-                    }
-                    _ => {
-                        if let Err(e) = tx.rollback().await {
-                            println!("Error rolling back transaction: {}", e);
-                        }
-                        return Err(Box::new(TransactionError::new(
-                            "Error on transaction".to_string(),
-                            axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        )));
-                    }
+        if let Err(e) = tx.commit().await {
+            println!("Error committing transaction: {}", e);
+            return Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )));
+        }
+
+        Ok(receipt)
+    }
+
+    /// Same as `create_transaction`, but a retry with the same key returns the receipt of the
+    /// first successful call instead of booking the transaction again.
+    ///
+    /// Reusing a key with a different transaction fails with 422 until the key expires. Failed
+    /// transactions don't consume the key, so they can be retried.
+    pub async fn create_idempotent_transaction(
+        &self,
+        transaction: Transaction,
+        idempotency_key: &str,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        idempotency::validate_key(idempotency_key)?;
+        let fingerprint = idempotency::fingerprint(&transaction)?;
+
+        let mut tx = match self.db_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                return Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )));
+            }
+        };
+
+        let claim = idempotency::claim_key(
+            idempotency_key,
+            &fingerprint,
+            self.idempotency_key_ttl,
+            &mut tx,
+        )
+        .await;
+
+        let result = match claim {
+            Ok(IdempotencyClaim::Replay(receipt)) => {
+                if let Err(e) = tx.rollback().await {
+                    println!("Error rolling back transaction: {}", e);
+                }
+                return Ok(receipt);
+            }
+            Ok(IdempotencyClaim::Claimed) => {
+                match TransactionManager::execute_transaction(transaction, &mut tx).await {
+                    Ok(receipt) => idempotency::store_response(idempotency_key, &receipt, &mut tx)
+                        .await
+                        .map(|_| receipt),
+                    Err(e) => Err(e),
+                }
+            }
+            Err(e) => Err(e),
+        };
+
+        let receipt = match result {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                if let Err(e) = tx.rollback().await {
+                    println!("Error rolling back transaction: {}", e);
+                }
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = tx.commit().await {
+            println!("Error committing transaction: {}", e);
+            return Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )));
+        }
+
+        Ok(receipt)
+    }
+
+    /// Reverses whatever was not reversed yet of a transaction, identified by its journal entry.
+    ///
+    /// The original postings are never changed, a compensating entry is booked instead, and the
+    /// accounts being debited by it must have the funds to cover it.
+    pub async fn reverse(
+        &self,
+        transaction_id: &Uuid,
+        reason: &str,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        self.book_reversal(transaction_id, None, reason).await
+    }
+
+    /// Gives back part of a transfer, can be repeated until the whole amount was refunded
+    pub async fn refund(
+        &self,
+        transaction_id: &Uuid,
+        amount: Amount,
+        reason: &str,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        self.book_reversal(transaction_id, Some(amount), reason)
+            .await
+    }
+
+    async fn book_reversal(
+        &self,
+        transaction_id: &Uuid,
+        amount: Option<Amount>,
+        reason: &str,
+    ) -> Result<TransactionReceipt, Box<dyn BankError>> {
+        println!(
+            "Reversal: transaction_id={:?}, amount={:?}, reason={:?}",
+            transaction_id, amount, reason
+        );
+
+        let mut tx = match self.db_pool.begin().await {
+            Ok(tx) => tx,
+            Err(e) => {
+                println!("Error starting database transaction: {}", e);
+                return Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )));
+            }
+        };
+
+        let receipt = match reversal::book_reversal(transaction_id, amount, reason, &mut tx).await {
+            Ok(receipt) => receipt,
+            Err(e) => {
+                if let Err(e) = tx.rollback().await {
+                    println!("Error rolling back transaction: {}", e);
+                }
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = tx.commit().await {
+            println!("Error committing transaction: {}", e);
+            return Err(Box::new(TransactionError::new(
+                "An unexpected error happened, please try again".to_string(),
+                ErrorKind::Internal,
+            )));
+        }
+
+        Ok(receipt)
+    }
+
+    /// Lists the account history ordered by `created_at` then `id`, starting after `cursor`.
+    ///
+    /// The running balance of each entry is computed over the whole history, so it is not
+    /// affected by the filter.
+    pub async fn list_transactions(
+        &self,
+        account: &Account,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+    ) -> Result<TransactionPage, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error getting connection: {}", e);
+                return Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )));
+            }
+        };
+
+        TransactionManager::list_account_transactions(account, filter, cursor, &mut *conn).await
+    }
+
+    /// Same as `list_transactions`, on the caller's unit of work
+    pub(crate) async fn list_account_transactions<R: LedgerRepository + ?Sized>(
+        account: &Account,
+        filter: &TransactionFilter,
+        cursor: Option<&TransactionCursor>,
+        conn: &mut R,
+    ) -> Result<TransactionPage, Box<dyn BankError>> {
+        let limit = filter
+            .limit
+            .unwrap_or(DEFAULT_HISTORY_PAGE_SIZE)
+            .clamp(1, MAX_HISTORY_PAGE_SIZE);
+
+        // Fetches one extra entry to know if there is a next page
+        let mut entries = conn
+            .list_transactions(account.id(), filter, cursor, limit + 1)
+            .await?;
+
+        let has_next_page = entries.len() as i64 > limit;
+        entries.truncate(limit as usize);
+
+        let next_cursor = match entries.last() {
+            Some(last) if has_next_page => Some(
+                TransactionCursor {
+                    created_at: last.created_at,
+                    id: last.id,
                 }
+                .to_string(),
+            ),
+            _ => None,
+        };
+
+        Ok(TransactionPage {
+            entries,
+            next_cursor,
+        })
+    }
+
+    /// Currency the customer side of a transaction, identified by its journal entry, was booked in.
+    /// Reversal amounts are measured in it, so for a conversion it is the one of the destination.
+    pub async fn get_transaction_currency(
+        &self,
+        transaction_id: &Uuid,
+    ) -> Result<Currency, Box<dyn BankError>> {
+        let currency = sqlx::query!(
+            r#"SELECT account.currency AS "currency: Currency" FROM transaction
+            JOIN account ON account.id = transaction.account_id
+            WHERE transaction.journal_entry_id = $1 AND account.type <> 'system'
+            ORDER BY transaction.type = 'transfer_in' DESC, transaction.id
+            LIMIT 1"#,
+            transaction_id
+        )
+        .fetch_optional(self.db_pool)
+        .await;
+
+        match currency {
+            Ok(Some(row)) => Ok(row.currency),
+            Ok(None) => Err(Box::new(TransactionError::new(
+                format!("Transaction [{}] not found", transaction_id),
+                ErrorKind::NotFound,
+            ))),
+            Err(e) => {
+                println!("Error getting transaction currency: {}", e);
+                Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )))
+            }
+        }
+    }
+
+    /// Debits and credits of every account, balanced books have both totals equal and no
+    /// journal entry that doesn't sum to zero
+    pub async fn trial_balance(&self) -> Result<TrialBalance, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error getting connection: {}", e);
+                return Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )));
+            }
+        };
+
+        ledger::trial_balance(&mut conn).await
+    }
+
+    /// Deletes the idempotency keys that already expired, returning how many were removed
+    pub async fn purge_expired_idempotency_keys(&self) -> Result<u64, Box<dyn BankError>> {
+        let mut conn = match self.db_pool.acquire().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                println!("Error getting connection: {}", e);
+                return Err(Box::new(TransactionError::new(
+                    "An unexpected error happened, please try again".to_string(),
+                    ErrorKind::Internal,
+                )));
             }
         };
+
+        idempotency::delete_expired_keys(&mut conn).await
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::internal::test_util::get_conn_with_new_db;
+    use crate::internal::account::domain::{AccountLimits, AccountType};
+    use crate::internal::test_util::{
+        create_customer, create_funded_account, get_conn_with_new_db,
+    };
     use crate::internal::transaction::domain::Transaction;
 
     #[tokio::test]
@@ -225,12 +856,18 @@ mod tests {
 
         let transaction_manager = TransactionManager::new(database.get_pool());
 
-        let account_manager = AccountManager::new(&database.get_pool());
+        let account_manager = AccountManager::new(database.get_pool());
 
-        let account = account_manager.create_account().await.unwrap();
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let transaction = Transaction::Deposit {
-            amount: 100,
+            amount: Amount::new(100).unwrap(),
             destination: account.clone(),
         };
 
@@ -238,11 +875,12 @@ mod tests {
         assert!(result.is_ok());
 
         let mut conn = database.get_pool().acquire().await.unwrap();
-        let balance = AccountManager::get_balance(&account, &mut conn)
+        let balance = AccountManager::get_balance(&account, &mut *conn)
             .await
-            .unwrap();
+            .unwrap()
+            .amount_minor;
 
-        assert_eq!(balance, 100.into());
+        assert_eq!(balance, 100);
     }
 
     #[tokio::test]
@@ -253,10 +891,16 @@ mod tests {
 
         let account_manager = AccountManager::new(db_pool);
 
-        let account = account_manager.create_account().await.unwrap();
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let transaction = Transaction::Deposit {
-            amount: 100,
+            amount: Amount::new(100).unwrap(),
             destination: account.clone(),
         };
 
@@ -265,7 +909,7 @@ mod tests {
         assert!(result.is_ok());
 
         let transaction = Transaction::Withdraw {
-            amount: 50,
+            amount: Amount::new(50).unwrap(),
             origin: account.clone(),
         };
 
@@ -273,14 +917,15 @@ mod tests {
 
         assert!(result.is_ok());
 
-        let balance = AccountManager::get_balance(&account, &mut db_pool.acquire().await.unwrap())
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
             .await
-            .unwrap();
+            .unwrap()
+            .amount_minor;
 
-        assert_eq!(balance, 50.into());
+        assert_eq!(balance, 50);
 
         let transaction = Transaction::Withdraw {
-            amount: 25,
+            amount: Amount::new(25).unwrap(),
             origin: account.clone(),
         };
 
@@ -288,11 +933,12 @@ mod tests {
 
         assert!(result.is_ok());
 
-        let balance = AccountManager::get_balance(&account, &mut db_pool.acquire().await.unwrap())
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
             .await
-            .unwrap();
+            .unwrap()
+            .amount_minor;
 
-        assert_eq!(balance, 25.into());
+        assert_eq!(balance, 25);
     }
 
     #[tokio::test]
@@ -304,10 +950,16 @@ mod tests {
 
         let account_manager = AccountManager::new(db_pool);
 
-        let account = account_manager.create_account().await.unwrap();
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let transaction = Transaction::Deposit {
-            amount: 100,
+            amount: Amount::new(100).unwrap(),
             destination: account.clone(),
         };
 
@@ -316,7 +968,7 @@ mod tests {
         assert!(result.is_ok());
 
         let transaction = Transaction::Withdraw {
-            amount: 150,
+            amount: Amount::new(150).unwrap(),
             origin: account,
         };
 
@@ -334,11 +986,23 @@ mod tests {
 
         let account_manager = AccountManager::new(db_pool);
 
-        let account_origin = account_manager.create_account().await.unwrap();
-        let account_destination = account_manager.create_account().await.unwrap();
+        let account_origin = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let account_destination = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
 
         let transaction = Transaction::Deposit {
-            amount: 100,
+            amount: Amount::new(100).unwrap(),
             destination: account_origin.clone(),
         };
 
@@ -347,7 +1011,7 @@ mod tests {
         assert!(result.is_ok());
 
         let transaction = Transaction::Transfer {
-            amount: 25,
+            amount: Amount::new(25).unwrap(),
             origin: account_origin.clone(),
             destination: account_destination.clone(),
         };
@@ -357,18 +1021,1711 @@ mod tests {
         assert!(result.is_ok());
 
         let balance_origin =
-            AccountManager::get_balance(&account_origin, &mut db_pool.acquire().await.unwrap())
+            AccountManager::get_balance(&account_origin, &mut *db_pool.acquire().await.unwrap())
                 .await
-                .unwrap();
+                .unwrap()
+                .amount_minor;
 
         let balance_destination = AccountManager::get_balance(
             &account_destination,
-            &mut db_pool.acquire().await.unwrap(),
+            &mut *db_pool.acquire().await.unwrap(),
+        )
+        .await
+        .unwrap()
+        .amount_minor;
+
+        assert_eq!(balance_origin, 75);
+        assert_eq!(balance_destination, 25);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_withdraws_never_overdraw() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = AccountManager::new(db_pool);
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let mut handles = Vec::new();
+        for _ in 0..300 {
+            let db_pool = db_pool.clone();
+            let account = account.clone();
+            handles.push(tokio::spawn(async move {
+                TransactionManager::new(&db_pool)
+                    .create_transaction(Transaction::Withdraw {
+                        amount: Amount::new(1).unwrap(),
+                        origin: account,
+                    })
+                    .await
+            }));
+        }
+
+        let mut succeeded = 0;
+        for handle in handles {
+            match handle.await.unwrap() {
+                Ok(_) => succeeded += 1,
+                Err(e) => assert!(e.message().starts_with("Insufficient funds")),
+            }
+        }
+
+        assert_eq!(succeeded, 100);
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 0);
+
+        let lowest_balance = sqlx::query!(
+            "SELECT MIN(running) AS lowest FROM (SELECT SUM(amount) OVER (ORDER BY id) AS running FROM transaction WHERE account_id = $1) AS history",
+            account.id()
         )
+        .fetch_one(db_pool)
         .await
         .unwrap();
 
-        assert_eq!(balance_origin, 75.into());
-        assert_eq!(balance_destination, 25.into());
+        assert!(lowest_balance.lowest.unwrap() >= 0.into());
+    }
+
+    #[tokio::test]
+    async fn test_overdraft_limit_boundaries() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let destination = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        account_manager
+            .update_limits(
+                &account,
+                &AccountLimits {
+                    overdraft_limit: 50,
+                },
+            )
+            .await
+            .unwrap();
+
+        let result = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(151).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "Insufficient funds, available amount is 1.50 BRL"
+        );
+
+        transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(120).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        // Transfers use the overdraft the same way withdraws do
+        transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(30).unwrap(),
+                origin: account.clone(),
+                destination: destination.clone(),
+            })
+            .await
+            .unwrap();
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, -50);
+
+        let result = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(1).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "Insufficient funds, available amount is 0.00 BRL"
+        );
+
+        // Lowering the limit below what is in use doesn't give a negative available amount
+        account_manager
+            .update_limits(
+                &account,
+                &AccountLimits {
+                    overdraft_limit: 10,
+                },
+            )
+            .await
+            .unwrap();
+
+        let result = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(1).unwrap(),
+                origin: account,
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "Insufficient funds, available amount is 0.00 BRL"
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_withdraws_within_overdraft() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = AccountManager::new(db_pool);
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        account_manager
+            .update_limits(
+                &account,
+                &AccountLimits {
+                    overdraft_limit: 50,
+                },
+            )
+            .await
+            .unwrap();
+
+        let mut handles = Vec::new();
+        for _ in 0..300 {
+            let db_pool = db_pool.clone();
+            let account = account.clone();
+            handles.push(tokio::spawn(async move {
+                TransactionManager::new(&db_pool)
+                    .create_transaction(Transaction::Withdraw {
+                        amount: Amount::new(1).unwrap(),
+                        origin: account,
+                    })
+                    .await
+            }));
+        }
+
+        let mut succeeded = 0;
+        for handle in handles {
+            match handle.await.unwrap() {
+                Ok(_) => succeeded += 1,
+                Err(e) => assert_eq!(
+                    e.message(),
+                    "Insufficient funds, available amount is 0.00 BRL"
+                ),
+            }
+        }
+
+        assert_eq!(succeeded, 150);
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, -50);
+
+        let lowest_balance = sqlx::query!(
+            "SELECT MIN(running) AS lowest FROM (SELECT SUM(amount) OVER (ORDER BY id) AS running FROM transaction WHERE account_id = $1) AS history",
+            account.id()
+        )
+        .fetch_one(db_pool)
+        .await
+        .unwrap();
+
+        assert!(lowest_balance.lowest.unwrap() >= (-50).into());
+    }
+
+    #[tokio::test]
+    async fn test_frozen_account_only_receives() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let other = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        account_manager
+            .freeze(&account, "Court order")
+            .await
+            .unwrap();
+
+        // The account read before freezing still says active, the status is checked again
+        let result = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(10).unwrap(),
+                origin: account.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!("Account [{}] is frozen", account.number())
+        );
+
+        let result = transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(10).unwrap(),
+                origin: account.clone(),
+                destination: other.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!("Account [{}] is frozen", account.number())
+        );
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(10).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(10).unwrap(),
+                destination: other.clone(),
+            })
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(10).unwrap(),
+                origin: other,
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 120);
+    }
+
+    #[tokio::test]
+    async fn test_closed_account_rejects_everything() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let other = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: other.clone(),
+            })
+            .await
+            .unwrap();
+
+        account_manager
+            .close(&account, None, "Customer request")
+            .await
+            .unwrap();
+
+        let transactions = [
+            Transaction::Deposit {
+                amount: Amount::new(10).unwrap(),
+                destination: account.clone(),
+            },
+            Transaction::Withdraw {
+                amount: Amount::new(10).unwrap(),
+                origin: account.clone(),
+            },
+            Transaction::Transfer {
+                amount: Amount::new(10).unwrap(),
+                origin: other.clone(),
+                destination: account.clone(),
+            },
+        ];
+
+        for transaction in transactions {
+            let result = transaction_manager.create_transaction(transaction).await;
+
+            assert_eq!(
+                result.unwrap_err().message(),
+                format!("Account [{}] is closed", account.number())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_savings_monthly_withdraw_limit() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+
+        let savings = account_manager
+            .create_account(AccountType::Savings, &[create_customer(db_pool).await])
+            .await
+            .unwrap();
+        let checking = account_manager
+            .create_account(AccountType::Checking, &[create_customer(db_pool).await])
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: savings.clone(),
+            })
+            .await
+            .unwrap();
+
+        // Transfers out count towards the limit as well
+        for _ in 0..SAVINGS_MONTHLY_WITHDRAW_LIMIT / 2 {
+            transaction_manager
+                .create_transaction(Transaction::Withdraw {
+                    amount: Amount::new(1).unwrap(),
+                    origin: savings.clone(),
+                })
+                .await
+                .unwrap();
+            transaction_manager
+                .create_transaction(Transaction::Transfer {
+                    amount: Amount::new(1).unwrap(),
+                    origin: savings.clone(),
+                    destination: checking.clone(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(1).unwrap(),
+                origin: savings.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            format!(
+                "Savings account [{}] reached the limit of 6 withdraws this month",
+                savings.number()
+            )
+        );
+
+        // Deposits and incoming transfers are not limited
+        transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(1).unwrap(),
+                origin: checking,
+                destination: savings.clone(),
+            })
+            .await
+            .unwrap();
+
+        let balance = AccountManager::get_balance(&savings, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 95);
+    }
+
+    #[tokio::test]
+    async fn test_system_accounts_are_not_transactable() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let cash_vault = AccountManager::get_system_account(
+            SystemAccount::CashVault,
+            Currency::Brl,
+            &mut *db_pool.acquire().await.unwrap(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(cash_vault.account_type, AccountType::System);
+
+        let result = TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(1).unwrap(),
+                origin: cash_vault,
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "System accounts can't be used in transactions"
+        );
+
+        let result = AccountManager::new(db_pool)
+            .create_account(AccountType::System, &[create_customer(db_pool).await])
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_accounts_in_different_currencies() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = AccountManager::new(db_pool);
+        let transaction_manager = TransactionManager::new(db_pool);
+
+        let reais = account_manager
+            .create_account(AccountType::Checking, &[create_customer(db_pool).await])
+            .await
+            .unwrap();
+        let dollars = account_manager
+            .create_account_with_currency(
+                AccountType::Checking,
+                Currency::Usd,
+                &[create_customer(db_pool).await],
+            )
+            .await
+            .unwrap();
+        let more_dollars = account_manager
+            .create_account_with_currency(
+                AccountType::Checking,
+                Currency::Usd,
+                &[create_customer(db_pool).await],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(reais.currency(), &Currency::Brl);
+        assert_eq!(dollars.currency(), &Currency::Usd);
+
+        for account in [&reais, &dollars] {
+            transaction_manager
+                .create_transaction(Transaction::Deposit {
+                    amount: Amount::new(1000).unwrap(),
+                    destination: account.clone(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let result = transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(100).unwrap(),
+                origin: dollars.clone(),
+                destination: reais.clone(),
+            })
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "Can't transfer from a USD account to a BRL account without a conversion"
+        );
+
+        transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(100).unwrap(),
+                origin: dollars.clone(),
+                destination: more_dollars.clone(),
+            })
+            .await
+            .unwrap();
+
+        // Each currency is balanced against its own cash vault
+        let mut conn = db_pool.acquire().await.unwrap();
+        for (currency, expected) in [(Currency::Brl, -1000), (Currency::Usd, -1000)] {
+            let cash_vault =
+                AccountManager::get_system_account(SystemAccount::CashVault, currency, &mut *conn)
+                    .await
+                    .unwrap();
+
+            assert_eq!(
+                AccountManager::get_balance(&cash_vault, &mut *conn)
+                    .await
+                    .unwrap()
+                    .amount_minor,
+                expected
+            );
+        }
+
+        let result = account_manager
+            .close(&dollars, Some(&reais), "Moving back home")
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_transfers_in_both_directions() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_manager = AccountManager::new(db_pool);
+        let account_a = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let account_b = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        for account in [&account_a, &account_b] {
+            transaction_manager
+                .create_transaction(Transaction::Deposit {
+                    amount: Amount::new(50).unwrap(),
+                    destination: account.clone(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let mut handles = Vec::new();
+        for i in 0..300 {
+            let db_pool = db_pool.clone();
+            let (origin, destination) = if i % 2 == 0 {
+                (account_a.clone(), account_b.clone())
+            } else {
+                (account_b.clone(), account_a.clone())
+            };
+            handles.push(tokio::spawn(async move {
+                let transaction = if i % 3 == 0 {
+                    Transaction::Withdraw {
+                        amount: Amount::new(1).unwrap(),
+                        origin,
+                    }
+                } else {
+                    Transaction::Transfer {
+                        amount: Amount::new(7).unwrap(),
+                        origin,
+                        destination,
+                    }
+                };
+
+                TransactionManager::new(&db_pool)
+                    .create_transaction(transaction)
+                    .await
+            }));
+        }
+
+        for handle in handles {
+            // Deadlocks or serialization errors would surface as anything else than this
+            if let Err(e) = handle.await.unwrap() {
+                assert!(e.message().starts_with("Insufficient funds"));
+            }
+        }
+
+        let withdrawn = sqlx::query!(
+            "SELECT COUNT(*) FROM transaction WHERE type = 'withdraw' AND amount = -1"
+        )
+        .fetch_one(db_pool)
+        .await
+        .unwrap()
+        .count
+        .unwrap();
+
+        let mut total = sqlx::types::BigDecimal::from(0);
+        for account in [&account_a, &account_b] {
+            let balance =
+                AccountManager::get_balance(account, &mut *db_pool.acquire().await.unwrap())
+                    .await
+                    .unwrap()
+                    .amount_minor;
+            assert!(balance >= 0.into());
+
+            let lowest_balance = sqlx::query!(
+                "SELECT MIN(running) AS lowest FROM (SELECT SUM(amount) OVER (ORDER BY id) AS running FROM transaction WHERE account_id = $1) AS history",
+                account.id()
+            )
+            .fetch_one(db_pool)
+            .await
+            .unwrap();
+            assert!(lowest_balance.lowest.unwrap() >= 0.into());
+
+            total += balance;
+        }
+
+        assert_eq!(total, sqlx::types::BigDecimal::from(100 - withdrawn));
+        assert!(account_manager
+            .find_balance_drifts()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_transaction_replay() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let first = transaction_manager
+            .create_idempotent_transaction(
+                Transaction::Deposit {
+                    amount: Amount::new(100).unwrap(),
+                    destination: account.clone(),
+                },
+                "deposit-1",
+            )
+            .await
+            .unwrap();
+
+        let replay = transaction_manager
+            .create_idempotent_transaction(
+                Transaction::Deposit {
+                    amount: Amount::new(100).unwrap(),
+                    destination: account.clone(),
+                },
+                "deposit-1",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(first, replay);
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 100);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_transaction_with_different_payload() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_idempotent_transaction(
+                Transaction::Deposit {
+                    amount: Amount::new(100).unwrap(),
+                    destination: account.clone(),
+                },
+                "deposit-1",
+            )
+            .await
+            .unwrap();
+
+        let result = transaction_manager
+            .create_idempotent_transaction(
+                Transaction::Deposit {
+                    amount: Amount::new(50).unwrap(),
+                    destination: account.clone(),
+                },
+                "deposit-1",
+            )
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::Unprocessable);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_transaction_replay_after_account_change() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+        let account = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let first = transaction_manager
+            .create_idempotent_transaction(
+                Transaction::Deposit {
+                    amount: Amount::new(100).unwrap(),
+                    destination: account.clone(),
+                },
+                "deposit-1",
+            )
+            .await
+            .unwrap();
+
+        account_manager.freeze(&account, "Review").await.unwrap();
+        let frozen = account_manager
+            .get_account_from_number(*account.number())
+            .await
+            .unwrap();
+
+        let replay = transaction_manager
+            .create_idempotent_transaction(
+                Transaction::Deposit {
+                    amount: Amount::new(100).unwrap(),
+                    destination: frozen,
+                },
+                "deposit-1",
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(first, replay);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_transaction_failure_does_not_consume_key() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let withdraw = || Transaction::Withdraw {
+            amount: Amount::new(30).unwrap(),
+            origin: account.clone(),
+        };
+
+        let result = transaction_manager
+            .create_idempotent_transaction(withdraw(), "withdraw-1")
+            .await;
+
+        assert!(result.is_err());
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let result = transaction_manager
+            .create_idempotent_transaction(withdraw(), "withdraw-1")
+            .await;
+
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_transaction_expired_key() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager =
+            TransactionManager::new(db_pool).with_idempotency_key_ttl(chrono::Duration::zero());
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        for amount in [100, 50] {
+            transaction_manager
+                .create_idempotent_transaction(
+                    Transaction::Deposit {
+                        amount: Amount::new(amount).unwrap(),
+                        destination: account.clone(),
+                    },
+                    "deposit-1",
+                )
+                .await
+                .unwrap();
+        }
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 150);
+
+        let purged = transaction_manager
+            .purge_expired_idempotency_keys()
+            .await
+            .unwrap();
+
+        assert_eq!(purged, 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 8)]
+    async fn test_concurrent_idempotent_transactions() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let mut handles = Vec::new();
+        for _ in 0..50 {
+            let db_pool = db_pool.clone();
+            let account = account.clone();
+            handles.push(tokio::spawn(async move {
+                TransactionManager::new(&db_pool)
+                    .create_idempotent_transaction(
+                        Transaction::Deposit {
+                            amount: Amount::new(10).unwrap(),
+                            destination: account,
+                        },
+                        "retried-deposit",
+                    )
+                    .await
+            }));
+        }
+
+        let mut receipts = Vec::new();
+        for handle in handles {
+            receipts.push(handle.await.unwrap().unwrap());
+        }
+
+        assert!(receipts.iter().all(|receipt| receipt == &receipts[0]));
+
+        let balance = AccountManager::get_balance(&account, &mut *db_pool.acquire().await.unwrap())
+            .await
+            .unwrap()
+            .amount_minor;
+
+        assert_eq!(balance, 10);
+    }
+
+    async fn create_history(
+        transaction_manager: &TransactionManager<'_>,
+        account: &crate::internal::account::domain::Account,
+    ) {
+        for transaction in [
+            Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            },
+            Transaction::Withdraw {
+                amount: Amount::new(30).unwrap(),
+                origin: account.clone(),
+            },
+            Transaction::Deposit {
+                amount: Amount::new(5).unwrap(),
+                destination: account.clone(),
+            },
+            Transaction::Withdraw {
+                amount: Amount::new(70).unwrap(),
+                origin: account.clone(),
+            },
+        ] {
+            transaction_manager
+                .create_transaction(transaction)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_with_running_balance() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        create_history(&transaction_manager, &account).await;
+
+        let page = transaction_manager
+            .list_transactions(&account, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+
+        let amounts: Vec<i64> = page.entries.iter().map(|entry| entry.amount).collect();
+        let balances: Vec<_> = page
+            .entries
+            .iter()
+            .map(|entry| entry.balance.clone())
+            .collect();
+
+        assert_eq!(amounts, vec![100, -30, 5, -70]);
+        assert_eq!(balances, vec![100.into(), 70.into(), 75.into(), 5.into()]);
+        assert!(page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_filters() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        create_history(&transaction_manager, &account).await;
+
+        let withdraws = transaction_manager
+            .list_transactions(
+                &account,
+                &TransactionFilter {
+                    types: Some(vec![TransactionType::Withdraw]),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let amounts: Vec<i64> = withdraws.entries.iter().map(|entry| entry.amount).collect();
+        assert_eq!(amounts, vec![-30, -70]);
+        // The running balance still accounts for the entries filtered out
+        assert_eq!(withdraws.entries[1].balance, 5.into());
+
+        let by_amount = transaction_manager
+            .list_transactions(
+                &account,
+                &TransactionFilter {
+                    min_amount: Some(30),
+                    max_amount: Some(70),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let amounts: Vec<i64> = by_amount.entries.iter().map(|entry| entry.amount).collect();
+        assert_eq!(amounts, vec![-30, -70]);
+
+        let in_the_future = transaction_manager
+            .list_transactions(
+                &account,
+                &TransactionFilter {
+                    from: Some(chrono::Utc::now() + chrono::Duration::hours(1)),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(in_the_future.entries.is_empty());
+
+        let until_now = transaction_manager
+            .list_transactions(
+                &account,
+                &TransactionFilter {
+                    to: Some(chrono::Utc::now()),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(until_now.entries.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_list_transactions_pagination() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        create_history(&transaction_manager, &account).await;
+
+        let filter = TransactionFilter {
+            limit: Some(3),
+            ..Default::default()
+        };
+
+        let first_page = transaction_manager
+            .list_transactions(&account, &filter, None)
+            .await
+            .unwrap();
+
+        assert_eq!(first_page.entries.len(), 3);
+
+        let cursor: TransactionCursor = first_page.next_cursor.unwrap().parse().unwrap();
+
+        let second_page = transaction_manager
+            .list_transactions(&account, &filter, Some(&cursor))
+            .await
+            .unwrap();
+
+        assert_eq!(second_page.entries.len(), 1);
+        assert_eq!(second_page.entries[0].amount, -70);
+        assert_eq!(second_page.entries[0].balance, 5.into());
+        assert!(second_page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transfer_legs_are_linked() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+        let origin = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let destination = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: origin.clone(),
+            })
+            .await
+            .unwrap();
+
+        let receipt = transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(40).unwrap(),
+                origin: origin.clone(),
+                destination: destination.clone(),
+            })
+            .await
+            .unwrap();
+
+        let transfer_id = receipt.transfer_id.unwrap();
+
+        let origin_history = transaction_manager
+            .list_transactions(
+                &origin,
+                &TransactionFilter {
+                    types: Some(vec![TransactionType::TransferOut]),
+                    ..Default::default()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let destination_history = transaction_manager
+            .list_transactions(&destination, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+
+        assert_eq!(origin_history.entries.len(), 1);
+        assert_eq!(destination_history.entries.len(), 1);
+
+        let transfer_out = &origin_history.entries[0];
+        let transfer_in = &destination_history.entries[0];
+
+        assert_eq!(transfer_out.amount, -40);
+        assert_eq!(transfer_out.transfer_id, Some(transfer_id));
+        assert_eq!(
+            transfer_out.counterparty_account_number,
+            Some(*destination.number())
+        );
+
+        assert_eq!(transfer_in.amount, 40);
+        assert_eq!(transfer_in.transaction_type, TransactionType::TransferIn);
+        assert_eq!(transfer_in.transfer_id, Some(transfer_id));
+        assert_eq!(
+            transfer_in.counterparty_account_number,
+            Some(*origin.number())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transfer_leg_requires_link() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let journal_entry_id = Uuid::now_v7();
+        sqlx::query!(
+            "INSERT INTO journal_entry (id, description) VALUES ($1, 'transfer')",
+            journal_entry_id
+        )
+        .execute(db_pool)
+        .await
+        .unwrap();
+
+        let result = sqlx::query!(
+            "INSERT INTO transaction (account_id, amount, type, journal_entry_id) VALUES ($1, $2, 'transfer_in', $3)",
+            account.id(),
+            10_i64,
+            journal_entry_id
+        )
+        .execute(db_pool)
+        .await;
+
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_books_are_balanced() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+        let account_a = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let account_b = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        create_history(&transaction_manager, &account_a).await;
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(20).unwrap(),
+                destination: account_a.clone(),
+            })
+            .await
+            .unwrap();
+        transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(15).unwrap(),
+                origin: account_a.clone(),
+                destination: account_b.clone(),
+            })
+            .await
+            .unwrap();
+
+        let trial_balance = transaction_manager.trial_balance().await.unwrap();
+
+        assert!(trial_balance.is_balanced());
+        // Customer withdraws and transfer out, plus the deposits on the vault
+        assert_eq!(trial_balance.total_debits, (100 + 15 + 125).into());
+
+        let cash_vault = trial_balance
+            .lines
+            .iter()
+            .find(|line| {
+                line.system_code.as_deref() == Some("cash_vault") && line.currency == Currency::Brl
+            })
+            .unwrap();
+
+        // 125 deposited and 100 withdrawn
+        assert_eq!(cash_vault.debits, 125.into());
+        assert_eq!(cash_vault.credits, 100.into());
+
+        let vault_account = AccountManager::get_system_account(
+            SystemAccount::CashVault,
+            Currency::Brl,
+            &mut *db_pool.acquire().await.unwrap(),
+        )
+        .await
+        .unwrap();
+        let vault_balance =
+            AccountManager::get_balance(&vault_account, &mut *db_pool.acquire().await.unwrap())
+                .await
+                .unwrap()
+                .amount_minor;
+
+        assert_eq!(vault_balance, -25);
+        assert!(account_manager
+            .find_balance_drifts()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_movements_of_different_accounts_do_not_wait_on_the_cash_vault() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account_a =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+        let account_b =
+            create_funded_account(db_pool, AccountType::Checking, Currency::Brl, 0).await;
+
+        let mut first = db_pool.begin().await.unwrap();
+        TransactionManager::book_transaction(
+            Transaction::Deposit {
+                amount: Amount::new(10).unwrap(),
+                destination: account_a.clone(),
+            },
+            &mut *first,
+        )
+        .await
+        .unwrap();
+
+        // Both deposits credit the cash vault, the second one must not wait for the first
+        let mut second = db_pool.begin().await.unwrap();
+        sqlx::query("SET LOCAL lock_timeout = '1s'")
+            .execute(&mut *second)
+            .await
+            .unwrap();
+        TransactionManager::book_transaction(
+            Transaction::Deposit {
+                amount: Amount::new(20).unwrap(),
+                destination: account_b.clone(),
+            },
+            &mut *second,
+        )
+        .await
+        .unwrap();
+
+        second.commit().await.unwrap();
+        first.commit().await.unwrap();
+
+        let mut conn = db_pool.acquire().await.unwrap();
+        let cash_vault =
+            AccountManager::get_system_account(SystemAccount::CashVault, Currency::Brl, &mut *conn)
+                .await
+                .unwrap();
+
+        assert_eq!(
+            AccountManager::get_balance(&cash_vault, &mut *conn)
+                .await
+                .unwrap()
+                .amount_minor,
+            -30
+        );
+        assert!(AccountManager::new(db_pool)
+            .find_balance_drifts()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unbalanced_journal_entry_is_rejected() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let mut tx = db_pool.begin().await.unwrap();
+
+        let journal_entry_id = Uuid::now_v7();
+        sqlx::query!(
+            "INSERT INTO journal_entry (id, description) VALUES ($1, 'deposit')",
+            journal_entry_id
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        sqlx::query!(
+            "INSERT INTO transaction (account_id, amount, type, journal_entry_id) VALUES ($1, $2, 'deposit', $3)",
+            account.id(),
+            10_i64,
+            journal_entry_id
+        )
+        .execute(&mut *tx)
+        .await
+        .unwrap();
+
+        assert!(tx.commit().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_deposit() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let deposit = transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        transaction_manager
+            .reverse(&deposit.journal_entry_id, "Deposited on the wrong account")
+            .await
+            .unwrap();
+
+        let history = transaction_manager
+            .list_transactions(&account, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+
+        let amounts: Vec<i64> = history.entries.iter().map(|entry| entry.amount).collect();
+        assert_eq!(amounts, vec![100, -100]);
+        assert_eq!(
+            history.entries[1].transaction_type,
+            TransactionType::Reversal
+        );
+        assert_eq!(history.entries[1].balance, 0.into());
+
+        let result = transaction_manager
+            .reverse(&deposit.journal_entry_id, "Again")
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::Conflict);
+
+        assert!(transaction_manager
+            .trial_balance()
+            .await
+            .unwrap()
+            .is_balanced());
+    }
+
+    #[tokio::test]
+    async fn test_reverse_deposit_already_spent() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let deposit = transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Withdraw {
+                amount: Amount::new(60).unwrap(),
+                origin: account.clone(),
+            })
+            .await
+            .unwrap();
+
+        let result = transaction_manager
+            .reverse(&deposit.journal_entry_id, "Deposited on the wrong account")
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "Insufficient funds, available amount is 0.40 BRL"
+        );
+
+        let partial = transaction_manager
+            .refund(
+                &deposit.journal_entry_id,
+                Amount::new(40).unwrap(),
+                "Only what is left",
+            )
+            .await;
+
+        assert_eq!(partial.unwrap_err().kind(), &ErrorKind::InvalidInput);
+    }
+
+    #[tokio::test]
+    async fn test_partially_refund_transfer() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let transaction_manager = TransactionManager::new(db_pool);
+        let account_manager = AccountManager::new(db_pool);
+        let origin = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+        let destination = account_manager
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        transaction_manager
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: origin.clone(),
+            })
+            .await
+            .unwrap();
+
+        let transfer = transaction_manager
+            .create_transaction(Transaction::Transfer {
+                amount: Amount::new(80).unwrap(),
+                origin: origin.clone(),
+                destination: destination.clone(),
+            })
+            .await
+            .unwrap();
+
+        transaction_manager
+            .refund(
+                &transfer.journal_entry_id,
+                Amount::new(30).unwrap(),
+                "Returned one item",
+            )
+            .await
+            .unwrap();
+
+        let too_much = transaction_manager
+            .refund(
+                &transfer.journal_entry_id,
+                Amount::new(51).unwrap(),
+                "Returned everything",
+            )
+            .await;
+
+        assert_eq!(too_much.unwrap_err().kind(), &ErrorKind::InvalidInput);
+
+        // Reverses the 50 left
+        let reversal = transaction_manager
+            .reverse(&transfer.journal_entry_id, "Returned everything")
+            .await
+            .unwrap();
+
+        let balance = |account| {
+            let db_pool = db_pool.clone();
+            async move {
+                AccountManager::get_balance(account, &mut *db_pool.acquire().await.unwrap())
+                    .await
+                    .unwrap()
+                    .amount_minor
+            }
+        };
+
+        assert_eq!(balance(&origin).await, 100);
+        assert_eq!(balance(&destination).await, 0);
+
+        let destination_history = transaction_manager
+            .list_transactions(&destination, &TransactionFilter::default(), None)
+            .await
+            .unwrap();
+
+        let last = destination_history.entries.last().unwrap();
+        assert_eq!(last.amount, -50);
+        assert_eq!(last.counterparty_account_number, Some(*origin.number()));
+
+        let result = transaction_manager
+            .reverse(&reversal.journal_entry_id, "Undo the undo")
+            .await;
+
+        assert_eq!(
+            result.unwrap_err().message(),
+            "A reversal can't be reversed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reverse_unknown_transaction() {
+        let database = get_conn_with_new_db().await;
+
+        let result = TransactionManager::new(database.get_pool())
+            .reverse(&Uuid::now_v7(), "Typo")
+            .await;
+
+        assert_eq!(result.unwrap_err().kind(), &ErrorKind::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_postings_are_immutable() {
+        let database = get_conn_with_new_db().await;
+        let db_pool = database.get_pool();
+
+        let account = AccountManager::new(db_pool)
+            .create_account(
+                AccountType::Checking,
+                &[create_customer(database.get_pool()).await],
+            )
+            .await
+            .unwrap();
+
+        let deposit = TransactionManager::new(db_pool)
+            .create_transaction(Transaction::Deposit {
+                amount: Amount::new(100).unwrap(),
+                destination: account,
+            })
+            .await
+            .unwrap();
+
+        let update = sqlx::query!(
+            "UPDATE transaction SET amount = 1 WHERE id = $1",
+            deposit.transaction_ids[0]
+        )
+        .execute(db_pool)
+        .await;
+
+        assert!(update.is_err());
+
+        let delete = sqlx::query!(
+            "DELETE FROM transaction WHERE journal_entry_id = $1",
+            deposit.journal_entry_id
+        )
+        .execute(db_pool)
+        .await;
+
+        assert!(delete.is_err());
     }
 }